@@ -0,0 +1,2397 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::{stderr, stdin, stdout, BufWriter, Error, ErrorKind, IsTerminal, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bib2json_core::bcf::Control;
+use bib2json_core::cache::Cache;
+use bib2json_core::check::{self, Rules};
+use bib2json_core::cite::{self, Document};
+#[cfg(feature = "gzip")]
+use bib2json_core::compress;
+use bib2json_core::diff::{Diff, DiffFormat};
+use bib2json_core::duplicates::{self, MergeBy};
+use bib2json_core::edit::{self, Editor};
+#[cfg(feature = "enrich")]
+use bib2json_core::enrich::{Enricher, Service};
+use bib2json_core::escape::Escape;
+use bib2json_core::files::{self, Found};
+use bib2json_core::filter::Filter;
+use bib2json_core::graph::{self, Network, NetworkFormat};
+use bib2json_core::input::{self, InputFormat, Location, ReadError, Skipped};
+use bib2json_core::keygen::{self, Pattern};
+use bib2json_core::merge::{self, Conflict, Strategy};
+use bib2json_core::names::NameFormat;
+use bib2json_core::output::{self, Format, Layout, SplitBy};
+use bib2json_core::parallel::{self, Progress};
+use bib2json_core::pretty::{self, FieldOrder, Style};
+use bib2json_core::query::Query;
+use bib2json_core::sort::{self, Locale, SortBy};
+use bib2json_core::stats::Stats;
+use bib2json_core::{
+    convert, convert_unselected, fields, notes, Options, SRAEntry, PRIVATE_FIELDS,
+};
+use biblatex::Bibliography;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::parser::ValueSource;
+use clap::{
+    Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use clap_complete::Shell;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::kv::{self, Key, Value, VisitSource};
+use log::LevelFilter;
+use notify_debouncer_full::new_debouncer;
+use notify_debouncer_full::notify::{EventKind, RecursiveMode};
+use regex::Regex;
+
+/// Parse bibtex into JSON (using the Typst biblatex crate).
+#[derive(Parser, Debug)]
+#[command(
+    name = "bib2json",
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Without an input file, the bibliography is read from stdin, e.g., `curl … | bib2json`.\n\n\
+        Exit codes: 1 for problems found in the bibliography, 2 for invalid arguments, \
+        3 for inputs that cannot be parsed and 4 for other errors, like missing files."
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// report more about the progress, `-vv` for debug output
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// only report errors, `-qq` for nothing at all
+    #[arg(
+        short,
+        long,
+        action = ArgAction::Count,
+        global = true,
+        conflicts_with = "verbose"
+    )]
+    quiet: u8,
+
+    /// format of errors and warnings on stderr, `json` prints an object per line with the
+    /// `level`, `message` and, if known, `file`, `line`, `column` and entry `key`
+    #[arg(long, value_enum, default_value_t, global = true)]
+    error_format: ErrorFormat,
+
+    /// configuration file with defaults for the options, default: `bib2json.toml` in the directory
+    /// of the first input or the closest parent directory that has one
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// ignore `bib2json.toml` files
+    #[arg(long, global = true, conflicts_with = "config")]
+    no_config: bool,
+
+    #[command(flatten)]
+    convert: Convert,
+}
+
+/// Options of the conversion, which is the default command.
+#[derive(clap::Args, Debug, Clone)]
+struct Convert {
+    /// input files (BibTeX, JSON, RIS, PubMed or YAML, optionally gzipped), `-` for stdin, directories or glob patterns
+    /// like `bib/**/*.bib`, BibTeX files share `@string` macros and crossref parents
+    #[arg(default_value = "-", hide_default_value = true)]
+    input: Vec<PathBuf>,
+
+    /// output file, default: stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// compress the output with gzip, the default for output files ending in `.gz`, or `.zst` for Zstandard
+    #[cfg(feature = "gzip")]
+    #[arg(long)]
+    compress: bool,
+
+    /// directory for the output files of `--per-file`, `--split-per-entry` or `--split-by`
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "split",
+        conflicts_with = "output"
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// convert each input file on its own into a file of the same relative path in the output
+    /// directory, with the extension of the output format
+    #[arg(long, group = "split", requires = "output_dir")]
+    per_file: bool,
+
+    /// write each entry into its own file `<key>.json` (or the extension of the output format)
+    /// in the output directory, files that would not change are left untouched, files of
+    /// entries that are gone since the last run are removed
+    #[arg(
+        long,
+        group = "split",
+        requires = "output_dir",
+        conflicts_with = "entry"
+    )]
+    split_per_entry: bool,
+
+    /// write a file per year or entry type, like `2023.json`, into the output directory,
+    /// files that would not change are left untouched, files of groups that are gone since the
+    /// last run are removed
+    #[arg(
+        long,
+        value_enum,
+        value_name = "BY",
+        group = "split",
+        requires = "output_dir",
+        conflicts_with = "entry"
+    )]
+    split_by: Option<SplitBy>,
+
+    /// only output the entry with this key (with resolved crossref data)
+    #[arg(short, long)]
+    entry: Option<String>,
+
+    /// only convert the entry with this key and its crossref and xref parents (repeatable)
+    #[arg(short, long, value_name = "KEY", conflicts_with_all = ["entry", "bcf"])]
+    key: Vec<String>,
+
+    /// only convert entries of this type, e.g., `article` (repeatable)
+    #[arg(long = "type", value_name = "TYPE")]
+    entry_type: Vec<String>,
+
+    /// only convert entries from this year or later, entries without a year are dropped
+    #[arg(long, value_name = "YEAR")]
+    from_year: Option<i32>,
+
+    /// only convert entries up to this year, entries without a year are dropped
+    #[arg(long, value_name = "YEAR")]
+    to_year: Option<i32>,
+
+    /// only convert entries with an author or editor matching this regular expression,
+    /// as `First Last` or `Last, First`
+    #[arg(long, value_name = "REGEX")]
+    author: Option<Regex>,
+
+    /// only convert entries with this keyword in their `keywords` field (repeatable, all have
+    /// to be present)
+    #[arg(long)]
+    keyword: Vec<String>,
+
+    /// only require one of the keywords of `--keyword`
+    #[arg(long, requires = "keyword")]
+    any_keyword: bool,
+
+    /// only convert entries that satisfy this expression,
+    /// e.g., `year >= 2020 && entry_type == "article" && keywords ~ "rtos"`
+    #[arg(long, value_name = "EXPR")]
+    query: Option<Query>,
+
+    /// only output these fields of the entries, e.g., `title,year,doi`, also in their `bibtex`;
+    /// properties like `authors`, `bibtex`, `slug` or `date_sort` only if listed as well
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    fields: Vec<String>,
+
+    /// leave out these fields of the entries, also from their `bibtex`, e.g., `abstract,file`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    exclude_fields: Vec<String>,
+
+    /// remove these fields also from the `bibtex` source, e.g., `notes,file,annotation`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    redact: Vec<String>,
+
+    /// redact the fields of reference managers with local files, private notes and timestamps
+    #[arg(long)]
+    anonymize: bool,
+
+    /// only parse and validate the input, report problems and write no output
+    #[arg(long, conflicts_with_all = ["output", "entry"])]
+    check: bool,
+
+    /// warn about field names that are not known BibTeX or biblatex fields, e.g., misspelled ones
+    #[arg(long)]
+    warn_unknown_fields: bool,
+
+    /// field name to accept in addition to the known ones (repeatable)
+    #[arg(long, value_name = "FIELD", requires = "warn_unknown_fields")]
+    allow_field: Vec<String>,
+
+    /// input format, default: inferred from the extension or content
+    #[arg(short, long, value_enum)]
+    from: Option<InputFormat>,
+
+    /// leave out BibTeX entries that cannot be parsed or resolved and report them,
+    /// instead of failing
+    #[arg(long)]
+    skip_invalid: bool,
+
+    /// fail without output on any anomaly: duplicate keys across inputs, missing crossref targets,
+    /// articles without authors, and all reported problems like unknown fields or cited keys
+    #[arg(long, conflicts_with_all = ["skip_invalid", "keep_incomplete"])]
+    strict: bool,
+
+    /// write the entries left out by `--skip-invalid` with their errors as JSON into this file
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "skip_invalid",
+        conflicts_with = "per_file"
+    )]
+    invalid_report: Option<PathBuf>,
+
+    /// output format, default: inferred from the extension or JSON
+    #[arg(
+        short,
+        long,
+        visible_alias = "format",
+        value_parser = PossibleValuesParser::new(output::FORMATS.iter().map(|f| f.name))
+            .map(|name| Format::by_name(&name).unwrap()),
+    )]
+    to: Option<&'static Format>,
+
+    /// columns of CSV and TSV output, fields or `id`, `entry_type`, `authors`, `editors`, `slug`,
+    /// default: id,entry_type,year,title,authors
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// separator of the persons in CSV and TSV columns
+    #[arg(long, value_name = "SEP", default_value = "; ")]
+    person_separator: String,
+
+    /// indent JSON output by two spaces per level
+    #[arg(long)]
+    pretty: bool,
+
+    /// indent JSON output by N spaces per level
+    #[arg(long, value_name = "N")]
+    indent: Option<usize>,
+
+    /// group the entries of Markdown output under year headings, newest first
+    #[arg(long)]
+    group_by_year: bool,
+
+    /// put fields from crossref, xref and xdata parents into a separate `inherited` object
+    #[arg(long)]
+    inherited: bool,
+
+    /// add a `display_name` to persons and an `authors_str` to entries,
+    /// formatted by a template with `{first}`, `{last}` and their initials `{f}` and `{l}`,
+    /// e.g., "{last}, {f}."
+    #[arg(long, value_name = "TEMPLATE")]
+    name_format: Option<NameFormat>,
+
+    /// locale for sorting the entries by their key (e.g., `de` or `sv-SE`), default: root collation
+    #[arg(long)]
+    locale: Option<Locale>,
+
+    /// order of the entries, `biblatex` also adds the computed `sort_key` to each entry
+    #[arg(long, value_enum, default_value_t)]
+    sort_by: SortBy,
+
+    /// reverse the order of the entries, e.g., newest first with `--sort-by year`,
+    /// entries without a year or author stay last
+    #[arg(long)]
+    reverse: bool,
+
+    /// skip the first N entries, after sorting and filtering
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "entry")]
+    offset: usize,
+
+    /// output at most N entries, after the offset
+    #[arg(long, value_name = "N", conflicts_with = "entry")]
+    limit: Option<usize>,
+
+    /// cut a field to at most N characters, ending with `…` (repeatable)
+    #[arg(long, value_name = "FIELD=N", value_parser = parse_truncate)]
+    truncate_field: Vec<(String, usize)>,
+
+    /// convert LaTeX markup and math in abstracts into plain text
+    #[arg(long)]
+    clean_abstract: bool,
+
+    /// escaping of all string values (except keys and entry types)
+    #[arg(long, value_enum, default_value_t)]
+    escape: Escape,
+
+    /// structure of the entries in JSON and YAML output
+    #[arg(long, value_enum, default_value_t)]
+    layout: Layout,
+
+    /// merge the fields of up to N levels of crossref and xref parents and append their BibTeX,
+    /// default: only the direct parents, without appending
+    #[arg(long, value_name = "N")]
+    crossref_depth: Option<usize>,
+
+    /// drop entries that lack one of these fields and report them
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    require: Vec<String>,
+
+    /// only report the entries that lack a required field, but keep them
+    #[arg(long, requires = "require")]
+    keep_incomplete: bool,
+
+    /// add a `_missing` list of the fields required by the entry type that the entry lacks
+    #[arg(long)]
+    missing: bool,
+
+    /// merge entries with the same DOI or the same title, year and first author
+    /// (comma-separated), the other keys become aliases in `ids`
+    #[arg(long, value_enum, value_name = "BY", value_delimiter = ',')]
+    merge_duplicates: Vec<MergeBy>,
+
+    /// add a `url` from the `doi` to entries without one, listed in `derived`
+    #[arg(long)]
+    doi_url: bool,
+
+    /// add metadata from an online service (repeatable, needs network access)
+    #[cfg(feature = "enrich")]
+    #[arg(long, value_enum, value_name = "SERVICE")]
+    enrich: Vec<Service>,
+
+    /// cache file for the responses of the services, default: `~/.cache/bib2json/enrich.json`
+    #[cfg(feature = "enrich")]
+    #[arg(long, value_name = "FILE", requires = "enrich")]
+    enrich_cache: Option<PathBuf>,
+
+    /// query the services again for responses older than DAYS
+    #[cfg(feature = "enrich")]
+    #[arg(long, value_name = "DAYS", default_value_t = 30, requires = "enrich")]
+    cache_ttl: u64,
+
+    /// only use cached responses, also expired ones, and never query the services
+    #[cfg(feature = "enrich")]
+    #[arg(long, requires = "enrich")]
+    offline: bool,
+
+    /// show the progress of the conversion and enrichment on stderr, by default only for large
+    /// bibliographies on a terminal
+    #[arg(long)]
+    progress: bool,
+
+    /// number of threads converting the entries or the files of `--per-file`, and of concurrent
+    /// lookups for `--enrich`, default: the number of CPUs
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// only convert the entries cited in this Markdown, Typst, LaTeX or LaTeX `.aux` document,
+    /// and their crossref parents, warning about unknown keys (repeatable)
+    #[arg(long, visible_alias = "used-by", value_name = "FILE", action = ArgAction::Append)]
+    cited_in: Vec<PathBuf>,
+
+    /// only convert the entries that a biber control file cites, and the crossref and xref parents
+    /// biber would include, fields are inherited by biblatex's default rules
+    #[arg(long, value_name = "FILE", conflicts_with = "cited_in")]
+    bcf: Option<PathBuf>,
+
+    /// write a Markdown note per entry into this directory instead of the output
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "check", "to"])]
+    notes: Option<PathBuf>,
+
+    /// template of the notes, with placeholders like `{title}`, `{citation}` and `{bibtex}`
+    #[arg(long, value_name = "FILE", requires = "notes")]
+    note_template: Option<PathBuf>,
+
+    /// convert again whenever an input file changes, until interrupted
+    #[arg(long, conflicts_with = "check")]
+    watch: bool,
+
+    /// do nothing if the inputs and options are the same as when the output was last written,
+    /// as recorded in `~/.cache/bib2json/stamps.json`
+    #[arg(long, conflicts_with_all = ["watch", "check"])]
+    if_changed: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Exit code for inputs that cannot be parsed.
+const UNPARSEABLE: u8 = 3;
+/// Exit code for other errors, like missing files.
+const FAILED: u8 = 4;
+
+/// An input that cannot be parsed.
+#[derive(Debug)]
+struct InputError {
+    file: PathBuf,
+    error: ReadError,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.error)
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// The error for an input that cannot be parsed.
+fn input_error(file: &Path, error: ReadError) -> Error {
+    let file = file.to_owned();
+    Error::new(ErrorKind::InvalidData, InputError { file, error })
+}
+
+/// Format a file in place, or print it if it is stdin. With `check`, only
+/// print the path of a file that is not formatted. Returns whether it was not.
+fn format_file(
+    file: &Path,
+    content: &str,
+    style: &Style,
+    check: bool,
+    out: &mut dyn Write,
+) -> Result<bool, Error> {
+    let formatted = pretty::format(content, style).map_err(|e| input_error(file, e))?;
+    let unformatted = formatted != content;
+    match (check, file.as_os_str() == "-") {
+        (true, _) if unformatted => writeln!(out, "{}", file.display())?,
+        (true, _) => {}
+        (false, true) => write!(out, "{formatted}")?,
+        (false, false) if unformatted => std::fs::write(file, formatted)?,
+        (false, false) => {}
+    }
+    Ok(unformatted)
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// convert a bibliography, the default command
+    Convert(Box<Convert>),
+    /// parse and validate a bibliography and report problems
+    Check {
+        /// input files (BibTeX, JSON, RIS, PubMed or YAML), `-` for stdin
+        #[arg(default_value = "-", hide_default_value = true)]
+        input: Vec<PathBuf>,
+
+        /// input format, default: inferred from the extension or content
+        #[arg(short, long, value_enum)]
+        from: Option<InputFormat>,
+
+        /// warn about field names that are not known BibTeX or biblatex fields
+        #[arg(long)]
+        warn_unknown_fields: bool,
+
+        /// field name to accept in addition to the known ones (repeatable)
+        #[arg(long, value_name = "FIELD", requires = "warn_unknown_fields")]
+        allow_field: Vec<String>,
+
+        /// also fail on duplicate keys across inputs and articles without authors
+        #[arg(long)]
+        strict: bool,
+
+        /// fields that entries of a type must have, like `article=author,title,journal`, or
+        /// `*=title` for all types (repeatable)
+        #[arg(long, value_name = "TYPE=FIELDS", value_parser = parse_field)]
+        require: Vec<(String, String)>,
+
+        /// fail on DOIs that do not look like `10.1000/xyz`
+        #[arg(long)]
+        valid_doi: bool,
+
+        /// fail on years outside of the range, like `1900..2030`
+        #[arg(long, value_name = "FIRST..LAST", value_parser = parse_years)]
+        years: Option<RangeInclusive<i32>>,
+
+        /// fail on keys that differ only in case, BibTeX treats them as the same
+        #[arg(long)]
+        case_keys: bool,
+    },
+    /// print the number of entries by type and year, and of the distinct persons, as JSON
+    Stats {
+        /// input file (BibTeX, JSON, RIS, PubMed or YAML), `-` for stdin
+        file: PathBuf,
+    },
+    /// print a new entry with the fields required for its type left empty
+    New {
+        /// entry type, e.g., `article` or `inproceedings`
+        entry_type: String,
+
+        /// citation key
+        #[arg(long)]
+        key: String,
+
+        /// set a field (repeatable)
+        #[arg(long, value_name = "FIELD=VALUE", value_parser = parse_field)]
+        set: Vec<(String, String)>,
+    },
+    /// print a Graphviz DOT graph of the crossref and xref relations between the entries
+    Graph {
+        /// input file (BibTeX, JSON, RIS, PubMed or YAML), `-` for stdin
+        file: PathBuf,
+    },
+    /// compare two bibliographies and list the added, removed and modified entries with their
+    /// changed fields, exits with 1 if they differ
+    Diff {
+        /// the old bibliography (BibTeX, JSON, RIS, PubMed or YAML)
+        old: PathBuf,
+
+        /// the new bibliography
+        new: PathBuf,
+
+        /// output format
+        #[arg(long, value_enum, default_value_t)]
+        format: DiffFormat,
+    },
+    /// list the groups of entries that describe the same publication, one per line, exits with 1
+    /// if there are any
+    Dedupe {
+        /// input file (BibTeX, JSON, RIS, PubMed or YAML), `-` for stdin
+        file: PathBuf,
+
+        /// what identifies duplicates (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "doi,title")]
+        by: Vec<MergeBy>,
+
+        /// print the bibliography as BibTeX with the duplicates merged into the first of them instead
+        #[arg(long)]
+        merge: bool,
+    },
+    /// convert JSON or YAML written by bib2json back to BibTeX, from the fields instead of the
+    /// `bibtex` strings, so that edits of the fields are kept
+    FromJson {
+        /// input file, `-` for stdin
+        file: PathBuf,
+
+        /// output file, default: stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// merge bibliographies into one, resolving entries with the same key by a strategy
+    Merge {
+        /// input files (BibTeX, JSON, RIS, PubMed or YAML)
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+
+        /// output file, default: stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// output format, default: inferred from the extension or BibTeX
+        #[arg(
+            short,
+            long,
+            value_parser = PossibleValuesParser::new(output::FORMATS.iter().map(|f| f.name))
+                .map(|name| Format::by_name(&name).unwrap()),
+        )]
+        to: Option<&'static Format>,
+
+        /// how to resolve entries with the same key, `union` asks on the terminal which of two
+        /// conflicting values to keep and keeps the first otherwise
+        #[arg(long, value_enum, default_value_t)]
+        strategy: Strategy,
+    },
+    /// print the co-authorship network, with edges weighted by the number of shared entries
+    Coauthors {
+        /// input file (BibTeX, JSON, RIS, PubMed or YAML), `-` for stdin
+        file: PathBuf,
+
+        /// file format of the network
+        #[arg(long, value_enum, default_value_t)]
+        format: NetworkFormat,
+    },
+    /// print a JSON Schema of the JSON output
+    #[cfg(feature = "schema")]
+    Schema,
+    /// set fields of an entry in a BibTeX file, leaving the rest of the file untouched
+    Set {
+        /// BibTeX file to edit in place
+        file: PathBuf,
+
+        /// citation key of the entry
+        key: String,
+
+        /// fields to set, with BibTeX values
+        #[arg(required = true, value_name = "FIELD=VALUE", value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+    /// remove fields from all entries in a BibTeX file, leaving the rest of the file untouched
+    RemoveField {
+        /// BibTeX file to edit in place
+        file: PathBuf,
+
+        /// field to remove (repeatable)
+        #[arg(long, required = true)]
+        field: Vec<String>,
+
+        /// only remove from entries of this type (repeatable)
+        #[arg(long = "type", value_name = "TYPE")]
+        entry_type: Vec<String>,
+    },
+    /// format BibTeX files in place with consistent indentation, alignment, field order and braces
+    Fmt {
+        /// BibTeX files to format, `-` to format stdin to stdout
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// list the files that are not formatted instead and exit with 1 if there are any
+        #[arg(long)]
+        check: bool,
+
+        /// spaces before each field
+        #[arg(long, value_name = "N", default_value_t = 2)]
+        indent: usize,
+
+        /// do not align the values of the fields of an entry
+        #[arg(long)]
+        no_align: bool,
+
+        /// order of the fields of an entry
+        #[arg(long, value_enum, default_value_t)]
+        field_order: FieldOrder,
+    },
+    /// print a BibTeX file with its entries sorted, keeping comments and `@string` blocks
+    Sort {
+        /// BibTeX file, `-` for stdin
+        file: PathBuf,
+
+        /// order of the entries
+        #[arg(long, value_enum, default_value_t)]
+        by: SortBy,
+
+        /// reverse the order of the entries
+        #[arg(long)]
+        reverse: bool,
+
+        /// locale for comparing keys and names (e.g., `de` or `sv-SE`), default: root collation
+        #[arg(long)]
+        locale: Option<Locale>,
+
+        /// write the file in place instead
+        #[arg(short, long)]
+        in_place: bool,
+    },
+    /// rename an entry and update the `crossref`, `xref`, `xdata` and `related` references to it
+    /// in all given BibTeX files
+    #[command(visible_alias = "rename-key")]
+    Rename {
+        /// BibTeX file to edit in place
+        file: PathBuf,
+
+        /// current citation key
+        #[arg(required_unless_present = "map")]
+        old: Option<String>,
+
+        /// new citation key
+        #[arg(required_unless_present = "map")]
+        new: Option<String>,
+
+        /// more BibTeX files with references to update, with `--map` all arguments after FILE
+        others: Vec<PathBuf>,
+
+        /// rename the keys of a file with an old and a new key per line, like the output of
+        /// `keygen`
+        #[arg(long, value_name = "FILE")]
+        map: Option<PathBuf>,
+    },
+    /// generate citation keys from a pattern and print the old and new keys, or rename the entries
+    /// and the references to them with `--write`
+    Keygen {
+        /// BibTeX file
+        file: PathBuf,
+
+        /// JabRef-style pattern like `[auth:lower][year][veryshorttitle]`, or one of the schemes
+        /// `FirstAuthorYear`, `FirstAuthorYearTitleWord` and `AuthorsYear`
+        #[arg(long, default_value = "FirstAuthorYearTitleWord")]
+        pattern: Pattern,
+
+        /// rename the entries in place and update the references to them
+        #[arg(long)]
+        write: bool,
+
+        /// more BibTeX files with references to update
+        #[arg(requires = "write")]
+        others: Vec<PathBuf>,
+    },
+    /// replace matches of a regular expression in a field of all entries in a BibTeX file
+    Rewrite {
+        /// BibTeX file to edit in place
+        file: PathBuf,
+
+        /// field to rewrite
+        #[arg(long)]
+        field: String,
+
+        /// regular expression, matched against the BibTeX values
+        #[arg(long)]
+        pattern: Regex,
+
+        /// replacement, `$1` or `${name}` insert capture groups
+        #[arg(long)]
+        replace: String,
+
+        /// only rewrite entries of this type (repeatable)
+        #[arg(long = "type", value_name = "TYPE")]
+        entry_type: Vec<String>,
+
+        /// print the changes instead of writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// print a shell completion script, e.g., `bib2json completions bash > ~/.local/share/bash-completion/completions/bib2json`
+    Completions {
+        /// shell to complete in
+        shell: Shell,
+    },
+    /// print the man page in roff format, e.g., `bib2json manpage > bib2json.1`
+    Manpage,
+}
+
+/// Read the inputs into one bibliography, in the given or inferred formats.
+fn bibliography(inputs: &[PathBuf], from: Option<InputFormat>) -> Result<Bibliography, Error> {
+    read_bibliography(inputs, from, false, false).map(|(bib, _)| bib)
+}
+
+/// Ask on the terminal whether to take the second value of a conflict, keep
+/// the first if there is none.
+fn choose(conflict: &Conflict) -> bool {
+    if !(stdin().is_terminal() && stderr().is_terminal()) {
+        log::warn!(key = conflict.key.as_str(); "{conflict}, keeping the first");
+        return false;
+    }
+    loop {
+        eprint!("{conflict}, keep [1] or [2]? ");
+        let mut answer = String::new();
+        if stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return false;
+        }
+        match answer.trim() {
+            "1" => return false,
+            "2" => return true,
+            _ => {}
+        }
+    }
+}
+
+/// Parse the inputs, leaving out invalid BibTeX entries if `lenient` and
+/// failing on duplicate keys across inputs if `strict`.
+fn read_bibliography(
+    inputs: &[PathBuf],
+    from: Option<InputFormat>,
+    lenient: bool,
+    strict: bool,
+) -> Result<(Bibliography, Vec<Skipped>), Error> {
+    let contents = inputs
+        .iter()
+        .map(|input| read_input(input))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sources: Vec<(InputFormat, &str)> = inputs
+        .iter()
+        .zip(&contents)
+        .map(|(input, content)| {
+            let format = from
+                .or_else(|| InputFormat::from_path(input))
+                .unwrap_or_else(|| InputFormat::detect(content));
+            (format, content.as_str())
+        })
+        .collect();
+    register(inputs, &sources);
+    let parsed = if lenient {
+        input::parse_lenient(&sources)
+    } else if strict {
+        input::parse_strict(&sources).map(|bib| (bib, Vec::new()))
+    } else {
+        input::parse_all(&sources).map(|bib| (bib, Vec::new()))
+    };
+    parsed.map_err(|(i, e)| input_error(&inputs[i], e))
+}
+
+/// Where an entry was read from, for messages about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Located {
+    file: String,
+    location: Location,
+}
+
+/// The locations of the entries read so far by key, `None` for keys read
+/// from different files, like the old and new file of `diff`.
+static LOCATIONS: Mutex<Option<HashMap<String, Option<Located>>>> = Mutex::new(None);
+
+/// Remember where the entries of the inputs are.
+fn register(inputs: &[PathBuf], sources: &[(InputFormat, &str)]) {
+    let mut locations = LOCATIONS.lock().unwrap();
+    let locations = locations.get_or_insert_with(HashMap::new);
+    for (key, location) in input::locate(sources) {
+        let file = inputs[location.input].display().to_string();
+        let located = Located { file, location };
+        let ambiguous = match locations.get(&key) {
+            Some(Some(other)) => other.file != located.file,
+            Some(None) => true,
+            None => false,
+        };
+        locations.insert(key, (!ambiguous).then_some(located));
+    }
+}
+
+/// Where the entry with the key was read from, if it is known.
+fn located(key: &str) -> Option<Located> {
+    LOCATIONS.lock().unwrap().as_ref()?.get(key)?.clone()
+}
+
+/// Warn about the entries left out of the inputs and write them into a JSON report.
+fn report_skipped(
+    inputs: &[PathBuf],
+    skipped: &[Skipped],
+    report: Option<&Path>,
+) -> Result<(), Error> {
+    for s in skipped {
+        let ReadError {
+            line,
+            column,
+            message,
+        } = &s.error;
+        let file = inputs[s.input].display();
+        let at = format!("{file}:{line}:{column}");
+        match &s.key {
+            Some(key) => log::warn!(
+                file:%, line, column, key = key.as_str();
+                "{at}: skipped entry `{key}`: {message}"
+            ),
+            None => log::warn!(file:%, line, column; "{at}: skipped: {message}"),
+        }
+    }
+    if let Some(path) = report {
+        let report: Vec<_> = skipped
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "file": inputs[s.input],
+                    "key": s.key,
+                    "line": s.error.line,
+                    "column": s.error.column,
+                    "message": s.error.message,
+                })
+            })
+            .collect();
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, &report)?;
+        writeln!(file)?;
+    }
+    Ok(())
+}
+
+/// The inputs as shown in messages.
+fn label(inputs: &[PathBuf]) -> String {
+    let names: Vec<String> = inputs.iter().map(|i| i.display().to_string()).collect();
+    names.join(", ")
+}
+
+/// Parse a BibTeX file for editing.
+fn editor<'s>(file: &Path, content: &'s str) -> Result<Editor<'s>, Error> {
+    Editor::new(content).map_err(|e| input_error(file, e))
+}
+
+/// The edited content of a BibTeX file.
+fn edited(file: &Path, editor: Editor) -> Result<String, Error> {
+    editor
+        .finish()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{}: {e}", file.display())))
+}
+
+/// Rename entries and the references to them in the files, failing before
+/// any change if a key is not found or a new key is already taken.
+fn rename_all(files: &[&PathBuf], renames: &[(String, String)]) -> Result<(), Error> {
+    let contents = files
+        .iter()
+        .map(std::fs::read_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut editors = files
+        .iter()
+        .zip(&contents)
+        .map(|(file, content)| editor(file, content))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut keys: BTreeSet<&str> = editors
+        .iter()
+        .flat_map(|e| e.entries().map(|(key, _)| key))
+        .collect();
+    for (old, _) in renames {
+        if !keys.remove(old.as_str()) {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("entry `{old}` not found"),
+            ));
+        }
+    }
+    for (_, new) in renames {
+        if !keys.insert(new) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("entry `{new}` already exists"),
+            ));
+        }
+    }
+    for (old, new) in renames {
+        for editor in &mut editors {
+            editor.rename(old, new);
+        }
+    }
+    let edited = files
+        .iter()
+        .zip(editors)
+        .map(|(file, editor)| edited(file, editor))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (file, content) in files.iter().zip(edited) {
+        std::fs::write(file, content)?;
+    }
+    Ok(())
+}
+
+/// The keys of the entries with one of the types, or all keys if there are no types.
+fn of_type<'s>(editor: &Editor<'s>, types: &[String]) -> Vec<&'s str> {
+    editor
+        .entries()
+        .filter(|(_, kind)| types.is_empty() || types.iter().any(|t| t.eq_ignore_ascii_case(kind)))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Parse a `field=value` pair.
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    let (field, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=VALUE, got `{s}`"))?;
+    Ok((field.to_lowercase(), value.to_owned()))
+}
+
+/// Parse a range of years like `1900..2030`.
+fn parse_years(s: &str) -> Result<RangeInclusive<i32>, String> {
+    let error = || format!("expected FIRST..LAST, got `{s}`");
+    let (first, last) = s.split_once("..").ok_or_else(error)?;
+    let first = first.trim().parse().map_err(|_| error())?;
+    let last = last.trim().parse().map_err(|_| error())?;
+    Ok(first..=last)
+}
+
+/// Parse a `field=N` pair.
+fn parse_truncate(s: &str) -> Result<(String, usize), String> {
+    let (field, max) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=N, got `{s}`"))?;
+    let max = max
+        .parse()
+        .map_err(|e| format!("invalid length `{max}`: {e}"))?;
+    Ok((field.to_lowercase(), max))
+}
+
+/// Read the input file, or stdin for `-`, and decompress it if needed.
+fn read_input(input: &Path) -> Result<String, Error> {
+    if input.as_os_str() == "-" {
+        let mut content = Vec::new();
+        stdin().read_to_end(&mut content)?;
+        input::text(content)
+    } else {
+        input::read(input)
+    }
+}
+
+/// Report the problems of a bibliography, failing if there are any.
+fn check_input(
+    inputs: &[PathBuf],
+    from: Option<InputFormat>,
+    warn_unknown_fields: bool,
+    allow_field: &[String],
+    strict: bool,
+    rules: &Rules,
+) -> Result<ExitCode, Error> {
+    let bibliography = match read_bibliography(inputs, from, false, strict) {
+        Ok((bibliography, _)) => bibliography,
+        Err(e) if e.kind() == ErrorKind::InvalidData => {
+            report(&e);
+            return Ok(ExitCode::from(UNPARSEABLE));
+        }
+        Err(e) => return Err(e),
+    };
+    let mut problems = match strict {
+        true => check::strict(&bibliography),
+        false => check::check(&bibliography),
+    };
+    if warn_unknown_fields {
+        problems.extend(fields::unknown(&bibliography, allow_field));
+    }
+    problems.extend(check::lint(&bibliography, rules));
+    let label = label(inputs);
+    for problem in &problems {
+        log::error!(file = label.as_str(), key = problem.key.as_str(); "{label}: {problem}");
+    }
+    Ok(if problems.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Report an error, with the position of inputs that cannot be parsed.
+fn report(e: &Error) {
+    match e.get_ref().and_then(|e| e.downcast_ref::<InputError>()) {
+        Some(input) => log::error!(
+            file:% = input.file.display(),
+            line = input.error.line,
+            column = input.error.column;
+            "{input}"
+        ),
+        None => log::error!("{e}"),
+    }
+}
+
+/// The exit code of an error.
+fn exit_code(e: &Error) -> ExitCode {
+    match e.kind() {
+        ErrorKind::InvalidData => ExitCode::from(UNPARSEABLE),
+        _ => ExitCode::from(FAILED),
+    }
+}
+
+/// Collects the key-value pairs of a log record as JSON.
+struct Fields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = match value.to_u64() {
+            Some(n) => n.into(),
+            None => value.to_string().into(),
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// The progress bar on stderr, if one is shown.
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Writes log messages to stderr, above the progress bar.
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &*BAR.lock().unwrap() {
+            Some(bar) => bar.suspend(|| stderr().write(buf)),
+            None => stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        stderr().flush()
+    }
+}
+
+/// Bibliographies with at least this many entries show their progress on terminals.
+const LARGE: usize = 5000;
+
+/// Shows the progress of a step on stderr until dropped, as a bar on a
+/// terminal and as a status line every few seconds otherwise.
+struct ProgressReport {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ProgressReport {
+    fn show(
+        args: &Convert,
+        message: &'static str,
+        progress: &Progress,
+        size: usize,
+    ) -> Option<Self> {
+        let terminal = stderr().is_terminal();
+        let wanted = args.progress || (terminal && size >= LARGE);
+        if !wanted || log::max_level() < LevelFilter::Warn {
+            return None;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            let progress = progress.clone();
+            move || {
+                let bar = ProgressBar::new(0).with_message(message).with_style(
+                    ProgressStyle::with_template("{msg} {wide_bar} {pos}/{len} ({eta})").unwrap(),
+                );
+                *BAR.lock().unwrap() = Some(bar.clone());
+                let mut last = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(100));
+                    let (done, total) = progress.get();
+                    bar.set_length(total as u64);
+                    bar.set_position(done as u64);
+                    if !terminal && last.elapsed() >= Duration::from_secs(2) {
+                        eprintln!("{message} {done}/{total}");
+                        last = Instant::now();
+                    }
+                }
+                *BAR.lock().unwrap() = None;
+                bar.finish_and_clear();
+            }
+        });
+        Some(ProgressReport {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ProgressReport {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Log to stderr, warnings and errors by default. `RUST_LOG` overrides the level.
+fn init_logging(verbose: u8, quiet: u8, format: ErrorFormat) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        ..=-2 => LevelFilter::Off,
+        -1 => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .target(env_logger::Target::Pipe(Box::new(LogWriter)))
+        .format(move |f, record| {
+            let level = match record.level() {
+                log::Level::Warn => "warning".to_owned(),
+                level => level.as_str().to_lowercase(),
+            };
+            // Messages about an entry without a position get the one of the entry
+            let kvs = record.key_values();
+            let located = match kvs.get(Key::from("line")) {
+                Some(_) => None,
+                None => kvs
+                    .get(Key::from("key"))
+                    .and_then(|key| located(&key.to_string())),
+            };
+            match format {
+                ErrorFormat::Human => {
+                    let message = record.args().to_string();
+                    match located {
+                        Some(Located { file, location }) => {
+                            // Instead of the inputs as a whole
+                            let label = kvs.get(Key::from("file")).map(|f| format!("{f}: "));
+                            let message = label
+                                .and_then(|label| message.strip_prefix(&label))
+                                .unwrap_or(&message);
+                            let Location { line, column, .. } = location;
+                            writeln!(f, "{level}: {file}:{line}:{column}: {message}")
+                        }
+                        None => writeln!(f, "{level}: {message}"),
+                    }
+                }
+                ErrorFormat::Json => {
+                    let mut fields = Fields(serde_json::Map::new());
+                    fields.0.insert("level".into(), level.into());
+                    fields
+                        .0
+                        .insert("message".into(), record.args().to_string().into());
+                    // The pairs are only collected, which cannot fail
+                    let _ = kvs.visit(&mut fields);
+                    if let Some(Located { file, location }) = located {
+                        fields.0.insert("file".into(), file.into());
+                        fields.0.insert("input".into(), location.input.into());
+                        fields.0.insert("line".into(), location.line.into());
+                        fields.0.insert("column".into(), location.column.into());
+                    }
+                    writeln!(f, "{}", serde_json::Value::Object(fields.0))
+                }
+            }
+        })
+        .init();
+}
+
+/// The name of configuration files.
+const CONFIG: &str = "bib2json.toml";
+
+/// The closest configuration file to the first input, or the working directory.
+fn find_config(inputs: &[PathBuf]) -> Option<PathBuf> {
+    let start = match inputs.first() {
+        Some(input) if input.as_os_str() != "-" => std::path::absolute(input).ok()?,
+        _ => std::env::current_dir().ok()?.join(CONFIG),
+    };
+    start
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(CONFIG))
+        .find(|path| path.is_file())
+}
+
+/// Whether two options of a command exclude each other.
+fn conflicting(command: &clap::Command, a: &Arg, b: &Arg) -> bool {
+    let conflicts = |a: &Arg, b: &Arg| {
+        command
+            .get_arg_conflicts_with(a)
+            .iter()
+            .any(|c| c.get_id() == b.get_id())
+    };
+    conflicts(a, b) || conflicts(b, a)
+}
+
+/// Arguments for the options of a configuration table that are neither given
+/// on the command line nor conflict with an option given there. Keys are the
+/// long names of the options.
+fn config_args(
+    table: &toml::Table,
+    command: &clap::Command,
+    matches: &ArgMatches,
+) -> Result<Vec<OsString>, String> {
+    let given: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|a| matches.value_source(a.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    let mut args = Vec::new();
+    for (key, value) in table {
+        // Tables configure the other subcommands
+        if value.is_table() && Args::command().find_subcommand(key).is_some() {
+            continue;
+        }
+        let Some(arg) = command
+            .get_arguments()
+            .find(|a| a.get_long() == Some(key.as_str()))
+        else {
+            return Err(format!("unknown option `{key}`"));
+        };
+        if given
+            .iter()
+            .any(|g| g.get_id() == arg.get_id() || conflicting(command, g, arg))
+        {
+            continue;
+        }
+        let flag = format!("--{key}");
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match (arg.get_action(), value) {
+                (ArgAction::SetTrue, toml::Value::Boolean(set)) => {
+                    args.extend(set.then(|| flag.clone().into()));
+                }
+                (ArgAction::Count, toml::Value::Integer(n)) => {
+                    args.extend((0..*n).map(|_| flag.clone().into()));
+                }
+                (ArgAction::Set | ArgAction::Append, toml::Value::String(s)) => {
+                    args.push(format!("{flag}={s}").into());
+                }
+                (
+                    ArgAction::Set | ArgAction::Append,
+                    toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_),
+                ) => args.push(format!("{flag}={value}").into()),
+                _ => return Err(format!("invalid value for `{key}`: {value}")),
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// The arguments with the defaults of the configuration file. Its top-level
+/// options are for the conversion, tables like `[check]` for subcommands.
+fn configured(matches: &ArgMatches) -> Result<Option<Args>, Error> {
+    let args = Args::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+    let inputs = match &args.command {
+        None => args.convert.input.as_slice(),
+        Some(Command::Convert(convert)) => convert.input.as_slice(),
+        Some(_) => &[],
+    };
+    let path = match (&args.config, args.no_config) {
+        (Some(path), _) => path.clone(),
+        (None, true) => return Ok(None),
+        (None, false) => match find_config(inputs) {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+    let invalid = |e: &dyn fmt::Display| {
+        Error::new(ErrorKind::InvalidData, format!("{}: {e}", path.display()))
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    let config: toml::Table = toml::from_str(&content).map_err(|e| invalid(&e))?;
+
+    let command = Args::command();
+    let (name, command, matches, table) = match matches.subcommand() {
+        Some((name, sub)) => {
+            let table = match name {
+                "convert" => Some(&config),
+                name => config.get(name).and_then(toml::Value::as_table),
+            };
+            (
+                Some(name),
+                command.find_subcommand(name).unwrap(),
+                sub,
+                table,
+            )
+        }
+        None => (None, &command, matches, Some(&config)),
+    };
+    let extra = match table {
+        Some(table) => config_args(table, command, matches).map_err(|e| invalid(&e))?,
+        None => return Ok(None),
+    };
+    if extra.is_empty() {
+        return Ok(None);
+    }
+    // The options go right after the subcommand, before a possible `--`
+    let mut argv: Vec<OsString> = std::env::args_os().collect();
+    let at = match name {
+        Some(_) => subcommand_index(&Args::command(), &argv).map_or(argv.len(), |i| i + 1),
+        None => 1,
+    };
+    argv.splice(at..at, extra);
+    Args::try_parse_from(argv).map(Some).map_err(|e| {
+        invalid(
+            &e.to_string()
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches("error: "),
+        )
+    })
+}
+
+/// The position of the subcommand in the arguments, the first one that is
+/// neither a global option nor its value.
+fn subcommand_index(command: &clap::Command, argv: &[OsString]) -> Option<usize> {
+    let takes_value = |arg: Option<&Arg>| arg.is_some_and(|a| a.get_action().takes_values());
+    let mut i = 1;
+    while let Some(a) = argv.get(i) {
+        let a = a.to_string_lossy();
+        if a == "--" {
+            return None;
+        }
+        let value = if let Some(long) = a.strip_prefix("--") {
+            !long.contains('=')
+                && takes_value(command.get_arguments().find(|x| x.get_long() == Some(long)))
+        } else if let Some(short) = a.strip_prefix('-').filter(|s| s.chars().count() == 1) {
+            let short = short.chars().next();
+            takes_value(command.get_arguments().find(|x| x.get_short() == short))
+        } else if a.starts_with('-') && a != "-" {
+            false
+        } else {
+            return Some(i);
+        };
+        i += 1 + usize::from(value);
+    }
+    None
+}
+
+fn main() -> ExitCode {
+    let matches = Args::command().get_matches();
+    let (args, config) = match configured(&matches) {
+        Ok(Some(args)) => (args, Ok(())),
+        Ok(None) => (Args::from_arg_matches(&matches).unwrap(), Ok(())),
+        Err(e) => (Args::from_arg_matches(&matches).unwrap(), Err(e)),
+    };
+    init_logging(args.verbose, args.quiet, args.error_format);
+    if let Err(e) = config {
+        report(&e);
+        return exit_code(&e);
+    }
+
+    let result = match args.command {
+        None => run(&args.convert),
+        Some(Command::Convert(convert)) => run(&convert),
+        Some(command) => execute(command),
+    };
+    result.unwrap_or_else(|e| {
+        report(&e);
+        exit_code(&e)
+    })
+}
+
+/// Run a command other than the conversion.
+fn execute(command: Command) -> Result<ExitCode, Error> {
+    match &command {
+        Command::Convert(_) => unreachable!(),
+        Command::Check {
+            input,
+            from,
+            warn_unknown_fields,
+            allow_field,
+            strict,
+            require,
+            valid_doi,
+            years,
+            case_keys,
+        } => {
+            let rules = Rules {
+                required: require
+                    .iter()
+                    .map(|(t, fields)| {
+                        let fields = fields.split(',').map(|f| f.trim().to_lowercase());
+                        (t.clone(), fields.collect())
+                    })
+                    .collect(),
+                doi: *valid_doi,
+                years: years.clone(),
+                case_keys: *case_keys,
+            };
+            check_input(
+                input,
+                *from,
+                *warn_unknown_fields,
+                allow_field,
+                *strict,
+                &rules,
+            )
+        }
+        Command::Stats { file } => {
+            let entries = convert(
+                &bibliography(std::slice::from_ref(file), None)?,
+                &Options::default(),
+            );
+            let stats = serde_json::to_string_pretty(&Stats::new(&entries))?;
+            println!("{stats}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::New {
+            entry_type,
+            key,
+            set,
+        } => {
+            let entry = edit::scaffold(key, entry_type, set);
+            println!("{}", entry.to_biblatex_string());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Graph { file } => {
+            print!(
+                "{}",
+                graph::dot(&bibliography(std::slice::from_ref(file), None)?)
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Diff { old, new, format } => {
+            let diff = Diff::new(
+                &bibliography(std::slice::from_ref(old), None)?,
+                &bibliography(std::slice::from_ref(new), None)?,
+            );
+            match format {
+                DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+                DiffFormat::Text => print!("{diff}"),
+            }
+            Ok(if diff.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Command::Dedupe { file, by, merge } => {
+            let mut bib = bibliography(std::slice::from_ref(file), None)?;
+            if !merge {
+                let groups = duplicates::find(&bib, by);
+                for group in &groups {
+                    println!("{}", group.join(" "));
+                }
+                return Ok(if groups.is_empty() {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                });
+            }
+            for problem in duplicates::merge(&mut bib, by) {
+                log::warn!(key = problem.key.as_str(); "{problem}");
+            }
+            let options = Options::default();
+            let bibtex = Format::by_name("bibtex").unwrap();
+            let mut writer = BufWriter::new(stdout());
+            output::write(&bib, &options, (bibtex.new)(&options), &mut writer)?;
+            writer.flush()?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::FromJson { file, output } => {
+            let content = read_input(file)?;
+            let format = InputFormat::from_path(file)
+                .filter(|f| *f != InputFormat::Bibtex)
+                .unwrap_or(InputFormat::Json);
+            let bib = format.parse(&content).map_err(|e| input_error(file, e))?;
+            let bibtex: Vec<String> = bib.iter().map(|e| e.to_biblatex_string()).collect();
+            let bibtex = bibtex.join("\n\n") + "\n";
+            match output {
+                Some(output) => std::fs::write(output, bibtex)?,
+                None => print!("{bibtex}"),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Merge {
+            input,
+            output,
+            to,
+            strategy,
+        } => {
+            let bibs = input
+                .iter()
+                .map(|file| bibliography(std::slice::from_ref(file), None))
+                .collect::<Result<Vec<_>, _>>()?;
+            let bib = match merge::merge(bibs, *strategy, &mut choose) {
+                Ok(bib) => bib,
+                Err(conflict) => {
+                    log::error!(key = conflict.key.as_str(); "{conflict}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            };
+            let to = to
+                .or_else(|| output.as_deref().and_then(Format::from_path))
+                .unwrap_or_else(|| Format::by_name("bibtex").unwrap());
+            let options = Options::default();
+            let writer: Box<dyn Write> = match output {
+                Some(output) => Box::new(File::create(output)?),
+                None => Box::new(stdout()),
+            };
+            let mut writer = BufWriter::new(writer);
+            output::write(&bib, &options, (to.new)(&options), &mut writer)?;
+            writer.flush()?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Coauthors { file, format } => {
+            print!(
+                "{}",
+                Network::new(&bibliography(std::slice::from_ref(file), None)?).write(*format)
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        #[cfg(feature = "schema")]
+        Command::Schema => {
+            let schema = bib2json_core::schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Set { file, key, fields } => {
+            let content = std::fs::read_to_string(file)?;
+            let mut editor = editor(file, &content)?;
+            for (field, value) in fields {
+                editor
+                    .set(key, field, value)
+                    .map_err(|e| Error::new(ErrorKind::NotFound, e))?;
+            }
+            std::fs::write(file, edited(file, editor)?)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::RemoveField {
+            file,
+            field,
+            entry_type,
+        } => {
+            let content = std::fs::read_to_string(file)?;
+            let mut editor = editor(file, &content)?;
+            for key in of_type(&editor, entry_type) {
+                for field in field {
+                    editor
+                        .remove(key, field)
+                        .map_err(|e| Error::new(ErrorKind::NotFound, e))?;
+                }
+            }
+            std::fs::write(file, edited(file, editor)?)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Fmt {
+            files,
+            check,
+            indent,
+            no_align,
+            field_order,
+        } => {
+            let style = Style {
+                indent: *indent,
+                align: !no_align,
+                order: *field_order,
+            };
+            let mut unformatted = false;
+            for file in files {
+                let content = read_input(file)?;
+                unformatted |= format_file(file, &content, &style, *check, &mut stdout())?;
+            }
+            Ok(if *check && unformatted {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            })
+        }
+        Command::Sort {
+            file,
+            by,
+            reverse,
+            locale,
+            in_place,
+        } => {
+            let content = read_input(file)?;
+            let options = Options {
+                sort_by: *by,
+                reverse: *reverse,
+                locale: locale.clone().unwrap_or_default(),
+                ..Default::default()
+            };
+            let sorted = sort::reorder(&content, &options).map_err(|e| input_error(file, e))?;
+            match in_place {
+                true => std::fs::write(file, sorted)?,
+                false => print!("{sorted}"),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Rename {
+            file,
+            old,
+            new,
+            others,
+            map,
+        } => {
+            let (renames, others) = match map {
+                Some(map) => {
+                    let renames = edit::renames(&std::fs::read_to_string(map)?)
+                        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                    let positional = old.iter().chain(new).map(PathBuf::from);
+                    (renames, positional.chain(others.iter().cloned()).collect())
+                }
+                None => {
+                    let (old, new) = (old.clone().unwrap(), new.clone().unwrap());
+                    (vec![(old, new)], others.clone())
+                }
+            };
+            let files: Vec<&PathBuf> = std::iter::once(file).chain(&others).collect();
+            rename_all(&files, &renames)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Keygen {
+            file,
+            pattern,
+            write,
+            others,
+        } => {
+            let content = std::fs::read_to_string(file)?;
+            let bib = InputFormat::Bibtex
+                .parse(&content)
+                .map_err(|e| input_error(file, e))?;
+            let renames = keygen::assign(&bib, pattern);
+            if !write {
+                for (old, new) in &renames {
+                    println!("{old}\t{new}");
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            let files: Vec<&PathBuf> = std::iter::once(file).chain(others).collect();
+            rename_all(&files, &renames)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Rewrite {
+            file,
+            field,
+            pattern,
+            replace,
+            entry_type,
+            dry_run,
+        } => {
+            let content = std::fs::read_to_string(file)?;
+            let mut editor = editor(file, &content)?;
+            for key in of_type(&editor, entry_type) {
+                let change = editor
+                    .rewrite(key, field, |value| {
+                        pattern.replace_all(value, replace.as_str()).into_owned()
+                    })
+                    .map_err(|e| Error::new(ErrorKind::NotFound, e))?;
+                if let (Some((old, new)), true) = (change, *dry_run) {
+                    println!("{key}:\n- {old}\n+ {new}");
+                }
+            }
+            if !dry_run {
+                std::fs::write(file, edited(file, editor)?)?;
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(*shell, &mut Args::command(), "bib2json", &mut stdout());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Manpage => {
+            clap_mangen::Man::new(Args::command()).render(&mut stdout())?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Convert the input and write the output.
+fn run(args: &Convert) -> Result<ExitCode, Error> {
+    if args.watch {
+        return watch(args);
+    }
+    let found = expand_inputs(&args.input)?;
+    let inputs: Vec<PathBuf> = found.iter().map(|f| f.path.clone()).collect();
+
+    if args.if_changed {
+        return if_changed(args, &inputs);
+    }
+    if args.check {
+        return check_input(
+            &inputs,
+            args.from,
+            args.warn_unknown_fields,
+            &args.allow_field,
+            args.strict,
+            &Rules::default(),
+        );
+    }
+    if let (Some(dir), true) = (&args.output_dir, args.per_file) {
+        let to = args.to.unwrap_or(&output::FORMATS[0]);
+        // The files are converted in parallel, each on one thread
+        let file_args = Convert {
+            jobs: Some(1),
+            ..args.clone()
+        };
+        let files: Vec<(&Path, PathBuf)> = found
+            .iter()
+            .map(|f| {
+                let output = dir.join(&f.relative).with_extension(to.extension());
+                (f.path.as_path(), output)
+            })
+            .collect();
+        // Inputs like `a.bib` and `a.json` would overwrite each other's output
+        let mut outputs = HashMap::new();
+        for (input, output) in &files {
+            if let Some(other) = outputs.insert(output, input) {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "{} and {} would both be converted to {}",
+                        other.display(),
+                        input.display(),
+                        output.display()
+                    ),
+                ));
+            }
+        }
+        let results = parallel::map(&files, jobs(args), |(input, output)| {
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            convert_inputs(&file_args, &[input.to_path_buf()], Some(output))
+        });
+        for result in results {
+            result?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    convert_inputs(args, &inputs, args.output.as_deref())
+}
+
+/// The documents of `--cited-in` and the `.aux` files they include with
+/// `\@input`, with their content.
+fn documents(paths: &[PathBuf]) -> Result<Vec<(PathBuf, String)>, Error> {
+    let mut documents = Vec::new();
+    let mut queue = paths.to_vec();
+    while let Some(path) = queue.pop() {
+        if documents.iter().any(|(p, _)| *p == path) {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        if Document::from_path(&path) == Document::Aux {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            queue.extend(
+                cite::aux_includes(&content)
+                    .into_iter()
+                    .map(|f| dir.join(f)),
+            );
+        }
+        documents.push((path, content));
+    }
+    Ok(documents)
+}
+
+/// A 64-bit FNV-1a hash, which unlike [`std::hash::DefaultHasher`] is the same in
+/// every build, so that stamps stay valid.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Add bytes, after their length to separate them from the next.
+    fn add(&mut self, bytes: &[u8]) {
+        let len = (bytes.len() as u64).to_le_bytes();
+        for byte in len.iter().chain(bytes) {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// A hash of the version, the options and all files the output depends on.
+fn stamp(args: &Convert, inputs: &[PathBuf]) -> Result<String, Error> {
+    let mut hasher = StableHasher::new();
+    hasher.add(env!("CARGO_PKG_VERSION").as_bytes());
+    // Without the options that do not change the output, and the format by
+    // name, as its debug output contains addresses
+    let options = Convert {
+        to: None,
+        jobs: None,
+        progress: false,
+        if_changed: false,
+        ..args.clone()
+    };
+    hasher.add(format!("{options:?}").as_bytes());
+    hasher.add(args.to.map_or("", |f| f.name).as_bytes());
+    let files = args.bcf.iter().chain(&args.note_template);
+    for file in inputs.iter().chain(files) {
+        hasher.add(file.as_os_str().as_encoded_bytes());
+        hasher.add(read_input(file)?.as_bytes());
+    }
+    for (path, content) in documents(&args.cited_in)? {
+        hasher.add(path.as_os_str().as_encoded_bytes());
+        hasher.add(content.as_bytes());
+    }
+    Ok(format!("{:016x}", hasher.0))
+}
+
+/// Convert, unless the stamp of the inputs and options is the same as when
+/// the output was written last.
+fn if_changed(args: &Convert, inputs: &[PathBuf]) -> Result<ExitCode, Error> {
+    let Some(target) = args
+        .output
+        .as_ref()
+        .or(args.output_dir.as_ref())
+        .or(args.notes.as_ref())
+    else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "`--if-changed` needs an output file or directory",
+        ));
+    };
+    if inputs.iter().any(|i| i.as_os_str() == "-") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot compare stdin, pass input files",
+        ));
+    }
+    let mut stamps = match Cache::dir() {
+        Some(dir) => Cache::open(dir.join("stamps.json"))?,
+        None => Cache::memory(),
+    };
+    let key = std::path::absolute(target)?.display().to_string();
+    let stamp = stamp(args, inputs)?;
+    let previous = stamps.get(&key).and_then(serde_json::Value::as_str);
+    if target.exists() && previous == Some(stamp.as_str()) {
+        log::info!("{}: up to date", target.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+    let code = run(&Convert {
+        if_changed: false,
+        ..args.clone()
+    })?;
+    if code == ExitCode::SUCCESS {
+        stamps.insert(key, stamp.into());
+        stamps.save()?;
+    }
+    Ok(code)
+}
+
+/// The number of threads to use, by default one per CPU.
+fn jobs(args: &Convert) -> usize {
+    args.jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// The files of the inputs, with directories and glob patterns expanded.
+fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<Found>, Error> {
+    let mut found = Vec::new();
+    for input in inputs {
+        match input.as_os_str() == "-" {
+            true => found.push(Found {
+                path: input.clone(),
+                relative: "stdin".into(),
+            }),
+            false => found.extend(files::expand(input)?),
+        }
+    }
+    if found.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound, "no input files found"));
+    }
+    Ok(found)
+}
+
+/// The absolute paths of the input files and the other files the output
+/// depends on.
+fn watched_files(args: &Convert) -> HashSet<PathBuf> {
+    let found = expand_inputs(&args.input).unwrap_or_default();
+    let dependencies = args
+        .cited_in
+        .iter()
+        .chain(&args.bcf)
+        .chain(&args.note_template);
+    found
+        .iter()
+        .map(|f| &f.path)
+        .chain(dependencies)
+        .filter_map(|path| std::path::absolute(path).ok())
+        .collect()
+}
+
+/// Convert whenever the input files or the other files the output depends on
+/// change. Errors are reported and the inputs watched further.
+fn watch(args: &Convert) -> Result<ExitCode, Error> {
+    if args.input.iter().any(|i| i.as_os_str() == "-") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot watch stdin, pass input files",
+        ));
+    }
+    let args = Convert {
+        watch: false,
+        ..args.clone()
+    };
+    let (sender, events) = mpsc::channel();
+    let mut debouncer =
+        new_debouncer(Duration::from_millis(200), None, sender).map_err(Error::other)?;
+    // Directories and patterns for the files added to them, the directories
+    // of files as editors replace them
+    let dependencies = args
+        .cited_in
+        .iter()
+        .chain(&args.bcf)
+        .chain(&args.note_template);
+    for path in args.input.iter().chain(dependencies) {
+        let (dir, mode) = match files::base(path) {
+            Some(base) => (base, RecursiveMode::Recursive),
+            None if path.is_dir() => (path.clone(), RecursiveMode::Recursive),
+            None => match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    (parent.to_owned(), RecursiveMode::NonRecursive)
+                }
+                _ => (PathBuf::from("."), RecursiveMode::NonRecursive),
+            },
+        };
+        let dir = std::path::absolute(&dir)?;
+        debouncer
+            .watch(&dir, mode)
+            .map_err(|e| Error::other(format!("{}: {e}", dir.display())))?;
+    }
+    loop {
+        // Taken before the conversion, so changes during it convert again
+        let files = watched_files(&args);
+        match run(&args) {
+            Ok(_) => eprintln!("converted {}", label(&args.input)),
+            Err(e) => report(&e),
+        }
+        loop {
+            let changed: Vec<PathBuf> = match events.recv().map_err(Error::other)? {
+                // Reading the files is not a change
+                Ok(events) => events
+                    .into_iter()
+                    .filter(|e| !matches!(e.kind, EventKind::Access(_)))
+                    .flat_map(|e| e.event.paths)
+                    .collect(),
+                Err(errors) => {
+                    for e in errors {
+                        report(&Error::other(e));
+                    }
+                    continue;
+                }
+            };
+            let relevant = |files: &HashSet<PathBuf>| changed.iter().any(|p| files.contains(p));
+            // Added files are only known after expanding the inputs again
+            if relevant(&files) || relevant(&watched_files(&args)) {
+                break;
+            }
+        }
+    }
+}
+
+/// Convert the inputs into one output file, or stdout.
+fn convert_inputs(
+    args: &Convert,
+    inputs: &[PathBuf],
+    output: Option<&Path>,
+) -> Result<ExitCode, Error> {
+    let label = label(inputs);
+    let (mut bibliography, skipped) =
+        read_bibliography(inputs, args.from, args.skip_invalid, args.strict)?;
+    report_skipped(inputs, &skipped, args.invalid_report.as_deref())?;
+    log::info!("{label}: read {} entries", bibliography.len());
+
+    // The problems that fail the conversion with `--strict`
+    let mut problems = match args.strict {
+        true => check::strict(&bibliography),
+        false => Vec::new(),
+    };
+    if args.warn_unknown_fields {
+        problems.extend(fields::unknown(&bibliography, &args.allow_field));
+    }
+    if !args.merge_duplicates.is_empty() {
+        problems.extend(duplicates::merge(&mut bibliography, &args.merge_duplicates));
+    }
+    for problem in &problems {
+        log::warn!(file = label.as_str(), key = problem.key.as_str(); "{label}: {problem}");
+    }
+    let mut anomalies = problems.len();
+
+    let keys = if let Some(path) = &args.bcf {
+        let content = std::fs::read_to_string(path)?;
+        let control = Control::parse(&content)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+        if control.custom_inheritance {
+            log::warn!(
+                file:% = path.display();
+                "{}: custom inheritance defaults are not supported, fields are inherited by biblatex's default rules",
+                path.display()
+            );
+        }
+        Some(control.keys(&bibliography))
+    } else if args.cited_in.is_empty() && args.key.is_empty() {
+        None
+    } else {
+        let mut keys = BTreeSet::new();
+        for key in &args.key {
+            if bibliography.get(key).is_none() {
+                log::warn!(file = label.as_str(), key = key.as_str(); "{label}: entry `{key}` not found");
+                anomalies += 1;
+            }
+            keys.insert(key.clone());
+        }
+        for (path, content) in documents(&args.cited_in)? {
+            let document = Document::from_path(&path);
+            let cited = document.citations(&content);
+            for key in cited
+                .iter()
+                .filter(|k| *k != "*" && bibliography.get(k).is_none())
+            {
+                log::warn!(
+                    file:% = path.display(), key = key.as_str();
+                    "{}: cited entry `{key}` not found", path.display()
+                );
+                anomalies += 1;
+            }
+            keys.extend(cited);
+        }
+        // `\nocite{*}` cites all entries
+        (!keys.contains("*")).then(|| cite::with_parents(&bibliography, keys))
+    };
+
+    let options = Options {
+        inherited: args.inherited,
+        name_format: args.name_format.clone(),
+        locale: args.locale.clone().unwrap_or_default(),
+        sort_by: args.sort_by,
+        reverse: args.reverse,
+        truncate: args.truncate_field.iter().cloned().collect(),
+        clean_abstract: args.clean_abstract,
+        escape: args.escape,
+        layout: args.layout,
+        keys,
+        filter: Filter {
+            types: args.entry_type.clone(),
+            from_year: args.from_year,
+            to_year: args.to_year,
+            author: args.author.clone(),
+            keywords: args.keyword.clone(),
+            any_keyword: args.any_keyword,
+            query: args.query.clone(),
+        },
+        fields: args.fields.clone(),
+        exclude_fields: args.exclude_fields.clone(),
+        redact: args
+            .redact
+            .iter()
+            .cloned()
+            .chain(
+                PRIVATE_FIELDS
+                    .iter()
+                    .filter(|_| args.anonymize)
+                    .map(|f| f.to_string()),
+            )
+            .collect(),
+        offset: args.offset,
+        limit: args.limit,
+        doi_url: args.doi_url,
+        crossref_depth: args.crossref_depth,
+        require: args.require.iter().map(|f| f.to_lowercase()).collect(),
+        keep_incomplete: args.keep_incomplete,
+        missing: args.missing,
+        columns: args.columns.clone(),
+        person_separator: Some(args.person_separator.clone()),
+        group_by_year: args.group_by_year,
+        indent: args.indent.or(args.pretty.then_some(2)),
+        jobs: jobs(args),
+        progress: Progress::default(),
+    };
+    if let Some(dir) = &args.notes {
+        let template = match &args.note_template {
+            Some(path) => std::fs::read_to_string(path)?,
+            None => notes::DEFAULT_TEMPLATE.to_owned(),
+        };
+        std::fs::create_dir_all(dir)?;
+        for entry in convert(&bibliography, &options) {
+            if args.entry.as_ref().is_none_or(|key| *key == entry.id) {
+                std::fs::write(
+                    dir.join(notes::file_name(&entry)),
+                    notes::render(&template, &entry),
+                )?;
+            }
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let to = args
+        .to
+        .or_else(|| output.and_then(Format::from_path))
+        .unwrap_or(&output::FORMATS[0]);
+
+    let entry =
+        match &args.entry {
+            Some(key) => Some(bibliography.get(key).ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, format!("entry `{key}` not found"))
+            })?),
+            None => None,
+        };
+
+    let report = ProgressReport::show(args, "converting", &options.progress, bibliography.len());
+    let mut entries = match entry {
+        Some(entry) => vec![SRAEntry::with_options(entry, &bibliography, &options)],
+        None => convert_unselected(
+            &bibliography,
+            &Options {
+                keep_incomplete: true,
+                offset: 0,
+                limit: None,
+                ..options.clone()
+            },
+        ),
+    };
+    drop(report);
+    entries.retain(|entry| match check::require(entry, &options.require) {
+        Some(problem) => {
+            log::warn!(file = label.as_str(), key = problem.key.as_str(); "{label}: {problem}");
+            anomalies += 1;
+            options.keep_incomplete
+        }
+        None => true,
+    });
+    if let (Some(key), true) = (&args.entry, entries.is_empty()) {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("entry `{key}` lacks a field required by `--require`"),
+        ));
+    }
+    if args.strict && anomalies > 0 {
+        log::error!("{label}: {anomalies} problem(s), no output because of `--strict`");
+        return Ok(ExitCode::FAILURE);
+    }
+    let mut entries = options.paginate(entries);
+    log::info!("{label}: converting {} entries", entries.len());
+    #[cfg(feature = "enrich")]
+    if !args.enrich.is_empty() {
+        let cache = match args.enrich_cache.clone().or_else(Cache::default_path) {
+            Some(path) => Cache::open(path)?,
+            None => Cache::memory(),
+        };
+        let cache = cache.with_ttl(Duration::from_secs(args.cache_ttl * 24 * 60 * 60));
+        let progress = Progress::default();
+        let enricher = Enricher::new(args.enrich.clone(), cache)
+            .offline(args.offline)
+            .progress(progress.clone());
+        let report = ProgressReport::show(args, "enriching", &progress, entries.len());
+        let enriched = enricher.enrich_all(&mut entries, jobs(args));
+        drop(report);
+        // Save what was fetched before a failure as well.
+        let saved = enricher.finish();
+        enriched?;
+        saved?;
+    }
+    for entry in &mut entries {
+        entry.select_fields(&options);
+    }
+
+    if let (Some(dir), true) = (&args.output_dir, args.split_per_entry) {
+        output::write_split(entries, to, &options, dir)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let (Some(dir), Some(by)) = (&args.output_dir, args.split_by) {
+        output::write_grouped(entries, by, to, &options, dir)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let writer: Box<dyn Write> = if let Some(output) = output {
+        let file = File::create(output)?;
+        Box::new(file)
+    } else {
+        Box::new(stdout())
+    };
+    #[cfg(feature = "gzip")]
+    let writer = {
+        let codec = output.and_then(compress::Codec::from_path);
+        let codec = codec.or(args.compress.then_some(compress::Codec::Gzip));
+        compress::writer(writer, codec)?
+    };
+    let mut writer = BufWriter::new(writer);
+
+    let format = (to.new)(&options);
+    if entry.is_some() {
+        format.single(entries.remove(0), &mut writer)?;
+    } else {
+        output::write_entries(entries, format, &mut writer)?;
+    }
+    let writer = writer.into_inner().map_err(|e| e.into_error())?;
+    #[cfg(feature = "gzip")]
+    let mut writer = writer.finish()?;
+    #[cfg(not(feature = "gzip"))]
+    let mut writer = writer;
+    writer.flush()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use std::ffi::OsString;
+
+    use clap::{CommandFactory, Parser};
+
+    use bib2json_core::pretty::Style;
+
+    use super::{config_args, convert_inputs, format_file, stamp, subcommand_index, Args};
+
+    #[test]
+    fn cited_in() {
+        let args = Args::try_parse_from([
+            "bib2json",
+            "--cited-in",
+            "a.md",
+            "refs.bib",
+            "--used-by",
+            "b.tex",
+        ])
+        .unwrap();
+        assert_eq!(args.convert.input, [PathBuf::from("refs.bib")]);
+        assert_eq!(
+            args.convert.cited_in,
+            [PathBuf::from("a.md"), PathBuf::from("b.tex")]
+        );
+    }
+
+    #[test]
+    fn config() {
+        let table: toml::Table = toml::from_str(
+            "strict = true\nsort-by = \"year\"\nlocale = \"de\"\n[check]\nstrict = true",
+        )
+        .unwrap();
+        let command = Args::command();
+        let matches = command
+            .clone()
+            .try_get_matches_from(["bib2json", "--skip-invalid", "--locale=en", "refs.bib"])
+            .unwrap();
+        // `strict` conflicts with `--skip-invalid` and `locale` is given
+        assert_eq!(
+            config_args(&table, &command, &matches).unwrap(),
+            [OsString::from("--sort-by=year")]
+        );
+        let matches = command.clone().try_get_matches_from(["bib2json"]).unwrap();
+        assert_eq!(
+            config_args(&table, &command, &matches).unwrap(),
+            ["--locale=de", "--sort-by=year", "--strict"].map(OsString::from)
+        );
+        let table: toml::Table = toml::from_str("unknown = 1").unwrap();
+        assert!(config_args(&table, &command, &matches).is_err());
+    }
+
+    #[test]
+    fn subcommand() {
+        let index = |argv: &[&str]| {
+            let argv: Vec<OsString> = argv.iter().map(OsString::from).collect();
+            subcommand_index(&Args::command(), &argv)
+        };
+        assert_eq!(index(&["bib2json", "check", "refs.bib"]), Some(1));
+        assert_eq!(
+            index(&[
+                "bib2json",
+                "-v",
+                "--config",
+                "check",
+                "rename-key",
+                "a",
+                "b"
+            ]),
+            Some(4)
+        );
+        assert_eq!(
+            index(&["bib2json", "--config=x.toml", "-qq", "stats"]),
+            Some(3)
+        );
+        assert_eq!(index(&["bib2json", "-v"]), None);
+    }
+
+    #[test]
+    fn stamps() {
+        let dir = std::env::temp_dir().join(format!("bib2json-stamp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("refs.bib"), "@misc{a, title={A}}").unwrap();
+        std::fs::write(dir.join("main.aux"), "\\@input{chapter.aux}\n").unwrap();
+        std::fs::write(dir.join("chapter.aux"), "\\citation{a}\n").unwrap();
+        let args = Args::try_parse_from([
+            "bib2json".into(),
+            dir.join("refs.bib"),
+            "--cited-in".into(),
+            dir.join("main.aux"),
+        ])
+        .unwrap();
+        let inputs = [dir.join("refs.bib")];
+        let before = stamp(&args.convert, &inputs).unwrap();
+        assert_eq!(stamp(&args.convert, &inputs).unwrap(), before);
+        std::fs::write(dir.join("chapter.aux"), "\\citation{b}\n").unwrap();
+        assert_ne!(stamp(&args.convert, &inputs).unwrap(), before);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn required_entry() {
+        let dir = std::env::temp_dir().join(format!("bib2json-required-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("refs.bib");
+        std::fs::write(&input, "@misc{a, title={A}}").unwrap();
+        let output = dir.join("a.json");
+        let args = Args::try_parse_from([
+            "bib2json".into(),
+            input.clone(),
+            "--entry".into(),
+            "a".into(),
+            "--require".into(),
+            "doi".into(),
+        ])
+        .unwrap();
+        let error = convert_inputs(&args.convert, &[input], Some(&output)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(!output.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn entry_pagination() {
+        for option in ["--offset", "--limit"] {
+            let args = Args::try_parse_from(["bib2json", "refs.bib", "--entry", "a", option, "1"]);
+            assert!(args.is_err(), "{option}");
+        }
+    }
+
+    #[test]
+    fn format_check() {
+        let stdin = PathBuf::from("-");
+        let style = Style::default();
+        let content = "@misc{a,title={A}}";
+        let mut out = Vec::new();
+        assert!(format_file(&stdin, content, &style, true, &mut out).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "-\n");
+
+        let mut out = Vec::new();
+        assert!(format_file(&stdin, content, &style, false, &mut out).unwrap());
+        let formatted = String::from_utf8(out).unwrap();
+        let mut out = Vec::new();
+        assert!(!format_file(&stdin, &formatted, &style, true, &mut out).unwrap());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Biber control files (`.bcf`), which biblatex writes for biber.
+//!
+//! They list the citations of a LaTeX document and the options that decide
+//! which crossref and xref parents biber adds to the bibliography. Fields are
+//! inherited by biblatex's default rules, not by the `<bcf:inheritance>`
+//! section, of which only the defaults are read to tell if they differ.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use biblatex::Bibliography;
+
+/// The citations and inclusion options of the first refsection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Control {
+    /// The cited keys, `None` for `\nocite{*}`.
+    pub citations: Option<BTreeSet<String>>,
+    /// Crossref parents cited by this many entries are included.
+    pub mincrossrefs: usize,
+    /// Xref parents cited by this many entries are included.
+    pub minxrefs: usize,
+    /// Whether the inheritance defaults differ from biblatex's, like with
+    /// `\DefaultInheritance[all=false]`, which is not supported.
+    pub custom_inheritance: bool,
+}
+
+impl Control {
+    /// Parse a control file.
+    pub fn parse(content: &str) -> Result<Self, roxmltree::Error> {
+        let doc = roxmltree::Document::parse(content)?;
+        let mut options: BTreeMap<&str, &str> = BTreeMap::new();
+        for option in doc.descendants().filter(|n| n.has_tag_name_local("option")) {
+            let child = |name| {
+                option
+                    .children()
+                    .find(|n| n.has_tag_name_local(name))
+                    .and_then(|n| n.text())
+            };
+            if let (Some(key), Some(value)) = (child("key"), child("value")) {
+                options.insert(key, value);
+            }
+        }
+        let min = |key| options.get(key).and_then(|v| v.parse().ok()).unwrap_or(2);
+
+        let mut citations = Some(BTreeSet::new());
+        let section = doc
+            .descendants()
+            .find(|n| n.has_tag_name_local("section") && n.attribute("number") == Some("0"));
+        let keys = section
+            .iter()
+            .flat_map(|s| s.descendants())
+            .filter(|n| n.has_tag_name_local("citekey"))
+            .filter_map(|n| n.text());
+        for key in keys {
+            match key {
+                "*" => citations = None,
+                key => {
+                    if let Some(citations) = &mut citations {
+                        citations.insert(key.to_owned());
+                    }
+                }
+            }
+        }
+        // All fields are inherited and the target's own ones kept, also for
+        // the type pairs in the defaults
+        let custom_inheritance = doc
+            .descendants()
+            .filter(|n| n.has_tag_name_local("inheritance"))
+            .flat_map(|n| n.children().filter(|n| n.has_tag_name_local("defaults")))
+            .flat_map(|n| std::iter::once(n).chain(n.children().filter(|n| n.is_element())))
+            .any(|n| {
+                n.attribute("inherit_all").is_some_and(|v| v != "true")
+                    || n.attribute("override_target").is_some_and(|v| v != "false")
+            });
+        Ok(Control {
+            citations,
+            mincrossrefs: min("mincrossrefs"),
+            minxrefs: min("minxrefs"),
+            custom_inheritance,
+        })
+    }
+
+    /// The keys of the entries biber would output: the cited ones and the
+    /// parents that enough included entries refer to.
+    pub fn keys(&self, bib: &Bibliography) -> BTreeSet<String> {
+        let Some(citations) = &self.citations else {
+            return bib.iter().map(|e| e.key.clone()).collect();
+        };
+        let mut keys: BTreeSet<String> = citations
+            .iter()
+            .filter_map(|key| bib.get(key))
+            .map(|e| e.key.clone())
+            .collect();
+        loop {
+            // Citations of the crossref and the xref parents
+            let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+            for e in keys.iter().filter_map(|key| bib.get(key)) {
+                if let Ok(parent) = e.get_as::<String>("crossref") {
+                    counts.entry(parent).or_default().0 += 1;
+                }
+                for parent in e.get_as::<Vec<String>>("xref").unwrap_or_default() {
+                    counts.entry(parent).or_default().1 += 1;
+                }
+            }
+            let parents: Vec<String> = counts
+                .into_iter()
+                .filter(|(_, (c, x))| *c >= self.mincrossrefs || *x >= self.minxrefs)
+                .filter_map(|(key, _)| bib.get(&key))
+                .map(|e| e.key.clone())
+                .filter(|key| !keys.contains(key))
+                .collect();
+            if parents.is_empty() {
+                return keys;
+            }
+            keys.extend(parents);
+        }
+    }
+}
+
+/// Matching of elements by their name without namespace prefix.
+trait LocalName {
+    fn has_tag_name_local(&self, name: &str) -> bool;
+}
+
+impl LocalName for roxmltree::Node<'_, '_> {
+    fn has_tag_name_local(&self, name: &str) -> bool {
+        self.is_element() && self.tag_name().name() == name
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::Control;
+
+    const BCF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<bcf:controlfile version="3.10" bltxversion="3.19" xmlns:bcf="https://sourceforge.net/projects/biblatex">
+  <bcf:options component="biber" type="global">
+    <bcf:option type="singlevalued">
+      <bcf:key>mincrossrefs</bcf:key>
+      <bcf:value>1</bcf:value>
+    </bcf:option>
+  </bcf:options>
+  <bcf:inheritance>
+    <bcf:defaults inherit_all="true" override_target="false">
+    </bcf:defaults>
+    <bcf:inherit>
+      <bcf:type_pair source="mvbook" target="inbook"/>
+      <bcf:field source="title" target="maintitle"/>
+    </bcf:inherit>
+  </bcf:inheritance>
+  <bcf:section number="0">
+    <bcf:citekey order="1" intorder="1">a</bcf:citekey>
+    <bcf:citekey order="2" intorder="1">b</bcf:citekey>
+  </bcf:section>
+  <bcf:section number="1">
+    <bcf:citekey order="1" intorder="1">*</bcf:citekey>
+  </bcf:section>
+</bcf:controlfile>"#;
+
+    #[test]
+    fn keys() {
+        let control = Control::parse(BCF).unwrap();
+        assert_eq!(control.mincrossrefs, 1);
+        assert_eq!(control.minxrefs, 2);
+        assert!(!control.custom_inheritance);
+        let custom = BCF.replace(
+            r#"<bcf:defaults inherit_all="true" override_target="false">"#,
+            r#"<bcf:defaults inherit_all="true" override_target="false"><bcf:type_pair source="*" target="inbook" inherit_all="false"/>"#,
+        );
+        assert!(Control::parse(&custom).unwrap().custom_inheritance);
+        let bib = Bibliography::parse(
+            r#"
+            @inproceedings{a, crossref={conf}, xref={book}}
+            @inproceedings{b, xref={book}}
+            @proceedings{conf, crossref={series}}
+            @book{book, title={B}}
+            @mvproceedings{series, title={S}}
+            @misc{c, title={C}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            control.keys(&bib),
+            ["a", "b", "book", "conf", "series"]
+                .map(String::from)
+                .into()
+        );
+    }
+}
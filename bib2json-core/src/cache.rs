@@ -0,0 +1,178 @@
+//! On-disk cache for the results of remote lookups, like DOI or title queries.
+//!
+//! The results are stored with the time of the lookup in a JSON file, so
+//! repeated conversions neither query the services again nor need network
+//! access.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A cached result.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Cached {
+    /// Seconds since the Unix epoch.
+    fetched: u64,
+    value: Value,
+}
+
+/// Results of earlier lookups, keyed by service and query.
+#[derive(Debug, Default)]
+pub struct Cache {
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, Cached>,
+    ttl: Option<Duration>,
+    changed: bool,
+}
+
+/// The current time in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl Cache {
+    /// A cache that is not stored.
+    pub fn memory() -> Self {
+        Cache::default()
+    }
+
+    /// Load the cache from a file, which does not have to exist yet. Files
+    /// that cannot be parsed are reported and replaced.
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let entries = read(&path)?;
+        Ok(Cache {
+            path: Some(path),
+            entries,
+            ..Default::default()
+        })
+    }
+
+    /// `bib2json` in `$XDG_CACHE_HOME` or `~/.cache`.
+    pub fn dir() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(dir.join("bib2json"))
+    }
+
+    /// `enrich.json` in [`Cache::dir`].
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Self::dir()?.join("enrich.json"))
+    }
+
+    /// Treat results older than the time to live as missing, they are still
+    /// available with [`Cache::get_stale`].
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// A result that is not older than the time to live.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let cached = self.entries.get(key)?;
+        let fresh = self
+            .ttl
+            .is_none_or(|ttl| now().saturating_sub(cached.fetched) < ttl.as_secs());
+        fresh.then_some(&cached.value)
+    }
+
+    /// A result of any age.
+    pub fn get_stale(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).map(|cached| &cached.value)
+    }
+
+    /// Store a new result.
+    pub fn insert(&mut self, key: String, value: Value) {
+        let fetched = now();
+        self.entries.insert(key, Cached { fetched, value });
+        self.changed = true;
+    }
+
+    /// Store the cache, if it is backed by a file and has new results.
+    ///
+    /// Results that other processes stored in the meantime are kept, and the
+    /// file is replaced at once, so that concurrent conversions neither see
+    /// a partly written file nor lose their results.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_ref().filter(|_| self.changed) else {
+            return Ok(());
+        };
+        let dir = path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        let mut entries = read(path)?;
+        entries.extend(self.entries.clone());
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+        std::fs::write(&temp, serde_json::to_string(&entries)?)?;
+        std::fs::rename(&temp, path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })
+    }
+}
+
+/// The results in a cache file, none if it does not exist or cannot be parsed.
+fn read(path: &Path) -> io::Result<BTreeMap<String, Cached>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("{}: ignoring the invalid cache: {e}", path.display());
+            BTreeMap::new()
+        })),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::Cache;
+
+    #[test]
+    fn ttl() {
+        let path = std::env::temp_dir().join(format!("bib2json-cache-{}.json", std::process::id()));
+        let mut cache = Cache::open(path.clone()).unwrap();
+        cache.insert("doi:10.1000/xyz".into(), json!({"id": 1}));
+        cache.save().unwrap();
+
+        let cache = Cache::open(path.clone()).unwrap();
+        assert_eq!(cache.get("doi:10.1000/xyz"), Some(&json!({"id": 1})));
+        let cache = cache.with_ttl(Duration::ZERO);
+        assert_eq!(cache.get("doi:10.1000/xyz"), None);
+        assert_eq!(cache.get_stale("doi:10.1000/xyz"), Some(&json!({"id": 1})));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save() {
+        let path = std::env::temp_dir().join(format!("bib2json-save-{}.json", std::process::id()));
+        std::fs::write(&path, "{ not json").unwrap();
+        let mut first = Cache::open(path.clone()).unwrap();
+        assert_eq!(first.get_stale("a"), None);
+        let mut second = Cache::open(path.clone()).unwrap();
+        first.insert("a".into(), json!(1));
+        second.insert("b".into(), json!(2));
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let cache = Cache::open(path.clone()).unwrap();
+        assert_eq!(cache.get("a"), Some(&json!(1)));
+        assert_eq!(cache.get("b"), Some(&json!(2)));
+        let dir = path.parent().unwrap();
+        let temp = std::fs::read_dir(dir).unwrap().flatten().any(|f| {
+            let name = f.file_name().to_string_lossy().into_owned();
+            name.starts_with(".bib2json-save-") && name.ends_with(".tmp")
+        });
+        assert!(!temp);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+//! Mapping of converted entries to `CITATION.cff` files, in the Citation File
+//! Format 1.2.0.
+//!
+//! The entry describes the software or dataset itself and, with all details,
+//! becomes its `preferred-citation`.
+
+use serde::Serialize;
+
+use crate::{date, SRAEntry, SRAPerson};
+
+/// Maps biblatex entry types to CFF reference types, other types are `generic`.
+const TYPES: &[(&str, &str)] = &[
+    ("article", "article"),
+    ("book", "book"),
+    ("inbook", "book"),
+    ("incollection", "book"),
+    ("inproceedings", "conference-paper"),
+    ("proceedings", "proceedings"),
+    ("manual", "manual"),
+    ("report", "report"),
+    ("techreport", "report"),
+    ("thesis", "thesis"),
+    ("phdthesis", "thesis"),
+    ("mastersthesis", "thesis"),
+    ("software", "software"),
+    ("dataset", "data"),
+    ("online", "website"),
+    ("patent", "patent"),
+    ("unpublished", "unpublished"),
+];
+
+/// A person, an author of the software or the reference.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Author {
+    family_names: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    given_names: String,
+}
+
+impl From<&SRAPerson> for Author {
+    fn from(person: &SRAPerson) -> Self {
+        Author {
+            family_names: person.last_name.clone(),
+            given_names: person.first_name.clone(),
+        }
+    }
+}
+
+/// A named venue, like a journal or a publisher.
+#[derive(Serialize, Debug)]
+pub(crate) struct Entity {
+    name: String,
+}
+
+/// The `preferred-citation` of a `CITATION.cff` file.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Reference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: String,
+    authors: Vec<Author>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doi: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    journal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conference: Option<Entity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publisher: Option<Entity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+}
+
+/// A `CITATION.cff` file.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Citation {
+    cff_version: &'static str,
+    message: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: String,
+    authors: Vec<Author>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doi: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_released: Option<String>,
+    preferred_citation: Reference,
+}
+
+/// The citation file for an entry, authors default to the editors.
+pub(crate) fn citation(entry: &SRAEntry) -> Citation {
+    let entry_type = entry.entry_type.to_lowercase();
+    let persons = if entry.authors.is_empty() {
+        &entry.editors
+    } else {
+        &entry.authors
+    };
+    let authors = || persons.iter().map(Author::from).collect();
+    let title = entry.get("title").unwrap_or_default().to_owned();
+    let doi = entry
+        .get("doi")
+        .map(|d| crate::strip_doi_prefix(d).to_owned());
+    let field = |name| entry.get(name).map(str::to_owned);
+    let mut pages = entry
+        .get("pages")
+        .into_iter()
+        .flat_map(|p| p.split(['-', '–']))
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_owned);
+    let container = ["journaltitle", "journal", "booktitle"]
+        .iter()
+        .find_map(|f| field(f));
+
+    let kind = TYPES
+        .iter()
+        .find(|(t, _)| *t == entry_type)
+        .map_or("generic", |(_, cff)| cff);
+    let reference = Reference {
+        kind,
+        title: title.clone(),
+        authors: authors(),
+        doi: doi.clone(),
+        url: field("url"),
+        year: entry
+            .date_sort
+            .as_deref()
+            .and_then(|d| date::sort_year(d).parse().ok()),
+        journal: container.clone().filter(|_| kind == "article"),
+        conference: container
+            .filter(|_| kind == "conference-paper")
+            .map(|name| Entity { name }),
+        publisher: field("publisher").map(|name| Entity { name }),
+        volume: field("volume"),
+        issue: field("number"),
+        start: pages.next(),
+        end: pages.next(),
+    };
+    Citation {
+        cff_version: "1.2.0",
+        message: "If you use this software, please cite it as below.",
+        kind: if entry_type == "dataset" {
+            "dataset"
+        } else {
+            "software"
+        },
+        title,
+        authors: authors(),
+        doi,
+        date_released: entry.date_sort.clone().filter(|d| !d.ends_with("-00")),
+        preferred_citation: reference,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::citation;
+    use crate::SRAEntry;
+
+    #[test]
+    fn software_paper() {
+        let bib = Bibliography::parse(
+            "@inproceedings{foo, author={Doe, Jane and Max Müller}, title={Lorem: A Tool}, \
+             booktitle={Proceedings of Dolor}, year=2020, pages={10--20}, doi={10.1000/xyz}}",
+        )
+        .unwrap();
+        let cff = citation(&SRAEntry::from(bib.get("foo").unwrap(), &bib));
+        let expected = r#"cff-version: 1.2.0
+message: If you use this software, please cite it as below.
+type: software
+title: 'Lorem: A Tool'
+authors:
+- family-names: Doe
+  given-names: Jane
+- family-names: Müller
+  given-names: Max
+doi: 10.1000/xyz
+preferred-citation:
+  type: conference-paper
+  title: 'Lorem: A Tool'
+  authors:
+  - family-names: Doe
+    given-names: Jane
+  - family-names: Müller
+    given-names: Max
+  doi: 10.1000/xyz
+  year: 2020
+  conference:
+    name: Proceedings of Dolor
+  start: '10'
+  end: '20'
+"#;
+        assert_eq!(serde_yaml::to_string(&cff).unwrap(), expected);
+    }
+}
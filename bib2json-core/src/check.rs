@@ -0,0 +1,249 @@
+//! Consistency checks on parsed bibliographies.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use biblatex::{Bibliography, ChunksExt};
+
+use crate::SRAEntry;
+
+/// A problem that prevents an entry from being converted correctly.
+#[derive(Debug)]
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// Check that all crossref and xref targets of the entries exist.
+pub fn check(bib: &Bibliography) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for entry in bib.iter() {
+        match entry.parents() {
+            Ok(parents) => problems.extend(
+                parents
+                    .into_iter()
+                    .filter(|id| bib.get(id).is_none())
+                    .map(|id| Problem {
+                        key: entry.key.clone(),
+                        message: format!("crossref target `{id}` not found"),
+                    }),
+            ),
+            Err(e) => problems.push(Problem {
+                key: entry.key.clone(),
+                message: format!("malformed crossref: {e}"),
+            }),
+        }
+    }
+    problems
+}
+
+/// Entry types that are expected to have authors.
+const AUTHORED: &[&str] = &["article", "inproceedings", "incollection"];
+
+/// The problems of [`check`] and anomalies that still convert, like articles
+/// without authors.
+pub fn strict(bib: &Bibliography) -> Vec<Problem> {
+    let mut problems = check(bib);
+    for entry in bib.iter() {
+        let entry_type = entry.entry_type.to_string().to_lowercase();
+        if AUTHORED.contains(&entry_type.as_str())
+            && entry.author().map_or(true, |authors| authors.is_empty())
+        {
+            problems.push(Problem {
+                key: entry.key.clone(),
+                message: format!("{entry_type} without authors"),
+            });
+        }
+    }
+    problems
+}
+
+/// Report the fields of a converted entry that are missing or empty, own and
+/// inherited fields count.
+pub fn require(entry: &SRAEntry, fields: &[String]) -> Option<Problem> {
+    let missing: Vec<String> = fields
+        .iter()
+        .filter(|field| entry.get(field).is_none_or(|v| v.trim().is_empty()))
+        .map(|field| format!("`{field}`"))
+        .collect();
+    (!missing.is_empty()).then(|| Problem {
+        key: entry.id.clone(),
+        message: format!("missing required {}", missing.join(", ")),
+    })
+}
+
+/// Rules for [`lint`], all off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// Fields that entries of a type must have, by lowercase type or `*`
+    /// for all types.
+    pub required: Vec<(String, Vec<String>)>,
+    /// DOIs must look like `10.1000/xyz`, with an optional resolver prefix.
+    pub doi: bool,
+    /// The plausible years of the entries.
+    pub years: Option<RangeInclusive<i32>>,
+    /// Keys must not differ only in case, BibTeX treats them as the same.
+    pub case_keys: bool,
+}
+
+/// Whether a DOI has a prefix like `10.1000` and a suffix without whitespace.
+fn valid_doi(doi: &str) -> bool {
+    let Some((registrant, suffix)) = doi.strip_prefix("10.").and_then(|d| d.split_once('/')) else {
+        return false;
+    };
+    registrant.len() >= 4
+        && registrant.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && !suffix.is_empty()
+        && !suffix.contains(char::is_whitespace)
+}
+
+/// Check the entries against the rules.
+pub fn lint(bib: &Bibliography, rules: &Rules) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut keys: HashMap<String, &str> = HashMap::new();
+    for e in bib.iter() {
+        let problem = |message: String| Problem {
+            key: e.key.clone(),
+            message,
+        };
+        let entry_type = e.entry_type.to_string().to_lowercase();
+        let required: Vec<String> = rules
+            .required
+            .iter()
+            .filter(|(t, _)| *t == "*" || *t == entry_type)
+            .flat_map(|(_, fields)| fields.iter().cloned())
+            .collect();
+        if !required.is_empty() {
+            problems.extend(require(&SRAEntry::from(e, bib), &required));
+        }
+        if let Some(doi) = e.get("doi").filter(|_| rules.doi) {
+            let doi = doi.format_verbatim();
+            if !valid_doi(crate::strip_doi_prefix(&doi)) {
+                problems.push(problem(format!("malformed DOI `{doi}`")));
+            }
+        }
+        let year = crate::date::sort_key(e).map(|key| crate::date::parts(&key).0);
+        if let (Some(year), Some(years)) = (year, &rules.years) {
+            if !years.contains(&year) {
+                problems.push(problem(format!(
+                    "year {year} outside of {}..{}",
+                    years.start(),
+                    years.end()
+                )));
+            }
+        }
+        if rules.case_keys {
+            match keys.get(&e.key.to_lowercase()) {
+                Some(other) if *other != e.key => {
+                    problems.push(problem(format!("key differs from `{other}` only in case")))
+                }
+                _ => {
+                    keys.insert(e.key.to_lowercase(), &e.key);
+                }
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{check, lint, require, strict, Rules};
+    use crate::SRAEntry;
+
+    #[test]
+    fn missing_crossref() {
+        let bib = r#"
+            @inproceedings{foo,
+                title = {Lorem Ipsum},
+                crossref = {ref},
+            }
+            @inproceedings{bar,
+                title = {Dolor},
+                xref = {foo},
+            }
+        "#;
+        let parsed = Bibliography::parse(bib).unwrap();
+        let problems = check(&parsed);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].key, "foo");
+        assert_eq!(
+            problems[0].to_string(),
+            "foo: crossref target `ref` not found"
+        );
+    }
+
+    #[test]
+    fn strict_problems() {
+        let bib = Bibliography::parse(
+            "@article{a, title={A}}\n@inproceedings{b, author={Doe, Jane}}\n@misc{c, crossref={d}}",
+        )
+        .unwrap();
+        let problems: Vec<String> = strict(&bib).iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "c: crossref target `d` not found",
+                "a: article without authors"
+            ]
+        );
+    }
+
+    #[test]
+    fn required_fields() {
+        let bib =
+            Bibliography::parse("@misc{a, date={2020}, doi={}, crossref={b}}\n@misc{b, note={N}}")
+                .unwrap();
+        let a = SRAEntry::from(bib.get("a").unwrap(), &bib);
+        let fields = ["year", "note", "doi", "title"].map(String::from);
+        assert_eq!(
+            require(&a, &fields).unwrap().to_string(),
+            "a: missing required `doi`, `title`"
+        );
+        assert!(require(&a, &fields[..2]).is_none());
+    }
+
+    #[test]
+    fn rules() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, title={A}, year=2020, doi={https://doi.org/10.1000/xyz}}
+            @article{A, author={Doe, Jane}, title={B}, year=1066, doi={10.1000}}
+            @misc{c, year=2021}
+            @misc{d, title={D}, date={-0044-03-15}}
+            "#,
+        )
+        .unwrap();
+        let rules = Rules {
+            required: vec![
+                ("article".into(), vec!["author".into()]),
+                ("*".into(), vec!["title".into()]),
+            ],
+            doi: true,
+            years: Some(1900..=2030),
+            case_keys: true,
+        };
+        let problems: Vec<String> = lint(&bib, &rules).iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "a: missing required `author`",
+                "A: malformed DOI `10.1000`",
+                "A: year 1066 outside of 1900..2030",
+                "A: key differs from `a` only in case",
+                "c: missing required `title`",
+                "d: year -44 outside of 1900..2030",
+            ]
+        );
+        assert!(lint(&bib, &Rules::default()).is_empty());
+    }
+}
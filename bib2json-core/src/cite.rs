@@ -0,0 +1,260 @@
+//! Citations in documents, for converting only the cited entries.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use biblatex::Bibliography;
+
+use crate::input::extension;
+
+/// A document format with citations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    /// Pandoc Markdown: `[@key]`, `@key` and `@{key}`
+    Markdown,
+    /// Typst: `@key` and `#cite(<key>)`
+    Typst,
+    /// LaTeX sources: `\cite{key,...}`, `\parencite[p. 3]{key}`,
+    /// `\textcites{a}{b}` and the other citation commands
+    Latex,
+    /// LaTeX `.aux` files: `\citation{key,...}` from BibTeX and
+    /// `\abx@aux@cite{refsection}{key}` from biblatex, `*` for `\nocite{*}`
+    Aux,
+}
+
+impl Document {
+    /// Infer the format from the file extension, Markdown for unknown extensions.
+    pub fn from_path(path: &Path) -> Self {
+        match extension(path).as_deref() {
+            Some("typ") => Document::Typst,
+            Some("tex" | "ltx" | "sty" | "cls") => Document::Latex,
+            Some("aux") => Document::Aux,
+            _ => Document::Markdown,
+        }
+    }
+
+    /// The keys cited in the document.
+    pub fn citations(self, content: &str) -> BTreeSet<String> {
+        match self {
+            Document::Aux => return aux_citations(content),
+            Document::Latex => return latex_citations(content),
+            _ => {}
+        }
+        let mut keys = BTreeSet::new();
+        let mut prev = None;
+        for (i, c) in content.char_indices() {
+            let after_word = prev.is_some_and(char::is_alphanumeric);
+            prev = Some(c);
+            if c != '@' || after_word {
+                continue;
+            }
+            let rest = &content[i + 1..];
+            let key = match self {
+                Document::Markdown if rest.starts_with('{') => {
+                    rest[1..].split_once('}').map_or("", |(key, _)| key)
+                }
+                Document::Markdown => key(rest, |c| ":.#$%&-+?<>~/".contains(c)),
+                Document::Typst => key(rest, |c| "-:.".contains(c)),
+                Document::Aux | Document::Latex => unreachable!(),
+            };
+            if !key.is_empty() {
+                keys.insert(key.to_string());
+            }
+        }
+        if self == Document::Typst {
+            for (i, _) in content.match_indices("cite(<") {
+                if let Some((key, _)) = content[i + 6..].split_once('>') {
+                    keys.insert(key.to_string());
+                }
+            }
+        }
+        keys
+    }
+}
+
+/// The braced arguments at the start of `text`.
+fn arguments(text: &str) -> Vec<&str> {
+    let mut arguments = Vec::new();
+    let mut rest = text;
+    while let Some(argument) = rest.strip_prefix('{') {
+        let Some((argument, after)) = argument.split_once('}') else {
+            break;
+        };
+        arguments.push(argument);
+        rest = after;
+    }
+    arguments
+}
+
+/// The keys of the citation commands in LaTeX sources, without comments.
+fn latex_citations(content: &str) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for line in content.lines() {
+        // A `%` starts a comment unless escaped
+        let end = line
+            .match_indices('%')
+            .find(|(i, _)| !line[..*i].ends_with('\\'))
+            .map_or(line.len(), |(i, _)| i);
+        let line = &line[..end];
+        for (i, _) in line.match_indices('\\') {
+            let rest = &line[i + 1..];
+            let name_end = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let name = &rest[..name_end];
+            if !name.to_ascii_lowercase().contains("cite") {
+                continue;
+            }
+            // Multicite commands like `\textcites` take several key lists
+            let multi = name.ends_with("cites");
+            let mut rest = rest[name_end..].trim_start().trim_start_matches('*');
+            loop {
+                rest = rest.trim_start();
+                // Skip optional pre- and postnotes
+                let close = match rest.chars().next() {
+                    Some('[') => ']',
+                    Some('(') => ')',
+                    Some('{') => {
+                        let Some((list, after)) = rest[1..].split_once('}') else {
+                            break;
+                        };
+                        let list = list.split(',').map(str::trim).filter(|k| !k.is_empty());
+                        keys.extend(list.map(str::to_owned));
+                        rest = after;
+                        match multi {
+                            true => continue,
+                            false => break,
+                        }
+                    }
+                    _ => break,
+                };
+                match rest.split_once(close) {
+                    Some((_, after)) => rest = after,
+                    None => break,
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// The keys of the citation commands in a LaTeX `.aux` file.
+fn aux_citations(content: &str) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for line in content.lines().map(str::trim_start) {
+        if let Some(rest) = line.strip_prefix("\\citation") {
+            let keys_of = arguments(rest).into_iter().flat_map(|a| a.split(','));
+            keys.extend(
+                keys_of
+                    .map(|k| k.trim().to_owned())
+                    .filter(|k| !k.is_empty()),
+            );
+        } else if let Some(rest) = line.strip_prefix("\\abx@aux@cite") {
+            // Older biblatex versions omit the refsection
+            if let Some(key) = arguments(rest).last() {
+                keys.insert(key.trim().to_owned());
+            }
+        }
+    }
+    keys
+}
+
+/// The files that a LaTeX `.aux` file includes with `\@input{file}`, the
+/// ones of chapters included with `\include`.
+pub fn aux_includes(content: &str) -> Vec<&str> {
+    content
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("\\@input"))
+        .filter_map(|rest| arguments(rest).first().copied())
+        .collect()
+}
+
+/// The key at the start of `text`, made of alphanumerics, `_` and internal punctuation.
+fn key(text: &str, punctuation: impl Fn(char) -> bool) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || punctuation(c)))
+        .unwrap_or(text.len());
+    text[..end].trim_end_matches(|c: char| !(c.is_alphanumeric() || c == '_'))
+}
+
+/// Add the crossref and xref parents of the entries, recursively. Aliases
+/// from `ids` are replaced by the keys of their entries.
+pub fn with_parents(bib: &Bibliography, keys: BTreeSet<String>) -> BTreeSet<String> {
+    let mut keys: BTreeSet<String> = keys
+        .into_iter()
+        .map(|key| bib.get(&key).map_or(key, |e| e.key.clone()))
+        .collect();
+    let mut todo: Vec<String> = keys.iter().cloned().collect();
+    while let Some(key) = todo.pop() {
+        let parents = bib.get(&key).and_then(|e| e.parents().ok());
+        for parent in parents.into_iter().flatten() {
+            if keys.insert(parent.clone()) {
+                todo.push(parent);
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{aux_includes, with_parents, Document};
+
+    #[test]
+    fn citations() {
+        let md = "As [@foo, p. 3; -@bar] and @{baz.2} show, see me@example.org or @qux.";
+        assert_eq!(
+            Document::Markdown.citations(md),
+            ["bar", "baz.2", "foo", "qux"].map(String::from).into()
+        );
+        let typ = "See @foo[p. 3] and #cite(<bar:2020>), mail me@example.org.";
+        assert_eq!(
+            Document::Typst.citations(typ),
+            ["bar:2020", "foo"].map(String::from).into()
+        );
+    }
+
+    #[test]
+    fn latex() {
+        let tex = r"As \cite{foo, bar} and \parencite[see][p.~3]{baz} show,
+\textcites(pre)(post)[p. 1]{a}[p. 2]{b} and \citeauthor*{c}.
+% \cite{commented}
+50\% of \nocite{d} \emph{x}{y} \cite{e}{not}";
+        assert_eq!(
+            Document::Latex.citations(tex),
+            ["a", "b", "bar", "baz", "c", "d", "e", "foo"]
+                .map(String::from)
+                .into()
+        );
+    }
+
+    #[test]
+    fn aux() {
+        let aux = r"\relax
+\citation{foo,bar}
+\citation{ baz }
+\abx@aux@cite{0}{qux}
+\abx@aux@cite{old}
+\@input{chapter1.aux}
+\bibdata{refs}";
+        assert_eq!(
+            Document::Aux.citations(aux),
+            ["bar", "baz", "foo", "old", "qux"].map(String::from).into()
+        );
+        assert_eq!(aux_includes(aux), ["chapter1.aux"]);
+    }
+
+    #[test]
+    fn parents() {
+        let bib = Bibliography::parse(
+            "@inproceedings{foo, title={Foo}, crossref={conf}}\n@proceedings{conf, title={Conf}}\n@misc{bar, title={Bar}}",
+        )
+        .unwrap();
+        assert_eq!(
+            with_parents(&bib, ["foo".to_string()].into()),
+            ["conf", "foo"].map(String::from).into()
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Transparent gzip and, with the `zstd` feature, Zstandard compression of
+//! inputs and outputs.
+//!
+//! Compressed inputs are recognized by their content, so the extension does
+//! not matter.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// A compression format of outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Codec {
+    /// The compression of an output file by its `.gz` or `.zst` extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" => Some(Codec::Gzip),
+            #[cfg(feature = "zstd")]
+            "zst" => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Decompress the content if it is compressed.
+pub fn decompress(content: Vec<u8>) -> io::Result<Vec<u8>> {
+    if content.starts_with(GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(content.as_slice()).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    } else if content.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return zstd::decode_all(content.as_slice());
+        #[cfg(not(feature = "zstd"))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zstd-compressed input is not supported, decompress it with `zstd -d` first",
+        ))
+    } else {
+        Ok(content)
+    }
+}
+
+/// A writer that compresses or passes through, which has to be finished to
+/// write the end of the compressed stream.
+pub enum Writer<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Writer<W> {
+    /// Write the end of the compressed stream and return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Writer::Plain(w) => Ok(w),
+            Writer::Gzip(w) => w.finish(),
+            #[cfg(feature = "zstd")]
+            Writer::Zstd(w) => w.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::Plain(w) => w.write(buf),
+            Writer::Gzip(w) => w.write(buf),
+            #[cfg(feature = "zstd")]
+            Writer::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Plain(w) => w.flush(),
+            Writer::Gzip(w) => w.flush(),
+            #[cfg(feature = "zstd")]
+            Writer::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+/// A writer that compresses with the given codec, if any.
+pub fn writer<W: Write>(writer: W, codec: Option<Codec>) -> io::Result<Writer<W>> {
+    Ok(match codec {
+        None => Writer::Plain(writer),
+        Some(Codec::Gzip) => Writer::Gzip(GzEncoder::new(writer, Compression::default())),
+        #[cfg(feature = "zstd")]
+        Some(Codec::Zstd) => Writer::Zstd(zstd::Encoder::new(writer, 0)?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::{decompress, writer, Codec};
+
+    #[test]
+    fn roundtrip() {
+        let mut compressed = writer(Vec::new(), Some(Codec::Gzip)).unwrap();
+        compressed.write_all(b"@misc{a, title={A}}").unwrap();
+        let compressed = compressed.finish().unwrap();
+        assert_ne!(compressed, b"@misc{a, title={A}}");
+        assert_eq!(decompress(compressed).unwrap(), b"@misc{a, title={A}}");
+        assert_eq!(decompress(b"plain".to_vec()).unwrap(), b"plain");
+        #[cfg(not(feature = "zstd"))]
+        assert!(decompress(vec![0x28, 0xb5, 0x2f, 0xfd, 0]).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let mut compressed =
+            writer(Vec::new(), Codec::from_path(Path::new("refs.json.zst"))).unwrap();
+        compressed.write_all(b"@misc{a, title={A}}").unwrap();
+        let compressed = compressed.finish().unwrap();
+        assert_eq!(decompress(compressed).unwrap(), b"@misc{a, title={A}}");
+    }
+
+    #[test]
+    fn codec() {
+        assert_eq!(
+            Codec::from_path(Path::new("refs.json.GZ")),
+            Some(Codec::Gzip)
+        );
+        assert_eq!(Codec::from_path(Path::new("refs.json")), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Mapping of converted entries to CSL-JSON items, as read by pandoc and citeproc.
+
+use serde_json::{json, Map, Value};
+
+use crate::date::{DateRange, SRADate};
+use crate::{SRAEntry, SRAPerson};
+
+/// Maps biblatex entry types to CSL types, other types become `document`.
+const TYPES: &[(&str, &str)] = &[
+    ("article", "article-journal"),
+    ("book", "book"),
+    ("mvbook", "book"),
+    ("booklet", "pamphlet"),
+    ("collection", "book"),
+    ("mvcollection", "book"),
+    ("dataset", "dataset"),
+    ("inbook", "chapter"),
+    ("incollection", "chapter"),
+    ("inproceedings", "paper-conference"),
+    ("conference", "paper-conference"),
+    ("manual", "report"),
+    ("mastersthesis", "thesis"),
+    ("online", "webpage"),
+    ("patent", "patent"),
+    ("periodical", "periodical"),
+    ("phdthesis", "thesis"),
+    ("proceedings", "book"),
+    ("mvproceedings", "book"),
+    ("report", "report"),
+    ("techreport", "report"),
+    ("software", "software"),
+    ("thesis", "thesis"),
+    ("unpublished", "manuscript"),
+];
+
+/// Maps biblatex fields to CSL variables, the first present field wins.
+const FIELDS: &[(&str, &[&str])] = &[
+    ("title", &["title"]),
+    ("container-title", &["journaltitle", "journal", "booktitle"]),
+    ("collection-title", &["series"]),
+    ("event-title", &["eventtitle"]),
+    (
+        "publisher",
+        &["publisher", "institution", "school", "organization"],
+    ),
+    ("publisher-place", &["location", "address"]),
+    ("volume", &["volume"]),
+    ("issue", &["number", "issue"]),
+    ("edition", &["edition"]),
+    ("DOI", &["doi"]),
+    ("URL", &["url"]),
+    ("ISBN", &["isbn"]),
+    ("ISSN", &["issn"]),
+    ("PMID", &["pmid"]),
+    ("abstract", &["abstract"]),
+    ("note", &["note"]),
+    ("language", &["langid", "language"]),
+    ("keyword", &["keywords"]),
+    ("genre", &["type"]),
+];
+
+/// The CSL type of a biblatex entry type.
+fn item_type(entry_type: &str) -> &'static str {
+    let entry_type = entry_type.to_lowercase();
+    TYPES
+        .iter()
+        .find(|(t, _)| *t == entry_type)
+        .map_or("document", |(_, csl)| csl)
+}
+
+/// CSL names of persons.
+fn names(persons: &[SRAPerson]) -> Value {
+    persons
+        .iter()
+        .map(|p| {
+            let mut name = Map::new();
+            name.insert("family".into(), p.last_name.clone().into());
+            if !p.first_name.is_empty() {
+                name.insert("given".into(), p.first_name.clone().into());
+            }
+            Value::Object(name)
+        })
+        .collect()
+}
+
+/// The parts of a date, without missing months and days.
+fn parts(date: &SRADate) -> Value {
+    std::iter::once(date.year)
+        .chain(date.month.map(i32::from))
+        .chain(date.day.map(i32::from))
+        .collect()
+}
+
+/// The `issued` date from a range or the date sort key.
+fn issued(entry: &SRAEntry) -> Option<Value> {
+    let dates: Vec<SRADate> = match (&entry.date_range, &entry.date_sort) {
+        (Some(DateRange { start, end }), _) => start.iter().chain(end).copied().collect(),
+        (None, Some(key)) => {
+            let mut parts = key.split('-').map(|p| p.parse::<i32>().unwrap_or(0));
+            let year = parts.next()?;
+            let mut part = || {
+                parts
+                    .next()
+                    .and_then(|p| u8::try_from(p).ok())
+                    .filter(|&p| p > 0)
+            };
+            vec![SRADate {
+                year,
+                month: part(),
+                day: part(),
+            }]
+        }
+        (None, None) => return None,
+    };
+    (!dates.is_empty())
+        .then(|| json!({ "date-parts": dates.iter().map(parts).collect::<Vec<_>>() }))
+}
+
+/// The CSL-JSON item of an entry.
+pub fn item(entry: &SRAEntry) -> Value {
+    let mut item = Map::new();
+    item.insert("id".into(), entry.id.clone().into());
+    item.insert("type".into(), item_type(&entry.entry_type).into());
+    if !entry.authors.is_empty() {
+        item.insert("author".into(), names(&entry.authors));
+    }
+    if !entry.editors.is_empty() {
+        item.insert("editor".into(), names(&entry.editors));
+    }
+    if let Some(issued) = issued(entry) {
+        item.insert("issued".into(), issued);
+    }
+    if let Some(pages) = entry.get("pages") {
+        item.insert(
+            "page".into(),
+            pages.replace("--", "-").replace('–', "-").into(),
+        );
+    }
+    for (variable, fields) in FIELDS {
+        if let Some(value) = fields.iter().find_map(|f| entry.get(f)) {
+            item.insert((*variable).into(), value.into());
+        }
+    }
+    Value::Object(item)
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+    use serde_json::json;
+
+    use super::item;
+    use crate::SRAEntry;
+
+    #[test]
+    fn article() {
+        let bib = Bibliography::parse(
+            r#"@article{foo,
+                author = {Doe, Jane and Smith, John},
+                title = {Lorem Ipsum},
+                journal = {Journal of Dolor},
+                year = 2020, month = sep,
+                volume = 3, number = 2, pages = {10--20},
+                doi = {10.1000/xyz},
+            }"#,
+        )
+        .unwrap();
+        let entry = SRAEntry::from(bib.get("foo").unwrap(), &bib);
+        assert_eq!(
+            item(&entry),
+            json!({
+                "id": "foo",
+                "type": "article-journal",
+                "author": [{"family": "Doe", "given": "Jane"}, {"family": "Smith", "given": "John"}],
+                "issued": {"date-parts": [[2020, 9]]},
+                "title": "Lorem Ipsum",
+                "container-title": "Journal of Dolor",
+                "volume": "3",
+                "issue": "2",
+                "page": "10-20",
+                "DOI": "10.1000/xyz",
+            })
+        );
+    }
+}
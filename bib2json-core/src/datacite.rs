@@ -0,0 +1,181 @@
+//! Mapping of converted entries to DataCite Metadata Schema 4 records, for
+//! registering DOIs of datasets, software and publications.
+
+use crate::xml::Xml;
+use crate::{date, SRAEntry, SRAPerson};
+
+/// The DataCite kernel 4 namespace.
+pub(crate) const NAMESPACE: &str = "http://datacite.org/schema/kernel-4";
+
+/// Maps biblatex entry types to DataCite resource types, other types are `Text`.
+const TYPES: &[(&str, &str)] = &[
+    ("article", "JournalArticle"),
+    ("book", "Book"),
+    ("mvbook", "Book"),
+    ("inbook", "BookChapter"),
+    ("incollection", "BookChapter"),
+    ("inproceedings", "ConferencePaper"),
+    ("proceedings", "ConferenceProceeding"),
+    ("phdthesis", "Dissertation"),
+    ("thesis", "Dissertation"),
+    ("report", "Report"),
+    ("techreport", "Report"),
+    ("software", "Software"),
+    ("dataset", "Dataset"),
+    ("online", "Text"),
+    ("unpublished", "Preprint"),
+];
+
+/// Creators or contributors with their name parts.
+fn persons(xml: &mut Xml, persons: &[SRAPerson], kind: &str, contributor_type: Option<&str>) {
+    let attrs: Vec<(&str, &str)> = contributor_type
+        .map(|t| ("contributorType", t))
+        .into_iter()
+        .collect();
+    for person in persons {
+        xml.open(kind, &attrs);
+        let name = format!("{kind}Name");
+        xml.element(&name, &[("nameType", "Personal")], &person.to_bibtex());
+        if !person.first_name.is_empty() {
+            xml.element("givenName", &[], &person.first_name);
+        }
+        xml.element("familyName", &[], &person.last_name);
+        xml.close(kind);
+    }
+}
+
+/// Write the DataCite record of an entry, `None` for entries without a DOI.
+pub(crate) fn resource(xml: &mut Xml, entry: &SRAEntry) -> Option<()> {
+    let doi = crate::strip_doi_prefix(entry.get("doi")?);
+    let entry_type = entry.entry_type.to_lowercase();
+    xml.open("resource", &[("xmlns", NAMESPACE)]);
+    xml.element("identifier", &[("identifierType", "DOI")], doi);
+
+    xml.open("creators", &[]);
+    if entry.authors.is_empty() {
+        persons(xml, &entry.editors, "creator", None);
+    } else {
+        persons(xml, &entry.authors, "creator", None);
+    }
+    xml.close("creators");
+
+    xml.open("titles", &[]);
+    xml.element("title", &[], entry.get("title").unwrap_or_default());
+    if let Some(subtitle) = entry.get("subtitle") {
+        xml.element("title", &[("titleType", "Subtitle")], subtitle);
+    }
+    xml.close("titles");
+
+    let publisher = ["publisher", "institution", "school", "organization"]
+        .iter()
+        .find_map(|f| entry.get(f))
+        .or_else(|| {
+            ["journaltitle", "journal", "booktitle"]
+                .iter()
+                .find_map(|f| entry.get(f))
+        });
+    xml.element("publisher", &[], publisher.unwrap_or_default());
+    let year = entry.date_sort.as_deref().map_or("", date::sort_year);
+    xml.element("publicationYear", &[], year);
+
+    let general = match entry_type.as_str() {
+        "software" => "Software",
+        "dataset" => "Dataset",
+        _ => "Text",
+    };
+    let specific = TYPES
+        .iter()
+        .find(|(t, _)| *t == entry_type)
+        .map_or(general, |(_, datacite)| datacite);
+    xml.element(
+        "resourceType",
+        &[("resourceTypeGeneral", general)],
+        specific,
+    );
+
+    let keywords = entry.keywords();
+    if !keywords.is_empty() {
+        xml.open("subjects", &[]);
+        for keyword in keywords {
+            xml.element("subject", &[], keyword);
+        }
+        xml.close("subjects");
+    }
+    if !entry.authors.is_empty() && !entry.editors.is_empty() {
+        xml.open("contributors", &[]);
+        persons(xml, &entry.editors, "contributor", Some("Editor"));
+        xml.close("contributors");
+    }
+    if let Some(date) = &entry.date_sort {
+        xml.open("dates", &[]);
+        xml.element("date", &[("dateType", "Issued")], crate::date::iso(date));
+        xml.close("dates");
+    }
+    if let Some(language) = entry.get("langid").or_else(|| entry.get("language")) {
+        xml.element("language", &[], language);
+    }
+    if let Some(url) = entry.get("url") {
+        xml.open("alternateIdentifiers", &[]);
+        xml.element(
+            "alternateIdentifier",
+            &[("alternateIdentifierType", "URL")],
+            url,
+        );
+        xml.close("alternateIdentifiers");
+    }
+    if let Some(version) = entry.get("version") {
+        xml.element("version", &[], version);
+    }
+    if let Some(abstract_) = entry.get("abstract") {
+        xml.open("descriptions", &[]);
+        xml.element("description", &[("descriptionType", "Abstract")], abstract_);
+        xml.close("descriptions");
+    }
+    xml.close("resource");
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::resource;
+    use crate::xml::Xml;
+    use crate::SRAEntry;
+
+    #[test]
+    fn software() {
+        let bib = Bibliography::parse(
+            "@software{foo, author={Doe, Jane}, title={Lorem}, publisher={Zenodo}, \
+             year=2021, version={1.2}, doi={https://doi.org/10.5281/zenodo.1}}\n\
+             @software{bar, title={Bar}}",
+        )
+        .unwrap();
+        let mut xml = Xml::new();
+        assert!(resource(&mut xml, &SRAEntry::from(bib.get("bar").unwrap(), &bib)).is_none());
+        resource(&mut xml, &SRAEntry::from(bib.get("foo").unwrap(), &bib)).unwrap();
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<resource xmlns="http://datacite.org/schema/kernel-4">
+  <identifier identifierType="DOI">10.5281/zenodo.1</identifier>
+  <creators>
+    <creator>
+      <creatorName nameType="Personal">Doe, Jane</creatorName>
+      <givenName>Jane</givenName>
+      <familyName>Doe</familyName>
+    </creator>
+  </creators>
+  <titles>
+    <title>Lorem</title>
+  </titles>
+  <publisher>Zenodo</publisher>
+  <publicationYear>2021</publicationYear>
+  <resourceType resourceTypeGeneral="Software">Software</resourceType>
+  <dates>
+    <date dateType="Issued">2021</date>
+  </dates>
+  <version>1.2</version>
+</resource>
+"#;
+        assert_eq!(xml.finish(), expected);
+    }
+}
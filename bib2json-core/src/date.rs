@@ -0,0 +1,206 @@
+//! Structured values of the biblatex `date` field.
+
+use biblatex::{Date, DateValue, Datetime, Entry, PermissiveType, RetrievalError};
+use serde::{Deserialize, Serialize};
+
+/// A calendar date, months and days start at 1.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SRADate {
+    pub year: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub month: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<u8>,
+}
+
+impl From<Datetime> for SRADate {
+    fn from(date: Datetime) -> Self {
+        SRADate {
+            year: date.year,
+            month: date.month.map(|m| m + 1),
+            day: date.day.map(|d| d + 1),
+        }
+    }
+}
+
+/// A range of dates like `2020-01/2021-06`, open ranges lack the start or end.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DateRange {
+    pub start: Option<SRADate>,
+    pub end: Option<SRADate>,
+}
+
+/// A key for sorting chronologically, like `2023-09-00` or `-0044-03-15`,
+/// missing months and days are `00`. Ranges sort by their start. Compare the
+/// [`parts`], not the strings, as years can be negative or have five digits.
+pub(crate) fn sort_key(e: &Entry) -> Option<String> {
+    let date = match e.date() {
+        Ok(PermissiveType::Typed(date)) => date,
+        _ => return None,
+    };
+    let at = match date.value {
+        DateValue::At(at) | DateValue::After(at) | DateValue::Before(at) => at,
+        DateValue::Between(start, _) => start,
+    };
+    let part = |p: Option<u8>| p.map_or(0, |p| p + 1);
+    let sign = if at.year < 0 { "-" } else { "" };
+    Some(format!(
+        "{sign}{:04}-{:02}-{:02}",
+        at.year.unsigned_abs(),
+        part(at.month),
+        part(at.day)
+    ))
+}
+
+/// The year of a [`sort_key`], like `2023` or `-0044`.
+pub(crate) fn sort_year(sort_key: &str) -> &str {
+    &sort_key[..sort_key.len() - "-MM-DD".len()]
+}
+
+/// The year, month and day of a [`sort_key`], ordered chronologically.
+pub(crate) fn parts(sort_key: &str) -> (i32, u8, u8) {
+    let (year, month_day) = sort_key.split_at(sort_key.len() - "-MM-DD".len());
+    let part = |range: std::ops::Range<usize>| month_day[range].parse().unwrap_or(0);
+    (year.parse().unwrap_or(0), part(1..3), part(4..6))
+}
+
+/// The date fields of the entry that are present but cannot be parsed, and
+/// are thus missing from the converted date.
+pub(crate) fn unparseable(e: &Entry) -> Vec<&'static str> {
+    match e.date() {
+        Ok(PermissiveType::Chunks(_)) | Err(RetrievalError::TypeError(_))
+            if e.get("date").is_some() =>
+        {
+            vec!["date"]
+        }
+        Ok(PermissiveType::Chunks(_)) | Err(RetrievalError::TypeError(_)) => vec!["year"],
+        Ok(PermissiveType::Typed(Date {
+            value: DateValue::At(at),
+            ..
+        })) if e.get("date").is_none() && e.get("month").is_some() && at.month.is_none() => {
+            vec!["month"]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// An ISO 8601 date like `2023-09` from a [`sort_key`], without missing parts.
+pub(crate) fn iso(sort_key: &str) -> &str {
+    sort_key.trim_end_matches("-00")
+}
+
+/// The parsed `date` field of the entry, if it has a valid one.
+pub(crate) fn date(e: &Entry) -> Option<Date> {
+    e.get("date")?;
+    match e.date() {
+        Ok(PermissiveType::Typed(date)) => Some(date),
+        _ => None,
+    }
+}
+
+/// The range of a date, if it is one.
+pub(crate) fn range(date: &Date) -> Option<DateRange> {
+    let (start, end) = match date.value {
+        DateValue::At(_) => return None,
+        DateValue::After(start) => (Some(start), None),
+        DateValue::Before(end) => (None, Some(end)),
+        DateValue::Between(start, end) => (Some(start), Some(end)),
+    };
+    Some(DateRange {
+        start: start.map(SRADate::from),
+        end: end.map(SRADate::from),
+    })
+}
+
+/// The year of a date, ranges become `2020–2021`, `2019–` or `–2019`.
+pub(crate) fn year(date: &Date) -> String {
+    match date.value {
+        DateValue::At(at) => at.year.to_string(),
+        DateValue::After(start) => format!("{}–", start.year),
+        DateValue::Before(end) => format!("–{}", end.year),
+        DateValue::Between(start, end) if start.year == end.year => start.year.to_string(),
+        DateValue::Between(start, end) => format!("{}–{}", start.year, end.year),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{
+        date, iso, parts, range, sort_key, sort_year, unparseable, year, DateRange, SRADate,
+    };
+
+    #[test]
+    fn ranges() {
+        let bib = Bibliography::parse(
+            "@misc{a, date={2020-01/2021-06}}\n@misc{b, date={2019/}}\n@misc{c, date={2018-03-04}}",
+        )
+        .unwrap();
+        let a = date(bib.get("a").unwrap()).unwrap();
+        assert_eq!(
+            range(&a),
+            Some(DateRange {
+                start: Some(SRADate {
+                    year: 2020,
+                    month: Some(1),
+                    day: None
+                }),
+                end: Some(SRADate {
+                    year: 2021,
+                    month: Some(6),
+                    day: None
+                }),
+            })
+        );
+        assert_eq!(year(&a), "2020–2021");
+
+        let b = date(bib.get("b").unwrap()).unwrap();
+        assert_eq!(range(&b).unwrap().end, None);
+        assert_eq!(year(&b), "2019–");
+
+        let c = date(bib.get("c").unwrap()).unwrap();
+        assert_eq!(range(&c), None);
+        assert_eq!(year(&c), "2018");
+    }
+
+    #[test]
+    fn sort_keys() {
+        let bib = Bibliography::parse(
+            "@misc{a, date={2020-01/2021-06}}\n@misc{b, year={2023}, month=sep}\n@misc{c, title={C}}",
+        )
+        .unwrap();
+        assert_eq!(sort_key(bib.get("a").unwrap()).unwrap(), "2020-01-00");
+        assert_eq!(sort_key(bib.get("b").unwrap()).unwrap(), "2023-09-00");
+        assert_eq!(sort_key(bib.get("c").unwrap()), None);
+        assert_eq!(iso("2023-09-00"), "2023-09");
+        assert_eq!(iso("2023-00-00"), "2023");
+    }
+
+    #[test]
+    fn negative_years() {
+        let bib = Bibliography::parse("@misc{a, date={-0044-03-15}}").unwrap();
+        let key = sort_key(bib.get("a").unwrap()).unwrap();
+        assert_eq!(key, "-0044-03-15");
+        assert_eq!(sort_year(&key), "-0044");
+        assert_eq!(parts(&key), (-44, 3, 15));
+        assert!(parts(&key) < parts("0001-00-00"));
+        assert!(parts("-0100-00-00") < parts("-0044-03-15"));
+        assert_eq!(sort_year("2023-09-00"), "2023");
+    }
+
+    #[test]
+    fn unparseable_fields() {
+        let bib = Bibliography::parse(
+            "@misc{a, date={2020-13}}\n@misc{b, year={in press}}\n@misc{c, year=2020, month={Sept.}}\n@misc{d, year=2020, month=sep}",
+        )
+        .unwrap();
+        let fields = |key| unparseable(bib.get(key).unwrap());
+        assert_eq!(fields("a"), ["date"]);
+        assert_eq!(fields("b"), ["year"]);
+        assert_eq!(fields("c"), ["month"]);
+        assert!(fields("d").is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Semantic differences between two bibliographies, entry by entry and field by field.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use biblatex::{Bibliography, ChunksExt, Entry};
+use serde::Serialize;
+
+/// Formats of a [`Diff`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DiffFormat {
+    /// An object with `added`, `removed` and `modified` entries
+    #[default]
+    Json,
+    /// Human-readable lines like `~ key` and `  title: old -> new`
+    Text,
+}
+
+/// A changed value, missing on the side where the field was added or removed.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The changes of an entry that is in both bibliographies.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Modified {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_type: Option<Change>,
+    pub fields: BTreeMap<String, Change>,
+}
+
+/// The entries that were added, removed or modified, by key.
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<Modified>,
+}
+
+/// The fields of an entry as BibTeX values.
+fn fields(entry: &Entry) -> BTreeMap<&str, String> {
+    entry
+        .fields
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.format_verbatim()))
+        .collect()
+}
+
+/// The changes from the old to the new entry, `None` if there are none.
+fn modified(old: &Entry, new: &Entry) -> Option<Modified> {
+    let (old_fields, new_fields) = (fields(old), fields(new));
+    let mut names: Vec<&str> = old_fields
+        .keys()
+        .chain(new_fields.keys())
+        .copied()
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let changes: BTreeMap<String, Change> = names
+        .into_iter()
+        .filter(|name| old_fields.get(name) != new_fields.get(name))
+        .map(|name| {
+            let change = Change {
+                old: old_fields.get(name).cloned(),
+                new: new_fields.get(name).cloned(),
+            };
+            (name.to_owned(), change)
+        })
+        .collect();
+    let entry_type = (old.entry_type != new.entry_type).then(|| Change {
+        old: Some(old.entry_type.to_string()),
+        new: Some(new.entry_type.to_string()),
+    });
+    (entry_type.is_some() || !changes.is_empty()).then(|| Modified {
+        key: new.key.clone(),
+        entry_type,
+        fields: changes,
+    })
+}
+
+impl Diff {
+    /// Compare the entries with the same keys, field values as written in
+    /// the inputs.
+    pub fn new(old: &Bibliography, new: &Bibliography) -> Self {
+        let mut diff = Diff::default();
+        for entry in new.iter() {
+            match old.get(&entry.key) {
+                Some(before) => diff.modified.extend(modified(before, entry)),
+                None => diff.added.push(entry.key.clone()),
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|e| new.get(&e.key).is_none())
+            .map(|e| e.key.clone())
+            .collect();
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort_by(|a, b| a.key.cmp(&b.key));
+        diff
+    }
+
+    /// Whether the bibliographies have the same entries.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in &self.added {
+            writeln!(f, "+ {key}")?;
+        }
+        for key in &self.removed {
+            writeln!(f, "- {key}")?;
+        }
+        for entry in &self.modified {
+            writeln!(f, "~ {}", entry.key)?;
+            let changes = entry.entry_type.iter().map(|c| ("type", c));
+            let changes = changes.chain(entry.fields.iter().map(|(k, c)| (k.as_str(), c)));
+            for (name, change) in changes {
+                match (&change.old, &change.new) {
+                    (Some(old), Some(new)) => writeln!(f, "  {name}: {old} -> {new}")?,
+                    (None, Some(new)) => writeln!(f, "  + {name}: {new}")?,
+                    (Some(old), None) => writeln!(f, "  - {name}: {old}")?,
+                    (None, None) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::Diff;
+
+    #[test]
+    fn changes() {
+        let old = Bibliography::parse(
+            "@article{a, title={A}, year=2020}\n@misc{b, title={B}}\n@misc{c, title={C}}",
+        )
+        .unwrap();
+        let new = Bibliography::parse(
+            "@article{a, title={A2}, doi={10.1/a}}\n@book{c, title={C}}\n@misc{d, title={D}}",
+        )
+        .unwrap();
+        let diff = Diff::new(&old, &new);
+        assert_eq!(diff.added, ["d"]);
+        assert_eq!(diff.removed, ["b"]);
+        assert_eq!(
+            diff.to_string(),
+            "+ d\n- b\n~ a\n  + doi: 10.1/a\n  title: A -> A2\n  - year: 2020\n~ c\n  type: misc -> book\n"
+        );
+        assert!(Diff::new(&old, &old).is_empty());
+    }
+}
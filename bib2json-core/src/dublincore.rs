@@ -0,0 +1,106 @@
+//! Mapping of converted entries to simple Dublin Core records, as harvested
+//! over OAI-PMH in the `oai_dc` format.
+
+use crate::xml::Xml;
+use crate::{date, SRAEntry};
+
+/// The namespaces of the `oai_dc` container and the Dublin Core elements.
+pub(crate) const NAMESPACES: &[(&str, &str)] = &[
+    (
+        "xmlns:oai_dc",
+        "http://www.openarchives.org/OAI/2.0/oai_dc/",
+    ),
+    ("xmlns:dc", "http://purl.org/dc/elements/1.1/"),
+];
+
+/// The DCMI type of an entry.
+fn dc_type(entry_type: &str) -> &'static str {
+    match entry_type.to_lowercase().as_str() {
+        "software" => "Software",
+        "dataset" => "Dataset",
+        "online" => "InteractiveResource",
+        _ => "Text",
+    }
+}
+
+/// Write the Dublin Core record of an entry.
+pub(crate) fn record(xml: &mut Xml, entry: &SRAEntry) {
+    xml.open("oai_dc:dc", &[]);
+    xml.element("dc:identifier", &[], &entry.id);
+    if let Some(title) = entry.get("title") {
+        xml.element("dc:title", &[], title);
+    }
+    for author in &entry.authors {
+        xml.element("dc:creator", &[], &author.to_bibtex());
+    }
+    for editor in &entry.editors {
+        xml.element("dc:contributor", &[], &editor.to_bibtex());
+    }
+    if let Some(issued) = &entry.date_sort {
+        xml.element("dc:date", &[], date::iso(issued));
+    }
+    xml.element("dc:type", &[], dc_type(&entry.entry_type));
+    let publisher = ["publisher", "institution", "school", "organization"]
+        .iter()
+        .find_map(|f| entry.get(f));
+    if let Some(publisher) = publisher {
+        xml.element("dc:publisher", &[], publisher);
+    }
+    let source = ["journaltitle", "journal", "booktitle"]
+        .iter()
+        .find_map(|f| entry.get(f));
+    if let Some(source) = source {
+        xml.element("dc:source", &[], source);
+    }
+    for keyword in entry.keywords() {
+        xml.element("dc:subject", &[], keyword);
+    }
+    if let Some(abstract_) = entry.get("abstract") {
+        xml.element("dc:description", &[], abstract_);
+    }
+    if let Some(language) = entry.get("langid").or_else(|| entry.get("language")) {
+        xml.element("dc:language", &[], language);
+    }
+    if let Some(doi) = entry.get("doi") {
+        let doi = format!("https://doi.org/{}", crate::strip_doi_prefix(doi));
+        xml.element("dc:identifier", &[], &doi);
+    }
+    if let Some(url) = entry.get("url") {
+        xml.element("dc:identifier", &[], url);
+    }
+    xml.close("oai_dc:dc");
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::record;
+    use crate::xml::Xml;
+    use crate::SRAEntry;
+
+    #[test]
+    fn record_elements() {
+        let bib = Bibliography::parse(
+            "@article{foo, author={Doe, Jane}, title={Lorem}, journal={Dolor}, \
+             date={2020-03}, doi={10.1000/xyz}, keywords={a, b}}",
+        )
+        .unwrap();
+        let mut xml = Xml::new();
+        record(&mut xml, &SRAEntry::from(bib.get("foo").unwrap(), &bib));
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<oai_dc:dc>
+  <dc:identifier>foo</dc:identifier>
+  <dc:title>Lorem</dc:title>
+  <dc:creator>Doe, Jane</dc:creator>
+  <dc:date>2020-03</dc:date>
+  <dc:type>Text</dc:type>
+  <dc:source>Dolor</dc:source>
+  <dc:subject>a</dc:subject>
+  <dc:subject>b</dc:subject>
+  <dc:identifier>https://doi.org/10.1000/xyz</dc:identifier>
+</oai_dc:dc>
+"#;
+        assert_eq!(xml.finish(), expected);
+    }
+}
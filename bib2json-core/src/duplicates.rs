@@ -0,0 +1,210 @@
+//! Merging of entries that describe the same publication.
+
+use std::collections::HashMap;
+
+use biblatex::{Bibliography, ChunksExt};
+
+use crate::check::Problem;
+use crate::input::chunks;
+use crate::slug::words;
+
+/// What identifies duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MergeBy {
+    /// The same DOI, ignoring case and resolver prefixes
+    Doi,
+    /// The same title, ignoring case, accents and punctuation, with the same
+    /// year and last name of the first author
+    Title,
+}
+
+/// The DOI of an entry, lowercase and without a resolver or `doi:` prefix.
+fn doi(entry: &biblatex::Entry) -> Option<String> {
+    let doi = entry.get("doi")?.format_verbatim();
+    let doi = crate::strip_doi_prefix(&doi).to_lowercase();
+    (!doi.is_empty()).then_some(doi)
+}
+
+/// The title, year and first author of an entry, normalized.
+fn title(entry: &biblatex::Entry) -> Option<String> {
+    let title = words(&entry.title().ok()?.format_verbatim()).join(" ");
+    let year = crate::date::sort_key(entry).map(|key| crate::date::sort_year(&key).to_owned());
+    let author = entry
+        .author()
+        .ok()
+        .and_then(|a| a.into_iter().next())
+        .map(|p| words(&p.name).join(" "));
+    (!title.is_empty()).then(|| {
+        format!(
+            "{title}|{}|{}",
+            year.unwrap_or_default(),
+            author.unwrap_or_default()
+        )
+    })
+}
+
+/// Disjoint sets of indices, with the smallest index of a set as its root.
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind((0..len).collect())
+    }
+
+    fn root(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.0[root] != root {
+            root = self.0[root];
+        }
+        // Shorten the path for the next time
+        let mut i = i;
+        while self.0[i] != root {
+            i = std::mem::replace(&mut self.0[i], root);
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+/// Groups of entries that are duplicates by any of the criteria, as keys in
+/// source order. Entries without duplicates are left out.
+pub fn find(bib: &Bibliography, by: &[MergeBy]) -> Vec<Vec<String>> {
+    let entries: Vec<&biblatex::Entry> = bib.iter().collect();
+    let mut sets = UnionFind::new(entries.len());
+    for &by in by {
+        // The first entry with each identifier, by index
+        let mut first: HashMap<String, usize> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let id = match by {
+                MergeBy::Doi => doi(entry),
+                MergeBy::Title => title(entry),
+            };
+            let Some(id) = id else { continue };
+            let j = *first.entry(id).or_insert(i);
+            if i != j {
+                sets.union(i, j);
+            }
+        }
+    }
+    let mut groups: Vec<Vec<String>> = vec![Vec::new(); entries.len()];
+    for (i, entry) in entries.iter().enumerate() {
+        groups[sets.root(i)].push(entry.key.clone());
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Merge duplicates into the first of them in source order.
+///
+/// The first entry gets the fields it lacks from the others, and their keys
+/// as aliases in `ids`, so citations of them still resolve. Fields with
+/// different values are reported and keep the value of the first entry.
+pub fn merge(bib: &mut Bibliography, by: &[MergeBy]) -> Vec<Problem> {
+    let duplicates: Vec<(String, String)> = find(bib, by)
+        .into_iter()
+        .flat_map(|group| {
+            let key = group[0].clone();
+            group.into_iter().skip(1).map(move |dup| (key.clone(), dup))
+        })
+        .collect();
+
+    let mut problems = Vec::new();
+    for (key, duplicate) in duplicates {
+        let Some(dup) = bib.remove(&duplicate) else {
+            continue;
+        };
+        let entry = bib.get_mut(&key).unwrap();
+        for (field, value) in &dup.fields {
+            match entry.get(field) {
+                // Equal after normalization
+                Some(_) if field == "doi" => {}
+                None => entry.set(field, value.clone()),
+                Some(own) if own.format_verbatim() != value.format_verbatim() => {
+                    problems.push(Problem {
+                        key: key.clone(),
+                        message: format!(
+                            "duplicate `{duplicate}` has a different `{field}`, keeping `{}`",
+                            own.format_verbatim()
+                        ),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        let mut ids: Vec<String> = entry.get_as("ids").unwrap_or_default();
+        ids.push(duplicate.clone());
+        entry.set("ids", chunks(&ids.join(", ")));
+        bib.alias(&key, duplicate);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{find, merge, MergeBy};
+
+    #[test]
+    fn doi() {
+        let mut bib = Bibliography::parse(
+            r#"
+            @article{a, title={Foo}, doi={10.1000/ABC}}
+            @article{b, title={Foo!}, note={N}, doi={https://doi.org/10.1000/abc}}
+            @article{c, title={Bar}, doi={10.1000/xyz}}
+            "#,
+        )
+        .unwrap();
+        let problems = merge(&mut bib, &[MergeBy::Doi]);
+        assert_eq!(bib.len(), 2);
+        let a = bib.get("b").unwrap();
+        assert_eq!(a.key, "a");
+        assert_eq!(a.get_as::<String>("note").unwrap(), "N");
+        assert_eq!(a.get_as::<Vec<String>>("ids").unwrap(), ["b"]);
+        let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            messages,
+            ["a: duplicate `b` has a different `title`, keeping `Foo`"]
+        );
+    }
+
+    #[test]
+    fn title() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, author={M{\"u}ller, Jane}, title={Lorem Ipsum: Dolor}, year=2020}
+            @inproceedings{b, author={Müller, J.}, title={{LOREM} ipsum -- dolor}, date={2020-05}}
+            @article{c, author={Müller, Jane}, title={Lorem Ipsum: Dolor}, year=2021, doi={10.1/c}}
+            @article{d, title={Other}, doi={https://doi.org/10.1/C}}
+            @book{e, author={Caesar, Gaius Iulius}, title={Gallic War}, date={-0044}}
+            @book{f, author={Caesar, Gaius Iulius}, title={Gallic War}, date={-0045}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(find(&bib, &[MergeBy::Title]), [["a", "b"]]);
+        assert_eq!(
+            find(&bib, &[MergeBy::Doi, MergeBy::Title]),
+            [vec!["a", "b"], vec!["c", "d"]]
+        );
+    }
+
+    #[test]
+    fn transitive() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, title={Foo}, author={Doe, J.}, year=2000}
+            @article{b, title={Bar}, doi={10.1000/x}}
+            @article{c, title={Foo}, author={Doe, J.}, year=2000, doi={10.1000/X}}
+            @article{d, title={Baz}}
+            "#,
+        )
+        .unwrap();
+        let groups = find(&bib, &[MergeBy::Doi, MergeBy::Title]);
+        assert_eq!(groups, [["a", "b", "c"]]);
+    }
+}
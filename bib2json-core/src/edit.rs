@@ -0,0 +1,407 @@
+//! Creating and editing entries in BibTeX sources.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use biblatex::{Entry, EntryType, Pair, RawBibliography, RawEntry, Spanned};
+
+use crate::input::{self, ReadError};
+
+/// An edit that cannot be applied.
+#[derive(Debug)]
+pub struct EditError(String);
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Fields that refer to other entries by key.
+const REFERENCES: &[&str] = &["crossref", "xref", "xdata", "related"];
+
+/// Edits of a BibTeX source that keep all other text byte-identical.
+pub struct Editor<'s> {
+    src: &'s str,
+    entries: Vec<Spanned<RawEntry<'s>>>,
+    replacements: Vec<(Range<usize>, String)>,
+    /// The replacements that add fields, by entry key and lowercase field name.
+    added: HashMap<(String, String), usize>,
+}
+
+impl<'s> Editor<'s> {
+    pub fn new(src: &'s str) -> Result<Self, ReadError> {
+        let raw = RawBibliography::parse(src)
+            .map_err(|e| ReadError::at(src, e.span.start, e.kind.to_string()))?;
+        Ok(Editor {
+            src,
+            entries: raw.entries,
+            replacements: Vec::new(),
+            added: HashMap::new(),
+        })
+    }
+
+    fn entry(&self, key: &str) -> Result<&RawEntry<'s>, EditError> {
+        self.entries
+            .iter()
+            .map(|e| &e.v)
+            .find(|e| e.key.v == key)
+            .ok_or_else(|| EditError(format!("entry `{key}` not found")))
+    }
+
+    /// The keys and types of the entries, in source order.
+    pub fn entries(&self) -> impl Iterator<Item = (&'s str, &'s str)> + '_ {
+        self.entries.iter().map(|e| (e.v.key.v, e.v.kind.v))
+    }
+
+    /// The whitespace before the position on its line.
+    fn indent(&self, pos: usize) -> &'s str {
+        let line = &self.src[..pos];
+        let start = line.rfind('\n').map_or(0, |i| i + 1);
+        let indent = &line[start..];
+        if indent.trim().is_empty() {
+            indent
+        } else {
+            ""
+        }
+    }
+
+    /// The span of a field value, without trailing whitespace.
+    fn value(&self, pair: &Pair) -> Range<usize> {
+        let span = pair.value.span.clone();
+        span.start..span.start + self.src[span].trim_end().len()
+    }
+
+    /// The span of a field value without its braces or quotes.
+    fn inner(&self, pair: &Pair) -> Range<usize> {
+        let span = self.value(pair);
+        let value = &self.src[span.clone()];
+        let delimited = value.len() >= 2
+            && (value.starts_with('{') && value.ends_with('}')
+                || value.starts_with('"') && value.ends_with('"'));
+        if delimited {
+            span.start + 1..span.end - 1
+        } else {
+            span
+        }
+    }
+
+    /// Replace a span of the source, a later replacement of the same span
+    /// takes precedence.
+    fn replace(&mut self, range: Range<usize>, text: String) {
+        let same = self
+            .replacements
+            .iter_mut()
+            .find(|(r, _)| *r == range && !r.is_empty());
+        match same {
+            Some((_, old)) => *old = text,
+            None => self.replacements.push((range, text)),
+        }
+    }
+
+    /// Set a field of an entry to a BibTeX value, a missing field is added after the last one.
+    pub fn set(&mut self, key: &str, field: &str, value: &str) -> Result<(), EditError> {
+        let entry = self.entry(key)?;
+        let value = format!("{{{value}}}");
+        let added = (key.to_owned(), field.to_ascii_lowercase());
+        let (range, text) = match entry
+            .fields
+            .iter()
+            .find(|pair| pair.key.v.eq_ignore_ascii_case(field))
+        {
+            Some(pair) => (self.value(pair), value),
+            None => {
+                let (at, separator) = match entry.fields.last() {
+                    Some(last)
+                        if self.src[entry.key.span.end..last.key.span.start].contains('\n') =>
+                    {
+                        let indent = self.indent(last.key.span.start);
+                        (self.value(last).end, format!(",\n{indent}"))
+                    }
+                    Some(last) => (self.value(last).end, ", ".to_owned()),
+                    None => (entry.key.span.end, ",\n  ".to_owned()),
+                };
+                let text = format!("{separator}{field} = {value}");
+                // Setting an added field again changes it
+                if let Some(&i) = self.added.get(&added) {
+                    self.replacements[i].1 = text;
+                    return Ok(());
+                }
+                self.added.insert(added, self.replacements.len());
+                (at..at, text)
+            }
+        };
+        self.replace(range, text);
+        Ok(())
+    }
+
+    /// Remove a field of an entry, with its line if it is on a line of its own.
+    ///
+    /// Returns whether the entry had the field.
+    pub fn remove(&mut self, key: &str, field: &str) -> Result<bool, EditError> {
+        let entry = self.entry(key)?;
+        let Some(pair) = entry
+            .fields
+            .iter()
+            .find(|pair| pair.key.v.eq_ignore_ascii_case(field))
+        else {
+            // Only added by `set`
+            let added = (key.to_owned(), field.to_ascii_lowercase());
+            return Ok(match self.added.remove(&added) {
+                Some(i) => {
+                    self.replacements[i].1.clear();
+                    true
+                }
+                None => false,
+            });
+        };
+        let indent = self.indent(pair.key.span.start);
+        let own_line = self.src[..pair.key.span.start - indent.len()].ends_with('\n');
+        let mut start = pair.key.span.start;
+        let mut rest = &self.src[self.value(pair).end..];
+        rest = rest.trim_start_matches([' ', '\t']);
+        rest = rest.strip_prefix(',').unwrap_or(rest);
+        if own_line {
+            start -= indent.len();
+            rest = rest.trim_start_matches([' ', '\t']);
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        } else {
+            rest = rest.trim_start_matches([' ', '\t']);
+        }
+        let end = self.src.len() - rest.len();
+        self.replace(start..end, String::new());
+        Ok(true)
+    }
+
+    /// Rewrite the BibTeX value of a field of an entry, keeping its braces or quotes.
+    ///
+    /// Returns the old and new value if the rewrite changed it.
+    pub fn rewrite(
+        &mut self,
+        key: &str,
+        field: &str,
+        f: impl FnOnce(&str) -> String,
+    ) -> Result<Option<(&'s str, String)>, EditError> {
+        let entry = self.entry(key)?;
+        let Some(pair) = entry
+            .fields
+            .iter()
+            .find(|pair| pair.key.v.eq_ignore_ascii_case(field))
+        else {
+            return Ok(None);
+        };
+        let inner = self.inner(pair);
+        let src = self.src;
+        let old = &src[inner.clone()];
+        let new = f(old);
+        if new == old {
+            return Ok(None);
+        }
+        self.replace(inner, new.clone());
+        Ok(Some((old, new)))
+    }
+
+    /// Rename the entry with the key, if there is one, and update all
+    /// `crossref`, `xref`, `xdata` and `related` references to it.
+    ///
+    /// Returns whether the entry was found.
+    pub fn rename(&mut self, old: &str, new: &str) -> bool {
+        let mut replacements = Vec::new();
+        let mut found = false;
+        for entry in &self.entries {
+            if entry.v.key.v == old {
+                replacements.push((entry.v.key.span.clone(), new.to_owned()));
+                found = true;
+            }
+            for pair in &entry.v.fields {
+                if !REFERENCES
+                    .iter()
+                    .any(|f| pair.key.v.eq_ignore_ascii_case(f))
+                {
+                    continue;
+                }
+                let inner = self.inner(pair);
+                let mut offset = inner.start;
+                for part in self.src[inner].split(',') {
+                    let trimmed = part.trim();
+                    if trimmed == old {
+                        let start = offset + part.find(trimmed).unwrap();
+                        replacements.push((start..start + old.len(), new.to_owned()));
+                    }
+                    offset += part.len() + 1;
+                }
+            }
+        }
+        for (range, text) in replacements {
+            self.replace(range, text);
+        }
+        found
+    }
+
+    /// The edited source, fails if edits overlap, like rewriting a removed field.
+    pub fn finish(mut self) -> Result<String, EditError> {
+        // Added fields before a replacement at the same position
+        self.replacements
+            .sort_by_key(|(range, _)| (range.start, range.end));
+        let mut out = String::new();
+        let mut cursor = 0;
+        for (range, replacement) in self.replacements {
+            if range.start < cursor {
+                let line = self.src[..range.start].matches('\n').count() + 1;
+                return Err(EditError(format!("overlapping edits on line {line}")));
+            }
+            out.push_str(&self.src[cursor..range.start]);
+            out.push_str(&replacement);
+            cursor = range.end;
+        }
+        out.push_str(&self.src[cursor..]);
+        Ok(out)
+    }
+}
+
+/// Parse a mapping of citation keys, one old and new key per line, separated
+/// by whitespace like the output of `bib2json keygen`. Empty lines and lines
+/// starting with `#` are skipped.
+pub fn renames(map: &str) -> Result<Vec<(String, String)>, EditError> {
+    map.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(
+            |(i, line)| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [old, new] => Ok((old.to_owned(), new.to_owned())),
+                _ => Err(EditError(format!(
+                    "line {}: expected an old and a new key",
+                    i + 1
+                ))),
+            },
+        )
+        .collect()
+}
+
+/// An entry of the type with the fields that biblatex's validation
+/// requires left empty, and the given fields set.
+pub fn scaffold(key: &str, entry_type: &str, fields: &[(String, String)]) -> Entry {
+    let mut entry = Entry::new(key.to_owned(), EntryType::new(entry_type));
+    for field in entry.verify().missing {
+        entry.set(field, Vec::new());
+    }
+    for (field, value) in fields {
+        entry.set(field, input::chunks(value));
+    }
+    entry
+}
+
+#[cfg(test)]
+mod test {
+    use super::{renames, scaffold, Editor};
+
+    #[test]
+    fn new_entry() {
+        let fields = [("title".to_string(), "Foo".to_string())];
+        let entry = scaffold("Foo2025", "inproceedings", &fields);
+        assert_eq!(
+            entry.to_biblatex_string(),
+            "@inproceedings{Foo2025,\nauthor = {},\nbooktitle = {},\neditor = {},\ntitle = {Foo},\nyear = {},\n}"
+        );
+    }
+
+    #[test]
+    fn set() {
+        let src =
+            "% refs\n@article{a,\n  title = \"Old\",\n  year  = 2020\n}\n\n@misc{b, title={B}}\n";
+        let mut editor = Editor::new(src).unwrap();
+        editor.set("a", "Title", "New").unwrap();
+        editor.set("a", "pages", "15--29").unwrap();
+        editor.set("b", "note", "C").unwrap();
+        assert!(editor.set("c", "title", "C").is_err());
+        assert_eq!(
+            editor.finish().unwrap(),
+            "% refs\n@article{a,\n  title = {New},\n  year  = 2020,\n  pages = {15--29}\n}\n\n@misc{b, title={B}, note = {C}}\n"
+        );
+    }
+
+    #[test]
+    fn repeated() {
+        let src = "@article{a,\n  title = {Old}\n}\n";
+        let mut editor = Editor::new(src).unwrap();
+        editor.set("a", "title", "First").unwrap();
+        editor.set("a", "title", "Second").unwrap();
+        editor.set("a", "pages", "1").unwrap();
+        editor.set("a", "Pages", "2").unwrap();
+        editor.set("a", "note", "N").unwrap();
+        assert!(editor.remove("a", "note").unwrap());
+        assert_eq!(
+            editor.finish().unwrap(),
+            "@article{a,\n  title = {Second},\n  Pages = {2}\n}\n"
+        );
+
+        let mut editor = Editor::new(src).unwrap();
+        editor.rewrite("a", "title", |_| "New".into()).unwrap();
+        editor.remove("a", "title").unwrap();
+        assert_eq!(
+            editor.finish().unwrap_err().to_string(),
+            "overlapping edits on line 2"
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let src = "@article{a,\n  title = {A},\n  abstract = {Long},\n  year = 2020\n}\n@misc{b, abstract={B}, title={B}}\n";
+        let mut editor = Editor::new(src).unwrap();
+        assert!(editor.remove("a", "abstract").unwrap());
+        assert!(editor.remove("b", "abstract").unwrap());
+        assert!(!editor.remove("b", "pages").unwrap());
+        assert_eq!(
+            editor.finish().unwrap(),
+            "@article{a,\n  title = {A},\n  year = 2020\n}\n@misc{b, title={B}}\n"
+        );
+    }
+
+    #[test]
+    fn rename() {
+        let src = "@book{old, title={Old}}\n@inbook{a, crossref = {old}, related = {x, old}}\n";
+        let mut editor = Editor::new(src).unwrap();
+        assert!(editor.rename("old", "new"));
+        assert_eq!(
+            editor.finish().unwrap(),
+            "@book{new, title={Old}}\n@inbook{a, crossref = {new}, related = {x, new}}\n"
+        );
+
+        let map = renames("# keys\nold\tnew\n\n  x  y\n").unwrap();
+        assert_eq!(
+            map,
+            [("old", "new"), ("x", "y")].map(|(a, b)| (a.into(), b.into()))
+        );
+        let mut editor = Editor::new(src).unwrap();
+        for (old, new) in &map {
+            editor.rename(old, new);
+        }
+        assert_eq!(
+            editor.finish().unwrap(),
+            "@book{new, title={Old}}\n@inbook{a, crossref = {new}, related = {y, new}}\n"
+        );
+        assert_eq!(
+            renames("a b\nc\n").unwrap_err().to_string(),
+            "line 2: expected an old and a new key"
+        );
+    }
+
+    #[test]
+    fn rewrite() {
+        let src = "@article{a, journal = \"Proc. of Foo\"}\n@article{b, journal = {Bar}}\n";
+        let mut editor = Editor::new(src).unwrap();
+        let expand = |s: &str| s.replace("Proc. of", "Proceedings of");
+        assert_eq!(
+            editor.rewrite("a", "journal", expand).unwrap(),
+            Some(("Proc. of Foo", "Proceedings of Foo".to_string()))
+        );
+        assert_eq!(editor.rewrite("b", "journal", expand).unwrap(), None);
+        assert_eq!(
+            editor.finish().unwrap(),
+            "@article{a, journal = \"Proceedings of Foo\"}\n@article{b, journal = {Bar}}\n"
+        );
+    }
+}
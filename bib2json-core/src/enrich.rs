@@ -0,0 +1,321 @@
+//! Enrichment of converted entries with metadata from online services.
+//!
+//! The responses are kept in a [`Cache`], so repeated conversions do not
+//! query the services again. Several entries are looked up concurrently,
+//! through a rate-limited [`Fetcher`].
+
+use std::io;
+use std::sync::Mutex;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::Value;
+
+use crate::cache::Cache;
+use crate::fetch::Fetcher;
+use crate::parallel::Progress;
+use crate::{strip_doi_prefix, SRAEntry};
+
+/// An online service with metadata about publications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Service {
+    /// OpenAlex: open-access status, concepts and citation count
+    Openalex,
+    /// Semantic Scholar: paper id, citation count and TLDR summary
+    SemanticScholar,
+}
+
+/// Looks up entries in online services and adds the results as fields.
+pub struct Enricher {
+    services: Vec<Service>,
+    cache: Mutex<Cache>,
+    fetcher: Fetcher,
+    offline: bool,
+    progress: Progress,
+}
+
+impl Enricher {
+    pub fn new(services: Vec<Service>, cache: Cache) -> Self {
+        Enricher {
+            services,
+            cache: Mutex::new(cache),
+            fetcher: Fetcher::default(),
+            offline: false,
+            progress: Progress::default(),
+        }
+    }
+
+    /// Count the enriched entries.
+    pub fn progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Only use cached responses, also expired ones, and never query the services.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Send the requests through this fetcher, e.g., with a different rate limit.
+    pub fn fetcher(mut self, fetcher: Fetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Add the fields of all services, the fields are listed in [`SRAEntry::derived`].
+    pub fn enrich(&self, entry: &mut SRAEntry) -> io::Result<()> {
+        for &service in &self.services {
+            let fields = match service {
+                Service::Openalex => self.openalex(entry)?,
+                Service::SemanticScholar => self.semantic_scholar(entry)?,
+            };
+            for (field, value) in fields {
+                entry.derived.push(field.clone());
+                entry.other.insert(field, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enrich the entries with up to `jobs` concurrent lookups.
+    pub fn enrich_all(&self, entries: &mut [SRAEntry], jobs: usize) -> io::Result<()> {
+        self.progress.start(entries.len());
+        let queue = Mutex::new(entries.iter_mut());
+        std::thread::scope(|s| {
+            let workers: Vec<_> = (0..jobs.max(1))
+                .map(|_| {
+                    s.spawn(|| loop {
+                        let Some(entry) = queue.lock().unwrap().next() else {
+                            return Ok(());
+                        };
+                        self.enrich(entry)?;
+                        self.progress.inc();
+                    })
+                })
+                .collect();
+            workers.into_iter().try_for_each(|w| w.join().unwrap())
+        })
+    }
+
+    /// Store the cache.
+    pub fn finish(self) -> io::Result<()> {
+        self.cache.into_inner().unwrap().save()
+    }
+
+    /// A cached GET request, returns `null` if nothing was found. Expired
+    /// responses are used if the service cannot be reached.
+    fn get(&self, key: String, request: ureq::Request) -> io::Result<Value> {
+        let stale = {
+            let cache = self.cache.lock().unwrap();
+            if let Some(response) = cache.get(&key) {
+                return Ok(response.clone());
+            }
+            cache.get_stale(&key).cloned()
+        };
+        if self.offline {
+            return Ok(stale.unwrap_or_default());
+        }
+        let response = match (self.fetcher.get(request), stale) {
+            (Ok(response), _) => response,
+            (Err(_), Some(stale)) => return Ok(stale),
+            (Err(e), None) => return Err(e),
+        };
+        self.cache.lock().unwrap().insert(key, response.clone());
+        Ok(response)
+    }
+
+    fn openalex(&self, entry: &SRAEntry) -> io::Result<Vec<(String, String)>> {
+        let work = if let Some(doi) = entry.get("doi") {
+            let request = self.fetcher.request(&format!(
+                "https://api.openalex.org/works/doi:{}",
+                encode_doi(doi)
+            ));
+            self.get(format!("openalex:doi:{doi}"), request)?
+        } else if let Some(title) = entry.get("title") {
+            // Commas separate filters, the query parameters are encoded by ureq
+            let title = title.replace(',', " ");
+            let request = self
+                .fetcher
+                .request("https://api.openalex.org/works")
+                .query("filter", &format!("title.search:{title}"))
+                .query("per-page", "1");
+            first(
+                self.get(format!("openalex:title:{title}"), request)?,
+                "results",
+            )
+        } else {
+            Value::Null
+        };
+        Ok(openalex_fields(&work))
+    }
+
+    fn semantic_scholar(&self, entry: &SRAEntry) -> io::Result<Vec<(String, String)>> {
+        const API: &str = "https://api.semanticscholar.org/graph/v1/paper";
+        const FIELDS: &str = "paperId,citationCount,tldr";
+        let paper = if let Some(doi) = entry.get("doi") {
+            let request = self
+                .fetcher
+                .request(&format!("{API}/DOI:{}", encode_doi(doi)))
+                .query("fields", FIELDS);
+            self.get(format!("s2:doi:{doi}"), request)?
+        } else if let Some(title) = entry.get("title") {
+            let request = self
+                .fetcher
+                .request(&format!("{API}/search/match"))
+                .query("query", title)
+                .query("fields", FIELDS);
+            first(self.get(format!("s2:title:{title}"), request)?, "data")
+        } else {
+            Value::Null
+        };
+        Ok(semantic_scholar_fields(&paper))
+    }
+}
+
+/// Characters that cannot appear in a path of a URL. Slashes are kept, the
+/// services expect them in DOIs.
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The DOI without prefix, encoded for the path of a URL.
+fn encode_doi(doi: &str) -> String {
+    utf8_percent_encode(strip_doi_prefix(doi), PATH).to_string()
+}
+
+/// The first result in the list of a search response, `null` if there is
+/// none or the response is not a search result, e.g., an error.
+fn first(mut response: Value, list: &str) -> Value {
+    response
+        .get_mut(list)
+        .and_then(|results| results.get_mut(0))
+        .map(Value::take)
+        .unwrap_or_default()
+}
+
+/// The fields from a Semantic Scholar paper.
+fn semantic_scholar_fields(paper: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    if let Some(id) = paper["paperId"].as_str() {
+        fields.push(("s2_paper_id".into(), id.to_owned()));
+    }
+    if let Some(count) = paper["citationCount"].as_u64() {
+        fields.push(("s2_citation_count".into(), count.to_string()));
+    }
+    if let Some(tldr) = paper["tldr"]["text"].as_str() {
+        fields.push(("s2_tldr".into(), tldr.to_owned()));
+    }
+    fields
+}
+
+/// The fields from an OpenAlex work.
+fn openalex_fields(work: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    if let Some(id) = work["id"].as_str() {
+        fields.push(("openalex_id".into(), id.to_owned()));
+    }
+    if let Some(status) = work["open_access"]["oa_status"].as_str() {
+        fields.push(("openalex_oa_status".into(), status.to_owned()));
+    }
+    if let Some(concepts) = work["concepts"].as_array() {
+        let names: Vec<&str> = concepts
+            .iter()
+            .filter_map(|c| c["display_name"].as_str())
+            .collect();
+        if !names.is_empty() {
+            fields.push(("openalex_concepts".into(), names.join(", ")));
+        }
+    }
+    if let Some(count) = work["cited_by_count"].as_u64() {
+        fields.push(("openalex_cited_by_count".into(), count.to_string()));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{encode_doi, first, openalex_fields, semantic_scholar_fields, Enricher, Service};
+    use crate::cache::Cache;
+    use crate::SRAEntry;
+
+    #[test]
+    fn openalex() {
+        let work = json!({
+            "id": "https://openalex.org/W2741809807",
+            "open_access": {"is_oa": true, "oa_status": "gold"},
+            "concepts": [{"display_name": "Biology"}, {"display_name": "Genome"}],
+            "cited_by_count": 42,
+        });
+        let fields = openalex_fields(&work);
+        let expected = [
+            ("openalex_id", "https://openalex.org/W2741809807"),
+            ("openalex_oa_status", "gold"),
+            ("openalex_concepts", "Biology, Genome"),
+            ("openalex_cited_by_count", "42"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(fields, expected);
+        assert!(openalex_fields(&serde_json::Value::Null).is_empty());
+    }
+
+    #[test]
+    fn search() {
+        let response = json!({"results": [{"id": "W1"}, {"id": "W2"}]});
+        assert_eq!(first(response, "results"), json!({"id": "W1"}));
+        assert!(first(json!({"results": []}), "results").is_null());
+        assert!(first(json!({"error": "Not Found"}), "results").is_null());
+        assert!(first(json!("error"), "results").is_null());
+        assert!(first(serde_json::Value::Null, "results").is_null());
+    }
+
+    #[test]
+    fn offline() {
+        let services = vec![Service::Openalex, Service::SemanticScholar];
+        let enricher = Enricher::new(services, Cache::memory()).offline(true);
+        let mut entry: SRAEntry =
+            serde_json::from_value(json!({"entry_type": "article", "title": "Uncached"})).unwrap();
+        enricher.enrich(&mut entry).unwrap();
+        assert!(entry.derived.is_empty());
+    }
+
+    #[test]
+    fn doi() {
+        assert_eq!(
+            encode_doi("https://doi.org/10.1000/a b#1"),
+            "10.1000/a%20b%231"
+        );
+        assert_eq!(
+            encode_doi("10.1002/(SICI)1097-4636"),
+            "10.1002/(SICI)1097-4636"
+        );
+    }
+
+    #[test]
+    fn semantic_scholar() {
+        let paper = json!({
+            "paperId": "649def34f8be52c8b66281af98ae884c09aef38b",
+            "citationCount": 7,
+            "tldr": {"model": "tldr@v2.0.0", "text": "A short summary."},
+        });
+        let expected = [
+            ("s2_paper_id", "649def34f8be52c8b66281af98ae884c09aef38b"),
+            ("s2_citation_count", "7"),
+            ("s2_tldr", "A short summary."),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(semantic_scholar_fields(&paper), expected);
+        let not_found = json!({"error": "Title match not found"});
+        assert!(semantic_scholar_fields(&first(not_found, "data")).is_empty());
+    }
+}
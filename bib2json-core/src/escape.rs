@@ -0,0 +1,134 @@
+//! Escaping of the output values for embedding them into other documents.
+
+use std::str::FromStr;
+
+use crate::{by_name, UnknownName};
+
+/// LaTeX formatting commands and the HTML tags replacing them.
+const TAGS: &[(&str, &str)] = &[
+    ("emph", "em"),
+    ("textit", "em"),
+    ("textbf", "strong"),
+    ("texttt", "code"),
+    ("textsuperscript", "sup"),
+    ("textsubscript", "sub"),
+];
+
+/// How the string values are escaped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Escape {
+    /// Output the values as they are
+    #[default]
+    None,
+    /// Escape `<`, `>`, `&` and `"`, and turn LaTeX emphasis in fields into tags
+    Html,
+}
+
+impl Escape {
+    /// The escapings by their names in the command line tool and the bindings.
+    const NAMES: &'static [(&'static str, Escape)] =
+        &[("none", Escape::None), ("html", Escape::Html)];
+
+    /// The names of the escapings.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().map(|(name, _)| *name)
+    }
+
+    /// Escape a plain string like a name or the BibTeX source.
+    pub fn text(self, text: &str) -> String {
+        match self {
+            Escape::None => text.to_owned(),
+            Escape::Html => html(text),
+        }
+    }
+
+    /// Escape a field value, converting formatting commands where possible.
+    pub fn field(self, value: &str) -> String {
+        match self {
+            Escape::None => value.to_owned(),
+            Escape::Html => tags(&html(value)),
+        }
+    }
+}
+
+fn html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Replace `\emph{...}` and similar commands with HTML tags.
+fn tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while let Some(start) = rest.find('\\') {
+        out.push_str(&rest[..start]);
+        let command = &rest[start + 1..];
+        for (name, tag) in TAGS {
+            let Some(arg) = command.strip_prefix(name).and_then(|c| c.strip_prefix('{')) else {
+                continue;
+            };
+            let mut depth = 1;
+            let Some(end) = arg.find(|c| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            }) else {
+                continue;
+            };
+            out.push_str(&format!("<{tag}>{}</{tag}>", tags(&arg[..end])));
+            rest = &arg[end + 1..];
+            continue 'outer;
+        }
+        out.push('\\');
+        rest = command;
+    }
+    out.push_str(rest);
+    out
+}
+
+impl FromStr for Escape {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        by_name(Self::NAMES, s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Escape;
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            Escape::Html.field(r"Fast \emph{R&D} for <T> \textbf{really \emph{fast}} \LaTeX"),
+            r"Fast <em>R&amp;D</em> for &lt;T&gt; <strong>really <em>fast</em></strong> \LaTeX"
+        );
+        assert_eq!(Escape::Html.text(r"\emph{a<b}"), r"\emph{a&lt;b}");
+        assert_eq!(Escape::None.field(r"\emph{a<b}"), r"\emph{a<b}");
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!("HTML".parse(), Ok(Escape::Html));
+        assert!("latex".parse::<Escape>().is_err());
+        #[cfg(feature = "clap")]
+        for variant in <Escape as clap::ValueEnum>::value_variants() {
+            let value = clap::ValueEnum::to_possible_value(variant).unwrap();
+            assert_eq!(value.get_name().parse(), Ok(*variant));
+        }
+    }
+}
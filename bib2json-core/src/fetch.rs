@@ -0,0 +1,127 @@
+//! HTTP requests to online services, shared by concurrent workers.
+//!
+//! Requests to the same host keep a minimum interval, and requests that fail
+//! temporarily are retried with exponential backoff.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// Sends GET requests with per-host rate limiting and retries.
+pub struct Fetcher {
+    agent: ureq::Agent,
+    interval: Duration,
+    retries: u32,
+    /// The earliest time of the next request per host.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for Fetcher {
+    /// At most ten requests per second and host, three retries.
+    fn default() -> Self {
+        Fetcher::new(Duration::from_millis(100), 3)
+    }
+}
+
+/// The host of a URL.
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+/// Whether a failed request might succeed later.
+fn is_temporary(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+        ureq::Error::Transport(_) => true,
+    }
+}
+
+impl Fetcher {
+    /// A fetcher that waits `interval` between requests to the same host.
+    pub fn new(interval: Duration, retries: u32) -> Self {
+        Fetcher {
+            agent: ureq::Agent::new(),
+            interval,
+            retries,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A GET request for the URL, to add query parameters to.
+    pub fn request(&self, url: &str) -> ureq::Request {
+        self.agent.get(url)
+    }
+
+    /// Reserve the next slot for a request to the host and wait for it.
+    fn wait(&self, host: &str) {
+        let now = Instant::now();
+        let at = {
+            let mut next = self.next.lock().unwrap();
+            let at = next.get(host).map_or(now, |&at| at.max(now));
+            next.insert(host.to_owned(), at + self.interval);
+            at
+        };
+        thread::sleep(at - now);
+    }
+
+    /// Send the request and parse the JSON response, `null` if nothing was found.
+    pub fn get(&self, request: ureq::Request) -> io::Result<Value> {
+        let host = host(request.url()).to_owned();
+        let mut backoff = self.interval.max(Duration::from_millis(500));
+        let mut attempt = 0;
+        loop {
+            self.wait(&host);
+            match request.clone().call() {
+                Ok(response) => return Ok(serde_json::from_str(&response.into_string()?)?),
+                Err(ureq::Error::Status(404, _)) => return Ok(Value::Null),
+                Err(e) if attempt < self.retries && is_temporary(&e) => {
+                    // Respect the delay the service asks for
+                    let delay = match &e {
+                        ureq::Error::Status(_, response) => response
+                            .header("retry-after")
+                            .and_then(|s| s.parse().ok())
+                            .map(Duration::from_secs),
+                        _ => None,
+                    };
+                    thread::sleep(delay.unwrap_or(backoff));
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{host, Fetcher};
+
+    #[test]
+    fn rate_limit() {
+        assert_eq!(
+            host("https://api.openalex.org/works?x=1"),
+            "api.openalex.org"
+        );
+        assert_eq!(host("http://localhost:8080"), "localhost:8080");
+
+        let fetcher = Fetcher::new(Duration::from_millis(50), 0);
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| fetcher.wait("example.org"));
+            }
+        });
+        fetcher.wait("example.com");
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    }
+}
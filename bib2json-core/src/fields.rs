@@ -0,0 +1,220 @@
+//! Known BibTeX and biblatex field names, to catch misspelled fields.
+
+use biblatex::Bibliography;
+
+use crate::check::Problem;
+
+/// The data and special fields of BibTeX and biblatex, and common extensions
+/// like `doi` or `pmid`, sorted.
+pub const FIELDS: &[&str] = &[
+    "abstract",
+    "addendum",
+    "address",
+    "afterword",
+    "annotation",
+    "annotator",
+    "annote",
+    "archiveprefix",
+    "author",
+    "authortype",
+    "bookauthor",
+    "bookpagination",
+    "booksubtitle",
+    "booktitle",
+    "booktitleaddon",
+    "chapter",
+    "commentator",
+    "crossref",
+    "date",
+    "doi",
+    "edition",
+    "editor",
+    "editora",
+    "editoratype",
+    "editorb",
+    "editorbtype",
+    "editorc",
+    "editorctype",
+    "editortype",
+    "eid",
+    "entryset",
+    "entrysubtype",
+    "eprint",
+    "eprintclass",
+    "eprinttype",
+    "eventdate",
+    "eventtitle",
+    "eventtitleaddon",
+    "execute",
+    "file",
+    "foreword",
+    "gender",
+    "holder",
+    "howpublished",
+    "ids",
+    "indexsorttitle",
+    "indextitle",
+    "institution",
+    "introduction",
+    "isan",
+    "isbn",
+    "ismn",
+    "isrn",
+    "issn",
+    "issue",
+    "issuesubtitle",
+    "issuetitle",
+    "iswc",
+    "journal",
+    "journalsubtitle",
+    "journaltitle",
+    "key",
+    "keywords",
+    "label",
+    "langid",
+    "langidopts",
+    "language",
+    "library",
+    "location",
+    "main",
+    "mainsubtitle",
+    "maintitle",
+    "maintitleaddon",
+    "month",
+    "nameaddon",
+    "note",
+    "number",
+    "options",
+    "organization",
+    "origdate",
+    "origlanguage",
+    "origlocation",
+    "origpublisher",
+    "origtitle",
+    "pages",
+    "pagetotal",
+    "pagination",
+    "part",
+    "pmcid",
+    "pmid",
+    "presort",
+    "primaryclass",
+    "publisher",
+    "pubstate",
+    "related",
+    "relatedoptions",
+    "relatedstring",
+    "relatedtype",
+    "school",
+    "series",
+    "shortauthor",
+    "shorteditor",
+    "shorthand",
+    "shorthandintro",
+    "shortjournal",
+    "shortseries",
+    "shorttitle",
+    "sortkey",
+    "sortname",
+    "sortshorthand",
+    "sorttitle",
+    "sortyear",
+    "subtitle",
+    "title",
+    "titleaddon",
+    "translator",
+    "type",
+    "url",
+    "urldate",
+    "venue",
+    "version",
+    "volume",
+    "volumes",
+    "xdata",
+    "xref",
+    "year",
+];
+
+/// Whether a field is known or allowed, ignoring case.
+pub fn is_known(field: &str, allow: &[String]) -> bool {
+    let field = field.to_lowercase();
+    FIELDS.binary_search(&field.as_str()).is_ok() || allow.iter().any(|a| a.to_lowercase() == field)
+}
+
+/// The number of single-character edits between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let next = (diagonal + usize::from(ca != cb))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// The known field closest to a misspelled one, if there is a close one.
+pub fn suggest(field: &str) -> Option<&'static str> {
+    let field = field.to_lowercase();
+    FIELDS
+        .iter()
+        .map(|known| (distance(&field, known), *known))
+        .filter(|(d, _)| *d <= 2 && *d < field.len() / 2 + 1)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Report the fields that are neither known nor allowed.
+pub fn unknown(bib: &Bibliography, allow: &[String]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for entry in bib.iter() {
+        for field in entry.fields.keys() {
+            if is_known(field, allow) {
+                continue;
+            }
+            let message = match suggest(field) {
+                Some(known) => format!("unknown field `{field}`, did you mean `{known}`?"),
+                None => format!("unknown field `{field}`"),
+            };
+            problems.push(Problem {
+                key: entry.key.clone(),
+                message,
+            });
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{unknown, FIELDS};
+
+    #[test]
+    fn unknown_fields() {
+        assert!(FIELDS.windows(2).all(|w| w[0] < w[1]));
+        let bib = Bibliography::parse(
+            "@misc{a, yeear=2020, addres={Hannover}, Title={T}, category={c}, zzzzzz={z}}",
+        )
+        .unwrap();
+        let messages: Vec<String> = unknown(&bib, &["Category".into()])
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "a: unknown field `addres`, did you mean `address`?",
+                "a: unknown field `yeear`, did you mean `year`?",
+                "a: unknown field `zzzzzz`",
+            ]
+        );
+    }
+}
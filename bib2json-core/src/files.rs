@@ -0,0 +1,172 @@
+//! Input files from directories and glob patterns like `bib/**/*.bib`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use globset::GlobBuilder;
+use walkdir::WalkDir;
+
+use crate::input::InputFormat;
+
+/// An input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub path: PathBuf,
+    /// The path below the searched directory, or the file name.
+    pub relative: PathBuf,
+}
+
+/// Whether a path component contains glob wildcards.
+fn is_pattern(component: &str) -> bool {
+    component.contains(['*', '?', '['])
+}
+
+/// All files below a directory up to a depth, sorted, without hidden ones.
+/// Linked directories are followed unless they link to a directory above.
+fn walk(dir: &Path, max_depth: usize) -> io::Result<Vec<PathBuf>> {
+    let walker = WalkDir::new(dir)
+        .follow_links(true)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'));
+    let mut files = Vec::new();
+    for entry in walker {
+        match entry {
+            Ok(entry) if entry.file_type().is_file() => files.push(entry.into_path()),
+            Ok(_) => {}
+            Err(e) if e.loop_ancestor().is_some() => {
+                log::warn!(
+                    "{}: symbolic link loop, skipped",
+                    e.path().unwrap_or(dir).display()
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(files)
+}
+
+/// The names of the components of a relative path.
+fn components(path: &Path) -> Vec<String> {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// The directory before the first wildcard of a glob pattern, `None` for
+/// other paths.
+pub fn base(input: &Path) -> Option<PathBuf> {
+    let first = components(input).iter().position(|c| is_pattern(c))?;
+    match first {
+        0 => Some(PathBuf::from(".")),
+        _ => Some(input.components().take(first).collect()),
+    }
+}
+
+/// The input files of a path: the path itself, the files of a known input
+/// format below a directory, or the files matching a glob pattern.
+pub fn expand(input: &Path) -> io::Result<Vec<Found>> {
+    if input.is_dir() {
+        return Ok(walk(input, usize::MAX)?
+            .into_iter()
+            .filter(|f| InputFormat::from_path(f).is_some())
+            .map(|path| Found {
+                relative: path.strip_prefix(input).unwrap().to_owned(),
+                path,
+            })
+            .collect());
+    }
+    let parts = components(input);
+    let Some(first) = parts.iter().position(|c| is_pattern(c)) else {
+        return Ok(vec![Found {
+            path: input.to_owned(),
+            relative: PathBuf::from(input.file_name().unwrap_or(input.as_os_str())),
+        }]);
+    };
+    let prefix: PathBuf = input.components().take(first).collect();
+    let root = if first == 0 { Path::new(".") } else { &prefix };
+    let pattern = &parts[first..];
+    let matcher = GlobBuilder::new(&pattern.join("/"))
+        .literal_separator(true)
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .compile_matcher();
+    // Only as deep as the pattern, unless it has `**`
+    let depth = match pattern.iter().any(|c| c == "**") {
+        true => usize::MAX,
+        false => pattern.len(),
+    };
+    let files = match root.is_dir() {
+        true => walk(root, depth)?,
+        false => Vec::new(),
+    };
+    Ok(files
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(root).unwrap().to_owned();
+            matcher.is_match(&relative).then(|| Found {
+                path: prefix.join(&relative),
+                relative,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::expand;
+
+    #[test]
+    fn glob() {
+        let dir = std::env::temp_dir().join(format!("bib2json-files-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("topic/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("x/y")).unwrap();
+        for file in [
+            "a.bib",
+            "notes.txt",
+            "topic/b.bib",
+            "topic/c.json",
+            "topic/.git/d.bib",
+            "x/b.bib",
+            "x/y/b1.bib",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("topic/loop")).unwrap();
+        let relative = |input: PathBuf| -> Vec<PathBuf> {
+            expand(&input)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.relative)
+                .collect()
+        };
+        assert_eq!(
+            relative(dir.clone()),
+            [
+                "a.bib",
+                "topic/b.bib",
+                "topic/c.json",
+                "x/b.bib",
+                "x/y/b1.bib"
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            relative(dir.join("**/*.bib")),
+            ["a.bib", "topic/b.bib", "x/b.bib", "x/y/b1.bib"].map(PathBuf::from)
+        );
+        assert_eq!(relative(dir.join("*.bib")), [PathBuf::from("a.bib")]);
+        assert_eq!(
+            relative(dir.join("x/**/[a-c]?.bib")),
+            [PathBuf::from("y/b1.bib")]
+        );
+        assert!(relative(dir.join("x/[!a-c]*.bib")).is_empty());
+        let found = expand(&dir.join("topic/*.json")).unwrap();
+        assert_eq!(found[0].path, dir.join("topic/c.json"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! Selection of the entries to convert by their properties.
+
+use regex::Regex;
+
+use crate::query::Query;
+use crate::{SRAEntry, SRAPerson};
+
+/// Conditions that the converted entries have to meet, all of them.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only entries of these types, ignoring case, all if empty.
+    pub types: Vec<String>,
+    /// Only entries from this year or later.
+    pub from_year: Option<i32>,
+    /// Only entries up to this year.
+    pub to_year: Option<i32>,
+    /// Only entries with an author or editor whose name, as `First Last` or
+    /// `Last, First`, matches.
+    pub author: Option<Regex>,
+    /// Only entries with these keywords, ignoring case.
+    pub keywords: Vec<String>,
+    /// Only require one of the [`Filter::keywords`] instead of all.
+    pub any_keyword: bool,
+    /// Only entries that satisfy the query.
+    pub query: Option<Query>,
+}
+
+impl Filter {
+    /// Whether the entry meets all conditions. Entries without a year do not
+    /// meet a year range.
+    pub fn matches(&self, entry: &SRAEntry) -> bool {
+        let types = self.types.is_empty()
+            || self
+                .types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(&entry.entry_type));
+        let years = match (self.from_year, self.to_year) {
+            (None, None) => true,
+            (from, to) => entry
+                .year()
+                .and_then(|y| y.trim().parse::<i32>().ok())
+                .is_some_and(|y| from.is_none_or(|f| y >= f) && to.is_none_or(|t| y <= t)),
+        };
+        let author = self.author.as_ref().is_none_or(|author| {
+            let matches = |p: &SRAPerson| {
+                author.is_match(&p.to_bibtex())
+                    || author.is_match(&format!("{} {}", p.first_name, p.last_name))
+            };
+            entry.authors.iter().chain(&entry.editors).any(matches)
+        });
+        let keywords = entry.keywords();
+        let has = |k: &String| keywords.iter().any(|e| e.eq_ignore_ascii_case(k.trim()));
+        let keywords = match self.any_keyword {
+            _ if self.keywords.is_empty() => true,
+            true => self.keywords.iter().any(has),
+            false => self.keywords.iter().all(has),
+        };
+        let query = self.query.as_ref().is_none_or(|q| q.matches(entry));
+        types && years && author && keywords && query
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::Filter;
+    use crate::{convert, Options};
+
+    /// The keys of the converted entries.
+    fn keys(bib: &str, filter: Filter) -> Vec<String> {
+        let bib = Bibliography::parse(bib).unwrap();
+        let options = Options {
+            filter,
+            ..Default::default()
+        };
+        convert(&bib, &options).into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn types() {
+        let bib = "@article{a, title={A}} @InProceedings{b, title={B}} @phdthesis{c, title={C}}";
+        let filter = Filter {
+            types: vec!["inproceedings".into(), "Article".into()],
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter), ["a", "b"]);
+    }
+
+    #[test]
+    fn years() {
+        let bib = "@proceedings{p, title={P}, year=2021}
+                   @inproceedings{a, title={A}, crossref={p}}
+                   @article{b, title={B}, date={2019-04-01}}
+                   @article{c, title={C}, year=2023}
+                   @misc{d, title={D}}";
+        let filter = Filter {
+            from_year: Some(2020),
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter), ["a", "c", "p"]);
+        let filter = Filter {
+            from_year: Some(2019),
+            to_year: Some(2021),
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter), ["a", "b", "p"]);
+    }
+
+    #[test]
+    fn author() {
+        let bib = r"@article{a, author={Lohmann, Daniel and Doe, Jane}, title={A}}
+                    @book{b, editor={Lohmann, D.}, title={B}}
+                    @misc{c, author={Smith, John}, title={C}}";
+        let filter = Filter {
+            author: Some("^(Daniel|D\\.) Lohmann$".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter), ["a", "b"]);
+        let filter = Filter {
+            author: Some("^Smith,".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter), ["c"]);
+    }
+
+    #[test]
+    fn keywords() {
+        let bib = "@misc{a, keywords={RTOS, energy}} @misc{b, keywords={rtos}} @misc{c, title={C}}";
+        let filter = |any_keyword| Filter {
+            keywords: vec!["rtos".into(), "Energy".into()],
+            any_keyword,
+            ..Default::default()
+        };
+        assert_eq!(keys(bib, filter(false)), ["a"]);
+        assert_eq!(keys(bib, filter(true)), ["a", "b"]);
+    }
+}
@@ -0,0 +1,268 @@
+//! Graphs of the relations between entries, for auditing large bibliographies,
+//! and of the co-authorships.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use biblatex::{Bibliography, ChunksExt};
+
+use crate::xml::Xml;
+use crate::SRAPerson;
+
+/// File formats of the co-authorship network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum NetworkFormat {
+    /// GraphML, e.g., for networkx, igraph or yEd
+    #[default]
+    Graphml,
+    /// GEXF, e.g., for Gephi
+    Gexf,
+}
+
+/// The co-authorship network: a node per author and an edge between authors
+/// of the same entry, weighted by the number of shared entries.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Network {
+    /// Names in `Last, First` notation and their number of entries.
+    pub authors: Vec<(String, usize)>,
+    /// Indices of the authors and their number of shared entries.
+    pub edges: BTreeMap<(usize, usize), usize>,
+}
+
+impl Network {
+    /// Build the network of the authors of all entries.
+    pub fn new(bib: &Bibliography) -> Self {
+        let mut network = Network::default();
+        let mut index: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in bib.iter() {
+            let mut ids: Vec<usize> = entry
+                .author()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| {
+                    let name = SRAPerson::from(p).to_bibtex();
+                    *index.entry(name.clone()).or_insert_with(|| {
+                        network.authors.push((name, 0));
+                        network.authors.len() - 1
+                    })
+                })
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            for (i, &a) in ids.iter().enumerate() {
+                network.authors[a].1 += 1;
+                for &b in &ids[i + 1..] {
+                    *network.edges.entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+        network
+    }
+
+    /// The network in a file format.
+    pub fn write(&self, format: NetworkFormat) -> String {
+        match format {
+            NetworkFormat::Graphml => self.graphml(),
+            NetworkFormat::Gexf => self.gexf(),
+        }
+    }
+
+    fn graphml(&self) -> String {
+        let mut xml = Xml::new();
+        xml.open(
+            "graphml",
+            &[("xmlns", "http://graphml.graphdrawing.org/xmlns")],
+        );
+        for (id, name, kind) in [
+            ("name", "node", "string"),
+            ("entries", "node", "int"),
+            ("weight", "edge", "int"),
+        ] {
+            let attrs = [
+                ("id", id),
+                ("for", name),
+                ("attr.name", id),
+                ("attr.type", kind),
+            ];
+            xml.element("key", &attrs, "");
+        }
+        xml.open(
+            "graph",
+            &[("id", "coauthors"), ("edgedefault", "undirected")],
+        );
+        for (i, (name, entries)) in self.authors.iter().enumerate() {
+            xml.open("node", &[("id", &format!("n{i}"))]);
+            xml.element("data", &[("key", "name")], name);
+            xml.element("data", &[("key", "entries")], &entries.to_string());
+            xml.close("node");
+        }
+        for ((a, b), weight) in &self.edges {
+            let (source, target) = (format!("n{a}"), format!("n{b}"));
+            xml.open("edge", &[("source", &source), ("target", &target)]);
+            xml.element("data", &[("key", "weight")], &weight.to_string());
+            xml.close("edge");
+        }
+        xml.close("graph");
+        xml.close("graphml");
+        xml.finish()
+    }
+
+    fn gexf(&self) -> String {
+        let mut xml = Xml::new();
+        xml.open(
+            "gexf",
+            &[("xmlns", "http://gexf.net/1.3"), ("version", "1.3")],
+        );
+        xml.open("graph", &[("defaultedgetype", "undirected")]);
+        xml.open("attributes", &[("class", "node")]);
+        xml.element(
+            "attribute",
+            &[("id", "entries"), ("title", "entries"), ("type", "integer")],
+            "",
+        );
+        xml.close("attributes");
+        xml.open("nodes", &[]);
+        for (i, (name, entries)) in self.authors.iter().enumerate() {
+            xml.open("node", &[("id", &format!("n{i}")), ("label", name)]);
+            xml.open("attvalues", &[]);
+            let value = entries.to_string();
+            xml.element("attvalue", &[("for", "entries"), ("value", &value)], "");
+            xml.close("attvalues");
+            xml.close("node");
+        }
+        xml.close("nodes");
+        xml.open("edges", &[]);
+        for (i, ((a, b), weight)) in self.edges.iter().enumerate() {
+            let attrs = [
+                ("id", format!("e{i}")),
+                ("source", format!("n{a}")),
+                ("target", format!("n{b}")),
+                ("weight", weight.to_string()),
+            ];
+            let attrs: Vec<(&str, &str)> = attrs.iter().map(|(k, v)| (*k, v.as_str())).collect();
+            xml.element("edge", &attrs, "");
+        }
+        xml.close("edges");
+        xml.close("graph");
+        xml.close("gexf");
+        xml.finish()
+    }
+}
+
+/// A DOT string literal, line breaks become centered line breaks.
+fn quote(s: &str) -> String {
+    let s = s.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", s.replace('\n', "\\n"))
+}
+
+/// A Graphviz DOT digraph with an edge from each entry to its crossref and
+/// xref parents. Entries without relations are left out, parents that do not
+/// exist are drawn in red.
+pub fn dot(bib: &Bibliography) -> String {
+    let mut edges = Vec::new();
+    for entry in bib.iter() {
+        for (field, style) in [("crossref", "solid"), ("xref", "dashed")] {
+            let Some(value) = entry.get(field) else {
+                continue;
+            };
+            for parent in value.format_verbatim().split(',').map(str::trim) {
+                if !parent.is_empty() {
+                    edges.push((entry.key.as_str(), parent.to_owned(), field, style));
+                }
+            }
+        }
+    }
+
+    let mut out = String::from("digraph crossrefs {\n  rankdir=LR;\n  node [shape=box];\n");
+    let mut nodes: Vec<&str> = edges
+        .iter()
+        .flat_map(|(child, parent, _, _)| [*child, parent.as_str()])
+        .collect();
+    nodes.sort_unstable();
+    nodes.dedup();
+    for key in nodes {
+        let _ = match bib.get(key) {
+            Some(e) => writeln!(
+                out,
+                "  {} [label={}];",
+                quote(key),
+                quote(&format!("{key}\n@{}", e.entry_type))
+            ),
+            None => writeln!(out, "  {} [color=red, fontcolor=red];", quote(key)),
+        };
+    }
+    for (child, parent, field, style) in &edges {
+        let _ = writeln!(
+            out,
+            "  {} -> {} [label={field}, style={style}];",
+            quote(child),
+            quote(parent)
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{dot, Network, NetworkFormat};
+
+    #[test]
+    fn crossrefs() {
+        let bib = Bibliography::parse(
+            r#"
+            @inproceedings{a, title={A}, crossref={conf}}
+            @inproceedings{b, title={B}, xref={missing}}
+            @proceedings{conf, title={Conf}}
+            @misc{c, title={C}}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            dot(&bib),
+            r#"digraph crossrefs {
+  rankdir=LR;
+  node [shape=box];
+  "a" [label="a\n@inproceedings"];
+  "b" [label="b\n@inproceedings"];
+  "conf" [label="conf\n@proceedings"];
+  "missing" [color=red, fontcolor=red];
+  "a" -> "conf" [label=crossref, style=solid];
+  "b" -> "missing" [label=xref, style=dashed];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn coauthors() {
+        let bib = Bibliography::parse(
+            r#"
+            @misc{a, author={Doe, Jane and Smith, John}}
+            @misc{b, author={Smith, John and Jane Doe and Bar}}
+            "#,
+        )
+        .unwrap();
+        let network = Network::new(&bib);
+        assert_eq!(
+            network.authors,
+            [
+                ("Doe, Jane".to_string(), 2),
+                ("Smith, John".to_string(), 2),
+                ("Bar".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            network.edges,
+            [((0, 1), 2), ((0, 2), 1), ((1, 2), 1)].into()
+        );
+        let graphml = network.write(NetworkFormat::Graphml);
+        assert!(graphml
+            .contains("<edge source=\"n0\" target=\"n1\">\n      <data key=\"weight\">2</data>"));
+        let gexf = network.write(NetworkFormat::Gexf);
+        assert!(gexf.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\" weight=\"2\"/>"));
+    }
+}
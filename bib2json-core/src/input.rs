@@ -0,0 +1,554 @@
+//! Supported input formats.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use biblatex::{Bibliography, Chunk, Chunks, ParseError, ParseErrorKind, Spanned};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use indexmap::IndexMap;
+
+use crate::{nbib, ris, SRAEntry};
+
+/// Formats a bibliography can be read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum InputFormat {
+    Bibtex,
+    Json,
+    Ris,
+    /// MEDLINE/PubMed export
+    Nbib,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+/// The lowercase extension of a path, before a `.gz` or `.zst` extension.
+pub(crate) fn extension(path: &Path) -> Option<String> {
+    let outer = path.extension()?.to_str()?.to_ascii_lowercase();
+    match outer.as_str() {
+        "gz" | "zst" => extension(Path::new(path.file_stem()?)),
+        _ => Some(outer),
+    }
+}
+
+/// An error while reading a bibliography, with its position in the input.
+#[derive(Debug)]
+pub struct ReadError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl ReadError {
+    /// An error at a byte offset of the content.
+    pub(crate) fn at(content: &str, offset: usize, message: String) -> Self {
+        let before = &content[..offset.min(content.len())];
+        ReadError {
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|&c| c != '\n').count() + 1,
+            message,
+        }
+    }
+}
+
+impl InputFormat {
+    /// Infer the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match extension(path)?.as_str() {
+            "bib" | "bibtex" => Some(InputFormat::Bibtex),
+            "json" => Some(InputFormat::Json),
+            "ris" => Some(InputFormat::Ris),
+            "nbib" => Some(InputFormat::Nbib),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the beginning of the content.
+    pub fn detect(content: &str) -> Self {
+        let start = content.trim_start_matches('\u{feff}').trim_start();
+        if start.starts_with('{') || start.starts_with('[') {
+            InputFormat::Json
+        } else if start.starts_with("TY  -") {
+            InputFormat::Ris
+        } else if start.starts_with("PMID-") {
+            InputFormat::Nbib
+        } else {
+            InputFormat::Bibtex
+        }
+    }
+
+    /// Parse the content as a bibliography in this format.
+    pub fn parse(self, content: &str) -> Result<Bibliography, ReadError> {
+        match self {
+            InputFormat::Bibtex => Bibliography::parse(content)
+                .map_err(|e| ReadError::at(content, e.span.start, e.kind.to_string())),
+            InputFormat::Json => serde_json::from_str(content)
+                .map(|entries: IndexMap<String, SRAEntry>| from_entries(entries))
+                .map_err(|e| ReadError {
+                    line: e.line(),
+                    column: e.column(),
+                    message: e.to_string(),
+                }),
+            InputFormat::Ris => ris::parse(content).map_err(|e| ReadError {
+                line: e.line,
+                column: 1,
+                message: e.message,
+            }),
+            InputFormat::Nbib => nbib::parse(content).map_err(|e| ReadError {
+                line: e.line,
+                column: 1,
+                message: e.message,
+            }),
+            #[cfg(feature = "yaml")]
+            InputFormat::Yaml => serde_yaml::from_str(content)
+                .map(|entries: IndexMap<String, SRAEntry>| from_entries(entries))
+                .map_err(|e| {
+                    let location = e.location();
+                    ReadError {
+                        line: location.as_ref().map_or(0, |l| l.line()),
+                        column: location.as_ref().map_or(0, |l| l.column()),
+                        message: e.to_string(),
+                    }
+                }),
+        }
+    }
+}
+
+/// Read several inputs into one bibliography. The BibTeX inputs are parsed
+/// together and in order, so they share `@string` macros and crossref parents
+/// across inputs. The entries of the other inputs are added afterwards and
+/// replace entries with the same key. Errors come with the index of the input.
+pub fn parse_all(inputs: &[(InputFormat, &str)]) -> Result<Bibliography, (usize, ReadError)> {
+    read_all(inputs, Mode::Default).map(|(bib, _)| bib)
+}
+
+/// Like [`parse_all`], but BibTeX entries that cannot be parsed or resolved
+/// are left out and returned instead of failing the whole bibliography.
+pub fn parse_lenient(
+    inputs: &[(InputFormat, &str)],
+) -> Result<(Bibliography, Vec<Skipped>), (usize, ReadError)> {
+    read_all(inputs, Mode::Lenient)
+}
+
+/// Like [`parse_all`], but an entry of a later input with the key of an
+/// earlier entry is an error instead of replacing it.
+pub fn parse_strict(inputs: &[(InputFormat, &str)]) -> Result<Bibliography, (usize, ReadError)> {
+    read_all(inputs, Mode::Strict).map(|(bib, _)| bib)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Default,
+    Lenient,
+    Strict,
+}
+
+/// An entry left out of a bibliography because it could not be read.
+#[derive(Debug)]
+pub struct Skipped {
+    /// The index of the input.
+    pub input: usize,
+    /// The citation key, if one could be found.
+    pub key: Option<String>,
+    pub error: ReadError,
+}
+
+/// The offsets of the `@` starting a line, where entries usually begin.
+fn boundaries(source: &str) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut line_start = true;
+    for (i, c) in source.char_indices() {
+        match c {
+            '@' if line_start => boundaries.push(i),
+            '\n' => line_start = true,
+            c if c.is_whitespace() => {}
+            _ => line_start = false,
+        }
+        if c == '@' {
+            line_start = false;
+        }
+    }
+    boundaries
+}
+
+/// The span of the entry a parse error belongs to.
+fn invalid_entry(source: &str, error: &ParseError) -> Range<usize> {
+    let offset = error.span.start.min(source.len());
+    let boundaries = boundaries(source);
+    let mut at = boundaries.partition_point(|&b| b <= offset);
+    // An entry that is not closed is noticed at the start of the next one
+    let syntax = matches!(
+        error.kind,
+        ParseErrorKind::Expected(_) | ParseErrorKind::Unexpected(_) | ParseErrorKind::UnexpectedEof
+    );
+    if syntax && at > 1 && boundaries[at - 1] == offset {
+        at -= 1;
+    }
+    let start = if at == 0 { 0 } else { boundaries[at - 1] };
+    let end = boundaries.get(at).copied().unwrap_or(source.len());
+    start..end
+}
+
+/// The citation key of an entry's source, `None` for `@string` and the like.
+fn key_of(entry: &str) -> Option<String> {
+    let (kind, rest) = entry
+        .trim_start()
+        .strip_prefix('@')?
+        .split_once(['{', '('])?;
+    let kind = kind.trim().to_ascii_lowercase();
+    if ["string", "preamble", "comment"].contains(&kind.as_str()) {
+        return None;
+    }
+    let key = rest.split([',', '}', ')', '\n']).next()?.trim();
+    (!key.is_empty()).then(|| key.to_owned())
+}
+
+/// Where an entry starts in the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// The index of the input.
+    pub input: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The locations of the BibTeX entries of the inputs by citation key, the
+/// last one for keys in several inputs. Only entries whose `@` starts a line
+/// are found.
+pub fn locate(inputs: &[(InputFormat, &str)]) -> HashMap<String, Location> {
+    let mut locations = HashMap::new();
+    for (input, (format, content)) in inputs.iter().enumerate() {
+        if *format != InputFormat::Bibtex {
+            continue;
+        }
+        for offset in boundaries(content) {
+            if let Some(key) = key_of(&content[offset..]) {
+                let ReadError { line, column, .. } = ReadError::at(content, offset, String::new());
+                let location = Location {
+                    input,
+                    line,
+                    column,
+                };
+                locations.insert(key, location);
+            }
+        }
+    }
+    locations
+}
+
+fn read_all(
+    inputs: &[(InputFormat, &str)],
+    mode: Mode,
+) -> Result<(Bibliography, Vec<Skipped>), (usize, ReadError)> {
+    let mut source = String::new();
+    let mut starts = Vec::new();
+    for (i, (format, content)) in inputs.iter().enumerate() {
+        if *format == InputFormat::Bibtex {
+            starts.push((i, source.len()));
+            source.push_str(content);
+            source.push('\n');
+        }
+    }
+    let locate = |offset: usize, message: String| {
+        let (i, start) = starts
+            .iter()
+            .rev()
+            .find(|(_, start)| *start <= offset)
+            .copied()
+            .unwrap_or_default();
+        (i, ReadError::at(inputs[i].1, offset - start, message))
+    };
+    let mut skipped = Vec::new();
+    let mut bib = loop {
+        let e = match Bibliography::parse(&source) {
+            Ok(bib) => break bib,
+            Err(e) => e,
+        };
+        let entry = invalid_entry(&source, &e);
+        let error = locate(e.span.start.min(source.len()), e.kind.to_string());
+        if mode != Mode::Lenient || source[entry.clone()].trim().is_empty() {
+            return Err(error);
+        }
+        let (input, error) = error;
+        skipped.push(Skipped {
+            input,
+            key: key_of(&source[entry.clone()]),
+            error,
+        });
+        // Blank the entry out, keeping the offsets and lines of the rest
+        let blank: String = source[entry.clone()]
+            .chars()
+            .map(|c| match c {
+                '\n' => "\n".to_owned(),
+                c => " ".repeat(c.len_utf8()),
+            })
+            .collect();
+        source.replace_range(entry, &blank);
+    };
+    for (i, (format, content)) in inputs.iter().enumerate() {
+        if *format != InputFormat::Bibtex {
+            for entry in format.parse(content).map_err(|e| (i, e))? {
+                if mode == Mode::Strict && bib.get(&entry.key).is_some() {
+                    let offset = content.find(entry.key.as_str()).unwrap_or(0);
+                    let message = format!("duplicate key `{}`", entry.key);
+                    return Err((i, ReadError::at(content, offset, message)));
+                }
+                bib.insert(entry);
+            }
+        }
+    }
+    Ok((bib, skipped))
+}
+
+/// Build a bibliography from converted entries, keyed by their id, in the
+/// order of the document.
+pub fn from_entries(entries: impl IntoIterator<Item = (String, SRAEntry)>) -> Bibliography {
+    let mut bib = Bibliography::new();
+    for (key, mut entry) in entries {
+        if entry.id.is_empty() {
+            entry.id = key;
+        }
+        bib.insert(entry.into_entry());
+    }
+    bib
+}
+
+/// Read a bibliography file, see [`text`].
+pub fn read(path: &Path) -> io::Result<String> {
+    std::fs::read(path)
+        .and_then(text)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+}
+
+/// Decompress the content of a file if it is compressed and [`decode`] it.
+pub fn text(content: Vec<u8>) -> io::Result<String> {
+    #[cfg(feature = "gzip")]
+    let content = crate::compress::decompress(content)?;
+    #[cfg(not(feature = "gzip"))]
+    if content.starts_with(&[0x1f, 0x8b]) || content.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "compressed input is not supported, decompress it first",
+        ));
+    }
+    Ok(decode(content))
+}
+
+/// Decode the content of a file: UTF-8 or, with a byte order mark, UTF-16,
+/// and Windows-1252 if it is neither. A byte order mark is removed.
+pub fn decode(content: Vec<u8>) -> String {
+    if let Some((encoding, _)) = Encoding::for_bom(&content) {
+        return encoding.decode_with_bom_removal(&content).0.into_owned();
+    }
+    match String::from_utf8(content) {
+        Ok(content) => content,
+        Err(e) => WINDOWS_1252
+            .decode_without_bom_handling(e.as_bytes())
+            .0
+            .into_owned(),
+    }
+}
+
+/// Split a converted field value back into chunks, `$...$` becomes math.
+pub fn chunks(value: &str) -> Chunks {
+    value
+        .split('$')
+        .enumerate()
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(i, s)| {
+            let chunk = if i % 2 == 1 {
+                Chunk::Math(s.to_owned())
+            } else {
+                Chunk::Normal(s.to_owned())
+            };
+            Spanned::detached(chunk)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{decode, locate, parse_all, parse_lenient, parse_strict, InputFormat, Location};
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            InputFormat::detect("@misc{foo, title={Bar}}"),
+            InputFormat::Bibtex
+        );
+        assert_eq!(InputFormat::detect("\n  {\"foo\": {}}"), InputFormat::Json);
+        assert_eq!(
+            InputFormat::detect("TY  - JOUR\nER  - \n"),
+            InputFormat::Ris
+        );
+        assert_eq!(InputFormat::detect("PMID- 12345678\n"), InputFormat::Nbib);
+    }
+
+    #[test]
+    fn decode_encodings() {
+        assert_eq!(decode("@misc{ä}".into()), "@misc{ä}");
+        assert_eq!(decode(b"\xef\xbb\xbf@misc".to_vec()), "@misc");
+        assert_eq!(decode(b"M\xfcller".to_vec()), "Müller");
+        assert_eq!(decode(b"\x93Quoted\x94 \x96".to_vec()), "“Quoted” –");
+        let utf16: Vec<u8> = [0xfeff_u16, 'ä' as u16, '@' as u16]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        assert_eq!(decode(utf16), "ä@");
+    }
+
+    #[test]
+    fn several() {
+        let a = "@string{conf = {Conference}}\n@proceedings{p, title = conf, year = 2020}";
+        let b = "@inproceedings{x, title = {X}, crossref = {p}}";
+        let c = r#"{"y": {"entry_type": "misc", "title": "Y"}}"#;
+        let bib = parse_all(&[
+            (InputFormat::Bibtex, a),
+            (InputFormat::Bibtex, b),
+            (InputFormat::Json, c),
+        ])
+        .unwrap();
+        assert_eq!(bib.len(), 3);
+        let x = bib.get("x").unwrap();
+        assert_eq!(x.get_as::<String>("booktitle").unwrap(), "Conference");
+
+        let (i, e) = parse_all(&[
+            (InputFormat::Bibtex, a),
+            (InputFormat::Bibtex, "\n@misc{z, title = {Z}"),
+        ])
+        .unwrap_err();
+        assert_eq!((i, e.line), (1, 2));
+    }
+
+    #[test]
+    fn lenient() {
+        let a = "@misc{a, title = {A}}\n@misc{b, title = {B}\n@misc{c, title = {C}}";
+        let b = "@misc{d, title = undefined}\n@misc{a, title = {Again}}\n@misc{e, title = {E}}";
+        let (bib, skipped) =
+            parse_lenient(&[(InputFormat::Bibtex, a), (InputFormat::Bibtex, b)]).unwrap();
+        let keys: Vec<&str> = bib.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["a", "c", "e"]);
+        let skipped: Vec<_> = skipped
+            .iter()
+            .map(|s| (s.input, s.key.as_deref(), s.error.line))
+            .collect();
+        assert_eq!(
+            skipped,
+            [(0, Some("b"), 3), (1, Some("d"), 1), (1, Some("a"), 2)]
+        );
+        assert_eq!(
+            bib.get("a").unwrap().get_as::<String>("title").unwrap(),
+            "A"
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let a = "@misc{a, title = {A}}";
+        let b = "{\n  \"b\": {\"entry_type\": \"misc\"},\n  \"a\": {\"entry_type\": \"misc\"}\n}";
+        let inputs = [(InputFormat::Bibtex, a), (InputFormat::Json, b)];
+        assert_eq!(parse_all(&inputs).unwrap().len(), 2);
+        let (i, e) = parse_strict(&inputs).unwrap_err();
+        assert_eq!((i, e.line), (1, 3));
+        assert_eq!(e.message, "duplicate key `a`");
+    }
+
+    #[test]
+    fn from_path() {
+        assert_eq!(
+            InputFormat::from_path(Path::new("refs.BIB")),
+            Some(InputFormat::Bibtex)
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("out/refs.json")),
+            Some(InputFormat::Json)
+        );
+        #[cfg(feature = "yaml")]
+        assert_eq!(
+            InputFormat::from_path(Path::new("out/refs.yml")),
+            Some(InputFormat::Yaml)
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("refs.bib.gz")),
+            Some(InputFormat::Bibtex)
+        );
+        assert_eq!(InputFormat::from_path(Path::new("refs")), None);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let bib = r#"
+            @article{foo,
+                author = {Max Müller and Erika Mustermann},
+                title = {Lorem $\alpha$ Ipsum},
+                journal = {Dolor},
+                year = 2005,
+            }
+        "#;
+        let parsed = InputFormat::Bibtex.parse(bib).unwrap();
+        let json = serde_json::to_string(&crate::SRABib::new(&parsed)).unwrap();
+
+        // Entries keep their order, derived fields are left out
+        let two = r#"{
+            "b": {"entry_type": "misc", "date": "2020-01", "year": "2020", "derived": ["year"]},
+            "a": {"entry_type": "misc", "title": "A"}
+        }"#;
+        let two = InputFormat::Json.parse(two).unwrap();
+        let keys: Vec<&str> = two.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["b", "a"]);
+        assert!(two.get("b").unwrap().get("year").is_none());
+        let laid_out = InputFormat::Json
+            .parse(r#"{"x": {"ID": "x", "ENTRYTYPE": "book", "title": "X"}}"#)
+            .unwrap();
+        assert_eq!(
+            laid_out.get("x").unwrap().to_biblatex_string(),
+            "@book{x,\ntitle = {X},\n}"
+        );
+
+        let reparsed = InputFormat::Json.parse(&json).unwrap();
+        let foo = reparsed.get("foo").unwrap();
+        assert_eq!(foo.entry_type.to_string(), "article");
+        assert_eq!(foo.author().unwrap().len(), 2);
+        assert_eq!(foo.author().unwrap()[0].name, "Müller");
+        assert_eq!(
+            foo.get_as::<String>("title").unwrap(),
+            parsed
+                .get("foo")
+                .unwrap()
+                .get_as::<String>("title")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let a = "% refs\n@article{a, title={A}}\n@string{x = {X}}\n\n@misc{b,\n title={B}}\n";
+        let b = "@misc{b, title={B}}\n";
+        let locations = locate(&[(InputFormat::Bibtex, a), (InputFormat::Bibtex, b)]);
+        assert_eq!(locations.len(), 2);
+        assert_eq!(
+            locations["a"],
+            Location {
+                input: 0,
+                line: 2,
+                column: 1
+            }
+        );
+        assert_eq!(locations["b"].input, 1);
+    }
+}
@@ -0,0 +1,269 @@
+//! Citation keys from JabRef-style patterns like `[auth][year][veryshorttitle]`.
+//!
+//! A pattern is literal text with markers in brackets:
+//!
+//! - `[auth]` and `[authN]`: the last name of the first author, or of the
+//!   first editor, or its first `N` characters
+//! - `[authors]`: the last names of all authors
+//! - `[year]` and `[shortyear]`: the year with at least four or the last two
+//!   digits, years before 1 with a `-` like `-0044` and `-44`
+//! - `[title]`, `[shorttitle]` and `[veryshorttitle]`: all, three or one of
+//!   the title words that are not function words, capitalized
+//! - `[FIELD]`: the value of any other field
+//!
+//! Markers take the modifiers `:lower` and `:upper`, like `[auth:lower]`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use biblatex::{Bibliography, ChunksExt, Entry};
+use deunicode::deunicode;
+
+/// Named patterns.
+const SCHEMES: &[(&str, &str)] = &[
+    ("FirstAuthorYear", "[auth][year]"),
+    ("FirstAuthorYearTitleWord", "[auth][year][veryshorttitle]"),
+    ("AuthorsYear", "[authors][year]"),
+];
+
+/// Title words that are left out.
+const FUNCTION_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "by", "for", "from", "in", "of", "on", "or", "the", "to", "with",
+];
+
+/// A pattern that cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError(String);
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Keep,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Marker(String, Case),
+}
+
+/// A parsed key pattern, or the name of a scheme like `FirstAuthorYearTitleWord`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern(Vec<Part>);
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = SCHEMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map_or(s, |(_, pattern)| pattern);
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('[') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find(']')
+                .ok_or_else(|| PatternError(format!("unclosed `[` in `{s}`")))?;
+            let marker = &rest[start + 1..start + end];
+            let (name, case) = match marker.split_once(':') {
+                None => (marker, Case::Keep),
+                Some((name, "lower")) => (name, Case::Lower),
+                Some((name, "upper")) => (name, Case::Upper),
+                Some((_, modifier)) => {
+                    return Err(PatternError(format!("unknown modifier `{modifier}`")))
+                }
+            };
+            parts.push(Part::Marker(name.to_lowercase(), case));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+        Ok(Pattern(parts))
+    }
+}
+
+/// The last names of the authors, or of the editors if there are none.
+fn names(entry: &Entry) -> Vec<String> {
+    let authors = entry.author().unwrap_or_default();
+    let persons = match authors.is_empty() {
+        true => entry
+            .editors()
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(persons, _)| persons)
+            .collect(),
+        false => authors,
+    };
+    persons.into_iter().map(|p| p.name).collect()
+}
+
+/// The capitalized title words that are not function words.
+fn title_words(entry: &Entry) -> Vec<String> {
+    let title = entry
+        .title()
+        .map(|t| t.format_verbatim())
+        .unwrap_or_default();
+    deunicode(&title)
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty() && !FUNCTION_WORDS.contains(&w.to_lowercase().as_str()))
+        .map(|w| w[..1].to_uppercase() + &w[1..])
+        .collect()
+}
+
+/// The value of a marker for an entry.
+fn marker(entry: &Entry, name: &str) -> String {
+    let year = || {
+        crate::date::sort_key(entry)
+            .map(|key| crate::date::sort_year(&key).to_owned())
+            .unwrap_or_default()
+    };
+    let short_year = || {
+        let year = year();
+        let (sign, digits) = match year.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", year.as_str()),
+        };
+        let short = digits
+            .get(digits.len().saturating_sub(2)..)
+            .unwrap_or_default();
+        format!("{sign}{short}")
+    };
+    match name {
+        "auth" => names(entry).into_iter().next().unwrap_or_default(),
+        "authors" => names(entry).concat(),
+        "year" => year(),
+        "shortyear" => short_year(),
+        "title" => title_words(entry).concat(),
+        "shorttitle" => title_words(entry).into_iter().take(3).collect(),
+        "veryshorttitle" => title_words(entry).into_iter().take(1).collect(),
+        _ => match name.strip_prefix("auth").and_then(|n| n.parse().ok()) {
+            Some(n) => {
+                let name = names(entry).into_iter().next().unwrap_or_default();
+                name.chars().take(n).collect()
+            }
+            None => entry
+                .get(name)
+                .map(|v| v.format_verbatim())
+                .unwrap_or_default(),
+        },
+    }
+}
+
+impl Pattern {
+    /// The key of an entry, with only ASCII letters, digits and `-_:./`.
+    pub fn key(&self, entry: &Entry) -> String {
+        let mut key = String::new();
+        for part in &self.0 {
+            match part {
+                Part::Literal(text) => key.push_str(text),
+                Part::Marker(name, case) => {
+                    let value = marker(entry, name);
+                    key.push_str(&match case {
+                        Case::Keep => value,
+                        Case::Lower => value.to_lowercase(),
+                        Case::Upper => value.to_uppercase(),
+                    });
+                }
+            }
+        }
+        deunicode(&key)
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || "-_:./".contains(*c))
+            .collect()
+    }
+}
+
+/// The new keys of the entries whose generated key differs from their own,
+/// as old and new key in source order. Entries with the same generated key
+/// get the suffixes `a`, `b` and so on, entries without any of the parts of
+/// the pattern keep their keys.
+pub fn assign(bib: &Bibliography, pattern: &Pattern) -> Vec<(String, String)> {
+    let keys: Vec<String> = bib.iter().map(|e| pattern.key(e)).collect();
+    // Kept keys count as generated ones, so that others get a suffix
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (entry, key) in bib.iter().zip(&keys) {
+        let key = if key.is_empty() { &entry.key } else { key };
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut used: HashMap<&str, usize> = HashMap::new();
+    let mut renames = Vec::new();
+    for (entry, key) in bib.iter().zip(&keys) {
+        if key.is_empty() {
+            continue;
+        }
+        let mut new = key.clone();
+        if counts[key.as_str()] > 1 {
+            let n = used.entry(key).or_default();
+            new.push((b'a' + (*n % 26) as u8) as char);
+            if *n >= 26 {
+                new.push_str(&(*n / 26).to_string());
+            }
+            *n += 1;
+        }
+        if new != entry.key {
+            renames.push((entry.key.clone(), new));
+        }
+    }
+    renames
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{assign, Pattern};
+
+    #[test]
+    fn keys() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{x, author={M{\"u}ller, Jane and Doe, John}, title={On the Art of Lorem Ipsum}, year=2020}
+            @article{y, author={Müller, J.}, title={The Art}, date={2020-05}}
+            @book{z, editor={Roe, Rick}, title={Dolor}, year=1999, journal={J. Foo}}
+            @misc{Muller2021, author={Müller, Jane}, title={Sit Amet}, year=2021}
+            @misc{Roe1999, note={N}}
+            @book{c44, author={Caesar, Gaius Iulius}, date={-0044}}
+            @book{c45, author={Caesar, Gaius Iulius}, date={-0045}}
+            "#,
+        )
+        .unwrap();
+        let pattern: Pattern = "FirstAuthorYearTitleWord".parse().unwrap();
+        assert_eq!(pattern.key(bib.get("x").unwrap()), "Muller2020Art");
+        let pattern: Pattern = "[auth3:lower]-[shortyear]-[shorttitle]_[journal]"
+            .parse()
+            .unwrap();
+        assert_eq!(pattern.key(bib.get("z").unwrap()), "roe-99-Dolor_J.Foo");
+        assert_eq!(pattern.key(bib.get("x").unwrap()), "mul-20-ArtLoremIpsum_");
+        assert_eq!(pattern.key(bib.get("c44").unwrap()), "cae--44-_");
+
+        let pattern: Pattern = "[auth][year]".parse().unwrap();
+        assert_eq!(
+            assign(&bib, &pattern),
+            [
+                ("x", "Muller2020a"),
+                ("y", "Muller2020b"),
+                ("z", "Roe1999a"),
+                ("c44", "Caesar-0044"),
+                ("c45", "Caesar-0045")
+            ]
+            .map(|(a, b)| (a.to_owned(), b.to_owned()))
+        );
+        assert!("[auth".parse::<Pattern>().is_err());
+        assert!("[auth:title]".parse::<Pattern>().is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Conversion of leftover LaTeX markup into readable plain text.
+
+/// Math commands and their Unicode equivalents.
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("sim", "∼"),
+    ("equiv", "≡"),
+    ("times", "×"),
+    ("cdot", "·"),
+    ("pm", "±"),
+    ("infty", "∞"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("wedge", "∧"),
+    ("vee", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("leftrightarrow", "↔"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("log", "log"),
+    ("ln", "ln"),
+    ("max", "max"),
+    ("min", "min"),
+    ("%", "%"),
+    ("_", "_"),
+    ("&", "&"),
+    ("#", "#"),
+    ("$", "$"),
+    (",", "\u{2009}"),
+    (";", " "),
+    ("!", ""),
+    ("\\", " "),
+];
+
+/// Commands that are dropped together with their argument.
+const DROPPED: &[&str] = &["cite", "citep", "citet", "ref", "label", "footnote"];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('n', 'ⁿ'),
+    ('i', 'ⁱ'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+];
+
+/// Read a `{...}` group or a single character argument.
+fn argument(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    while chars.next_if(|c| *c == ' ').is_some() {}
+    match chars.next() {
+        Some('{') => {
+            let mut depth = 1;
+            let mut arg = String::new();
+            for c in chars.by_ref() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                arg.push(c);
+            }
+            arg
+        }
+        Some(c) => c.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Replace `^x` and `_x` with Unicode super- or subscripts if possible.
+fn script(arg: &str, table: &[(char, char)], marker: char) -> String {
+    let mapped: Option<String> = arg
+        .chars()
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect();
+    mapped.unwrap_or_else(|| {
+        let mut text = String::new();
+        convert(arg, true, &mut text);
+        match text.chars().count() {
+            1 => format!("{marker}{text}"),
+            _ => format!("{marker}({text})"),
+        }
+    })
+}
+
+fn convert(text: &str, math: bool, out: &mut String) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                }
+                if name.is_empty() {
+                    if let Some(c) = chars.next() {
+                        name.push(c);
+                    }
+                }
+                if let Some((_, symbol)) = SYMBOLS.iter().find(|(cmd, _)| *cmd == name) {
+                    out.push_str(symbol);
+                } else if DROPPED.contains(&name.as_str()) {
+                    argument(&mut chars);
+                    let trimmed = out.trim_end().len();
+                    out.truncate(trimmed);
+                } else if chars.peek() == Some(&'{') {
+                    // \emph{...}, \textbf{...}, \mathcal{...}: keep the content
+                    convert(&argument(&mut chars), math, out);
+                }
+            }
+            '$' => {
+                let mut formula = String::new();
+                for c in chars.by_ref() {
+                    if c == '$' {
+                        break;
+                    }
+                    formula.push(c);
+                }
+                convert(&formula, true, out);
+            }
+            '^' if math => out.push_str(&script(&argument(&mut chars), SUPERSCRIPTS, '^')),
+            '_' if math => out.push_str(&script(&argument(&mut chars), SUBSCRIPTS, '_')),
+            '{' | '}' => {}
+            '~' => out.push(' '),
+            '`' if chars.next_if_eq(&'`').is_some() => out.push('“'),
+            '\'' if chars.next_if_eq(&'\'').is_some() => out.push('”'),
+            c if math && c == ' ' => {
+                if !out.ends_with(' ') {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+/// Convert LaTeX markup into plain text and collapse whitespace.
+///
+/// Formatting commands keep their content, math becomes Unicode where
+/// possible and citations are removed.
+pub fn to_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    convert(text, false, &mut out);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_text;
+
+    #[test]
+    fn abstract_cleanup() {
+        assert_eq!(
+            to_text(
+                r"We reduce   latency by 50% in \emph{real-time}
+                  systems with $O(n^2)$ cost, $\alpha \leq 0.5$, $x_{10}$ and ``quotes'' \cite{x}."
+            ),
+            "We reduce latency by 50% in real-time systems with O(n²) cost, α ≤ 0.5, x₁₀ and “quotes”."
+        );
+        assert_eq!(
+            to_text(r"$2^{k+1}$ \textbf{fast\_path}"),
+            "2^(k+1) fast_path"
+        );
+    }
+}
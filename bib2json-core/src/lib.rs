@@ -0,0 +1,1099 @@
+//! Conversion of biblatex bibliographies into JSON and other formats.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use biblatex::{
+    Bibliography, Chunk, Chunks, ChunksExt, Entry, EntryType, Person, RetrievalError, Spanned,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::Progress;
+
+pub mod bcf;
+pub mod cache;
+#[cfg(feature = "yaml")]
+mod cff;
+pub mod check;
+pub mod cite;
+#[cfg(feature = "gzip")]
+pub mod compress;
+pub mod csl;
+mod datacite;
+pub mod date;
+pub mod diff;
+mod dublincore;
+pub mod duplicates;
+pub mod edit;
+#[cfg(feature = "enrich")]
+pub mod enrich;
+pub mod escape;
+#[cfg(feature = "enrich")]
+pub mod fetch;
+pub mod fields;
+pub mod files;
+pub mod filter;
+pub mod graph;
+pub mod input;
+pub mod keygen;
+pub mod latex;
+pub mod merge;
+mod mods;
+pub mod names;
+mod nbib;
+pub mod notes;
+pub mod output;
+pub mod parallel;
+pub mod pretty;
+pub mod query;
+mod ris;
+pub mod slug;
+pub mod sort;
+pub mod stats;
+mod xml;
+
+use date::DateRange;
+use escape::Escape;
+use filter::Filter;
+use names::NameFormat;
+use output::Layout;
+use sort::{Locale, SortBy};
+
+/// Options for the conversion of entries.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Put fields from crossref, xref and xdata parents into a separate
+    /// `inherited` object instead of merging them into the entry.
+    pub inherited: bool,
+    /// Add a preformatted `display_name` to persons and `authors_str` to entries.
+    pub name_format: Option<NameFormat>,
+    /// Locale for the collation of the entry order.
+    pub locale: Locale,
+    /// Order of the entries.
+    pub sort_by: SortBy,
+    /// Reverse the order of the entries, entries without a year or author stay last.
+    pub reverse: bool,
+    /// Maximum number of characters per field, longer values are cut and end with `…`.
+    pub truncate: BTreeMap<String, usize>,
+    /// Convert LaTeX markup and math in `abstract` fields into plain text.
+    pub clean_abstract: bool,
+    /// Escaping of the string values, except keys and entry types.
+    pub escape: Escape,
+    /// Structure of the entries in the JSON and YAML output.
+    pub layout: Layout,
+    /// Only convert the entries with these keys.
+    pub keys: Option<BTreeSet<String>>,
+    /// Only convert the entries that match the filter.
+    pub filter: Filter,
+    /// Only keep these fields of the entries, all if empty.
+    pub fields: Vec<String>,
+    /// Remove these fields from the entries.
+    pub exclude_fields: Vec<String>,
+    /// Remove these fields from the entries and from their BibTeX, ignoring
+    /// case, e.g., [`PRIVATE_FIELDS`].
+    pub redact: Vec<String>,
+    /// Skip this many entries after sorting and filtering.
+    pub offset: usize,
+    /// Keep at most this many entries after the offset.
+    pub limit: Option<usize>,
+    /// Add a `url` from the `doi` to entries without one.
+    pub doi_url: bool,
+    /// Merge the fields of this many levels of parents instead of only the
+    /// direct ones, and append their BibTeX to [`SRAEntry::bibtex`].
+    pub crossref_depth: Option<usize>,
+    /// Drop entries that lack one of these fields, see [`check::require`].
+    pub require: Vec<String>,
+    /// Keep the entries that lack a required field.
+    pub keep_incomplete: bool,
+    /// List the fields that the entry type requires but the entry lacks in
+    /// [`SRAEntry::missing`].
+    pub missing: bool,
+    /// Columns of tabular output, empty for the default ones.
+    pub columns: Vec<String>,
+    /// Separator of the persons in a column of tabular output, `; ` if unset.
+    pub person_separator: Option<String>,
+    /// Group the entries of Markdown output by year, newest first.
+    pub group_by_year: bool,
+    /// Indent JSON output by this many spaces per level instead of writing a single line.
+    pub indent: Option<usize>,
+    /// Convert the entries on this many threads, on the calling thread if at most one.
+    pub jobs: usize,
+    /// Counts the converted entries.
+    pub progress: Progress,
+}
+
+/// Fields that reference managers use for local files, private notes and
+/// bookkeeping, removed before publishing a bibliography.
+pub const PRIVATE_FIELDS: &[&str] = &[
+    "annotation",
+    "annote",
+    "comment",
+    "comments",
+    "date-added",
+    "date-modified",
+    "file",
+    "groups",
+    "local-url",
+    "mendeley-tags",
+    "notes",
+    "owner",
+    "pdf",
+    "review",
+    "timestamp",
+];
+
+impl Options {
+    /// Whether [`Options::redact`] removes the field.
+    fn redacts(&self, field: &str) -> bool {
+        self.redact.iter().any(|f| f.eq_ignore_ascii_case(field))
+    }
+
+    /// Whether [`Options::fields`] selects one of the names and
+    /// [`Options::exclude_fields`] excludes none of them, ignoring case.
+    fn selects(&self, names: &[&str]) -> bool {
+        let has = |fields: &[String]| {
+            fields
+                .iter()
+                .any(|f| names.iter().any(|n| f.eq_ignore_ascii_case(n)))
+        };
+        (self.fields.is_empty() || has(&self.fields)) && !has(&self.exclude_fields)
+    }
+
+    /// The BibTeX of an entry without the redacted and unselected fields.
+    fn bibtex(&self, e: &Entry) -> String {
+        if self.redact.is_empty() && self.fields.is_empty() && self.exclude_fields.is_empty() {
+            return e.to_biblatex_string();
+        }
+        let mut e = e.clone();
+        e.fields
+            .retain(|field, _| !self.redacts(field) && self.selects(&[field]));
+        e.to_biblatex_string()
+    }
+
+    /// The page of the sorted and filtered entries selected by
+    /// [`Options::offset`] and [`Options::limit`].
+    pub fn paginate(&self, entries: Vec<SRAEntry>) -> Vec<SRAEntry> {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        entries.into_iter().skip(self.offset).take(limit).collect()
+    }
+}
+
+/// A value of an option that is none of the names of its variants, e.g., of
+/// [`SortBy`] or [`Escape`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownName {
+    name: String,
+    expected: Vec<&'static str>,
+}
+
+impl std::fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected: Vec<String> = self.expected.iter().map(|n| format!("`{n}`")).collect();
+        write!(
+            f,
+            "unknown value `{}`, expected one of {}",
+            self.name,
+            expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownName {}
+
+/// The variant with the name in a table of names and variants, ignoring case.
+pub(crate) fn by_name<T: Copy>(table: &[(&'static str, T)], name: &str) -> Result<T, UnknownName> {
+    table
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, variant)| *variant)
+        .ok_or_else(|| UnknownName {
+            name: name.to_owned(),
+            expected: table.iter().map(|(n, _)| *n).collect(),
+        })
+}
+
+/// An author or editor.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SRAPerson {
+    pub first_name: String,
+    pub last_name: String,
+    /// Only with [`Options::name_format`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+impl From<Person> for SRAPerson {
+    fn from(person: Person) -> Self {
+        SRAPerson {
+            first_name: person.given_name,
+            last_name: [person.prefix, person.name, person.suffix]
+                .into_iter()
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<String>>()
+                .join(" "),
+            display_name: None,
+        }
+    }
+}
+
+impl SRAPerson {
+    /// The name in BibTeX `Last, First` notation.
+    pub fn to_bibtex(&self) -> String {
+        if self.first_name.is_empty() {
+            self.last_name.clone()
+        } else {
+            format!("{}, {}", self.last_name, self.first_name)
+        }
+    }
+}
+
+/// A converted entry, with the fields of its crossref and xref parents.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SRAEntry {
+    /// `ID` and `ENTRYTYPE` are read as well, as in [`Layout::Bibtexparser`].
+    #[serde(default, alias = "ID")]
+    pub id: String,
+    #[serde(default)]
+    pub authors: Vec<SRAPerson>,
+    #[serde(default)]
+    pub editors: Vec<SRAPerson>,
+    /// The joined display names of the authors, only with [`Options::name_format`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authors_str: Option<String>,
+    #[serde(alias = "ENTRYTYPE")]
+    pub entry_type: String,
+    /// Empty if [`SRAEntry::select_fields`] removed it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub bibtex: String,
+    /// Only with [`SortBy::Biblatex`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort_key: Vec<String>,
+    /// Fields that were shortened by [`Options::truncate`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<String>,
+    /// Fields that were not in the source but derived from others, e.g., by [`Options::doi_url`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived: Vec<String>,
+    /// Unique ASCII name for URLs and file names.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub slug: String,
+    /// The date as `YYYY-MM-DD` for sorting, missing parts are `00`, years
+    /// before 1 are negative like `-0044-03-15`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_sort: Option<String>,
+    /// The start and end of a `date` range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_range: Option<DateRange>,
+    /// Fields required by the entry type that are absent, only with [`Options::missing`].
+    #[serde(rename = "_missing", default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Keys of the `@set` entries that contain this entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sets: Vec<String>,
+    /// Fields of the parents, only with [`Options::inherited`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inherited: BTreeMap<String, String>,
+
+    #[serde(flatten)]
+    pub other: BTreeMap<String, String>,
+}
+
+impl SRAEntry {
+    /// The value of an own or inherited field.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.other
+            .get(key)
+            .or_else(|| self.inherited.get(key))
+            .map(String::as_str)
+    }
+
+    /// Remove the fields that [`Options::fields`] does not select or that
+    /// [`Options::exclude_fields`] excludes, ignoring case.
+    ///
+    /// The other properties are selected by their names, like `slug` or
+    /// `date_sort`, the persons also by `author` and `editor`. The `id` and
+    /// `entry_type` are always kept. The `bibtex` source is already without
+    /// the fields after the conversion with the same options.
+    pub fn select_fields(&mut self, options: &Options) {
+        self.other.retain(|key, _| options.selects(&[key]));
+        self.inherited.retain(|key, _| options.selects(&[key]));
+        let kept = |key: &String| self.other.contains_key(key) || self.inherited.contains_key(key);
+        self.truncated.retain(kept);
+        self.derived.retain(kept);
+        if !options.selects(&["authors", "author"]) {
+            self.authors.clear();
+        }
+        if !options.selects(&["authors_str", "authors", "author"]) {
+            self.authors_str = None;
+        }
+        if !options.selects(&["editors", "editor"]) {
+            self.editors.clear();
+        }
+        if !options.selects(&["bibtex"]) {
+            self.bibtex.clear();
+        }
+        if !options.selects(&["sort_key"]) {
+            self.sort_key.clear();
+        }
+        if !options.selects(&["slug"]) {
+            self.slug.clear();
+        }
+        if !options.selects(&["date_sort"]) {
+            self.date_sort = None;
+        }
+        if !options.selects(&["date_range"]) {
+            self.date_range = None;
+        }
+        if !options.selects(&["_missing"]) {
+            self.missing.clear();
+        }
+        if !options.selects(&["sets"]) {
+            self.sets.clear();
+        }
+    }
+
+    /// The comma-separated entries of the `keywords` field.
+    pub fn keywords(&self) -> Vec<&str> {
+        self.get("keywords")
+            .into_iter()
+            .flat_map(|k| k.split(','))
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .collect()
+    }
+
+    /// The year of the date, like `2023` or `-0044`, or of the `year` field.
+    pub fn year(&self) -> Option<&str> {
+        self.date_sort
+            .as_deref()
+            .map(date::sort_year)
+            .or_else(|| self.get("year"))
+    }
+
+    fn fields(from: &Entry) -> impl Iterator<Item = (String, String)> + '_ {
+        from.fields
+            .iter()
+            .map(|(key, value)| (key.to_owned(), Self::value(value)))
+    }
+
+    /// A field value as converted, with math between `$`.
+    fn value(chunks: &[Spanned<Chunk>]) -> String {
+        chunks
+            .iter()
+            .map(|v| match &v.v {
+                Chunk::Math(s) => format!("${s}$"),
+                c => c.get().to_owned(),
+            })
+            .collect()
+    }
+
+    /// The crossref and xref parents up to the given depth, nearest first,
+    /// each entry at most once.
+    fn ancestors<'a>(e: &Entry, bib: &'a Bibliography, depth: usize) -> Vec<&'a Entry> {
+        let mut seen = HashSet::from([e.key.as_str()]);
+        let mut ancestors = Vec::new();
+        let parents = match e.parents() {
+            Ok(parents) => parents,
+            Err(err) => {
+                log::warn!(key = e.key.as_str(); "`{}`: malformed crossref: {err}", e.key);
+                Vec::new()
+            }
+        };
+        for id in parents.iter().filter(|id| bib.get(id).is_none()) {
+            log::warn!(key = e.key.as_str(); "`{}`: crossref target `{id}` not found", e.key);
+        }
+        let mut level = vec![parents];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for parent in level.into_iter().flatten().filter_map(|id| bib.get(&id)) {
+                if seen.insert(&parent.key) {
+                    ancestors.push(parent);
+                    next.push(parent.parents().unwrap_or_default());
+                }
+            }
+            level = next;
+        }
+        ancestors
+    }
+
+    /// Fields that were copied from crossref and xdata parents while parsing.
+    fn resolved<'a>(e: &'a Entry, parents: &[&Entry], bib: &Bibliography) -> HashSet<&'a str> {
+        // Copied fields keep the spans of the parent, fields created during
+        // parsing are detached.
+        let from_source = e.fields.values().flatten().any(|c| !c.is_detached());
+        let spans: HashSet<_> = parents
+            .iter()
+            .copied()
+            .chain(bib.iter().filter(|p| p.entry_type == EntryType::XData))
+            .flat_map(|p| p.fields.values())
+            .map(|v| v.span())
+            .collect();
+        e.fields
+            .iter()
+            .filter(|(_, v)| match v.iter().any(|c| c.is_detached()) {
+                true => from_source,
+                false => spans.contains(&v.span()),
+            })
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    pub fn from(e: &Entry, bib: &Bibliography) -> Self {
+        Self::with_options(e, bib, &Options::default())
+    }
+
+    pub fn with_options(e: &Entry, bib: &Bibliography, options: &Options) -> Self {
+        Self::with_sets(e, bib, options, &sets(bib))
+    }
+
+    /// Convert with the keys of the `@set` entries of each member, see [`sets`].
+    fn with_sets(
+        e: &Entry,
+        bib: &Bibliography,
+        options: &Options,
+        sets: &HashMap<String, Vec<String>>,
+    ) -> Self {
+        for field in date::unparseable(e) {
+            log::warn!(key = e.key.as_str(), field; "`{}`: field `{field}` unparseable", e.key);
+        }
+        if let Err(RetrievalError::TypeError(err)) = e.author() {
+            log::warn!(key = e.key.as_str(), field = "author"; "`{}`: field `author` unparseable: {err}", e.key);
+        }
+        // Add xref and crossref fields, nearer parents overwrite farther ones
+        let parents = Self::ancestors(e, bib, options.crossref_depth.unwrap_or(1));
+        let parent_fields = parents.iter().rev().flat_map(|p| Self::fields(p));
+
+        let (mut other, mut inherited): (BTreeMap<_, _>, BTreeMap<_, _>) = if options.inherited {
+            let resolved = Self::resolved(e, &parents, bib);
+            let (copied, own): (Vec<_>, Vec<_>) =
+                Self::fields(e).partition(|(key, _)| resolved.contains(key.as_str()));
+            let other: BTreeMap<_, _> = own.into_iter().collect();
+            let inherited = parent_fields
+                .chain(copied)
+                .filter(|(key, _)| !other.contains_key(key))
+                .collect();
+            (other, inherited)
+        } else {
+            // Own fields overwrite parent ones
+            (
+                parent_fields.chain(Self::fields(e)).collect(),
+                BTreeMap::new(),
+            )
+        };
+
+        other.retain(|field, _| !options.redacts(field));
+        inherited.retain(|field, _| !options.redacts(field));
+
+        // Sets contain the sources of their members
+        let mut sources = vec![e];
+        if e.entry_type == EntryType::Set {
+            sources.extend(set_members(e).iter().filter_map(|key| bib.get(key)));
+        }
+        if options.crossref_depth.is_some() {
+            sources.extend(&parents);
+        }
+
+        let mut entry = SRAEntry {
+            id: e.key.to_owned(),
+            authors: e
+                .author()
+                .unwrap_or_default()
+                .into_iter()
+                .map(SRAPerson::from)
+                .collect(),
+            editors: e
+                .editors()
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|tup| tup.0)
+                .map(SRAPerson::from)
+                .collect(),
+            authors_str: None,
+            entry_type: e.entry_type.to_string(),
+            bibtex: sources
+                .iter()
+                .map(|e| options.bibtex(e))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            sort_key: Vec::new(),
+            truncated: Vec::new(),
+            derived: Vec::new(),
+            slug: slug::slug(e),
+            date_sort: date::sort_key(e),
+            date_range: None,
+            missing: Vec::new(),
+            sets: sets.get(&e.key).cloned().unwrap_or_default(),
+            inherited,
+            other,
+        };
+
+        if let Some(format) = &options.name_format {
+            for person in entry.authors.iter_mut().chain(&mut entry.editors) {
+                person.display_name = Some(format.format(person));
+            }
+            entry.authors_str = Some(format.join(&entry.authors));
+        }
+        if options.sort_by == SortBy::Biblatex {
+            entry.sort_key = sort::biblatex_key(&entry);
+        }
+        if let Some(date) = date::date(e) {
+            entry.date_range = date::range(&date);
+            if entry.get("year").is_none() {
+                entry.other.insert("year".into(), date::year(&date));
+                entry.derived.push("year".into());
+            }
+        }
+        if options.missing {
+            entry.missing = e
+                .verify()
+                .missing
+                .into_iter()
+                .filter(|field| entry.get(field).is_none())
+                .map(str::to_owned)
+                .collect();
+        }
+        if options.doi_url && entry.get("url").is_none() {
+            if let Some(doi) = entry.get("doi") {
+                let url = format!("https://doi.org/{}", strip_doi_prefix(doi));
+                entry.other.insert("url".into(), url);
+                entry.derived.push("url".into());
+            }
+        }
+        for (key, value) in entry.other.iter_mut().chain(&mut entry.inherited) {
+            if options.clean_abstract && key == "abstract" {
+                *value = latex::to_text(value);
+            }
+            if let Some(&max) = options.truncate.get(key) {
+                if let Some((end, _)) = value.char_indices().nth(max) {
+                    value.truncate(end);
+                    value.push('…');
+                    entry.truncated.push(key.clone());
+                }
+            }
+            if options.escape != Escape::None {
+                *value = options.escape.field(value);
+            }
+        }
+        if options.escape != Escape::None {
+            entry.bibtex = options.escape.text(&entry.bibtex);
+            for person in entry.authors.iter_mut().chain(&mut entry.editors) {
+                person.first_name = options.escape.text(&person.first_name);
+                person.last_name = options.escape.text(&person.last_name);
+                person.display_name = person
+                    .display_name
+                    .as_deref()
+                    .map(|n| options.escape.text(n));
+            }
+            entry.authors_str = entry.authors_str.as_deref().map(|s| options.escape.text(s));
+            entry.sort_key = entry
+                .sort_key
+                .iter()
+                .map(|k| options.escape.text(k))
+                .collect();
+        }
+        entry
+    }
+
+    /// Reconstruct a biblatex entry from the converted fields, without the
+    /// derived ones.
+    ///
+    /// Fields with the same value as in the `bibtex` source keep its markup,
+    /// like braces that protect the case, the others are taken as they are.
+    /// En and em dashes are written as `--` and `---`.
+    pub fn into_entry(self) -> Entry {
+        let source = Bibliography::parse(&self.bibtex)
+            .ok()
+            .and_then(|bib| bib.get(&self.id).cloned());
+        let mut entry = Entry::new(self.id, EntryType::new(&self.entry_type));
+        for (key, value) in self.inherited.iter().chain(&self.other) {
+            if self.derived.contains(key) {
+                continue;
+            }
+            let unchanged = source
+                .as_ref()
+                .and_then(|source| source.fields.get(key))
+                .filter(|chunks| Self::value(chunks) == *value);
+            let chunks = match unchanged {
+                Some(chunks) => chunks.clone(),
+                None => input::chunks(value),
+            };
+            entry.set(key, dashes(chunks));
+        }
+        for (field, persons) in [("author", self.authors), ("editor", self.editors)] {
+            if !persons.is_empty() && entry.get(field).is_none() {
+                let names: Vec<String> = persons.iter().map(SRAPerson::to_bibtex).collect();
+                entry.set(field, dashes(input::chunks(&names.join(" and "))));
+            }
+        }
+        entry
+    }
+}
+
+/// The chunks with en and em dashes in text as `--` and `---`, which the
+/// parser turns into the dashes.
+fn dashes(chunks: Chunks) -> Chunks {
+    chunks
+        .into_iter()
+        .map(|mut chunk| {
+            if let Chunk::Normal(text) = &mut chunk.v {
+                *text = text.replace('—', "---").replace('–', "--");
+            }
+            chunk
+        })
+        .collect()
+}
+
+/// The keys in the `entryset` field of a `@set` entry.
+fn set_members(e: &Entry) -> Vec<String> {
+    e.get_as("entryset").unwrap_or_default()
+}
+
+/// The keys of the `@set` entries that contain an entry, by its key.
+fn sets(bib: &Bibliography) -> HashMap<String, Vec<String>> {
+    let mut sets: HashMap<String, Vec<String>> = HashMap::new();
+    for set in bib.iter().filter(|s| s.entry_type == EntryType::Set) {
+        for member in set_members(set) {
+            let containing = sets.entry(member).or_default();
+            if containing.last() != Some(&set.key) {
+                containing.push(set.key.clone());
+            }
+        }
+    }
+    sets
+}
+
+/// The DOI without a resolver or `doi:` prefix.
+pub(crate) fn strip_doi_prefix(doi: &str) -> &str {
+    let doi = doi.trim();
+    [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "http://dx.doi.org/",
+        "doi:",
+    ]
+    .iter()
+    .find_map(|prefix| doi.strip_prefix(prefix))
+    .unwrap_or(doi)
+}
+
+/// A converted bibliography, keyed by citation key in output order.
+#[derive(Serialize, Debug)]
+pub struct SRABib {
+    #[serde(flatten)]
+    pub entries: IndexMap<String, SRAEntry>,
+}
+
+impl SRABib {
+    pub fn new(bib: &Bibliography) -> Self {
+        Self::with_options(bib, &Options::default())
+    }
+
+    pub fn with_options(bib: &Bibliography, options: &Options) -> Self {
+        let entries = convert(bib, options)
+            .into_iter()
+            .map(|e| (e.id.clone(), e))
+            .collect();
+
+        Self { entries }
+    }
+}
+
+/// A JSON Schema of the JSON output with the default layout.
+#[cfg(feature = "schema")]
+pub fn schema() -> schemars::Schema {
+    let mut schema = schemars::schema_for!(BTreeMap<String, SRAEntry>);
+    schema.insert("title".into(), "bib2json".into());
+    schema.insert(
+        "description".into(),
+        "Converted entries, keyed by citation key".into(),
+    );
+    schema
+}
+
+/// Convert the entries of the bibliography, in output order.
+pub fn convert(bib: &Bibliography, options: &Options) -> Vec<SRAEntry> {
+    let mut entries = convert_unselected(bib, options);
+    for entry in &mut entries {
+        entry.select_fields(options);
+    }
+    entries
+}
+
+/// Convert the entries like [`convert`], but only remove the unselected
+/// fields from the `bibtex` source, for [`SRAEntry::select_fields`] later.
+pub fn convert_unselected(bib: &Bibliography, options: &Options) -> Vec<SRAEntry> {
+    let selected: Vec<&Entry> = bib
+        .iter()
+        .filter(|e| {
+            options
+                .keys
+                .as_ref()
+                .is_none_or(|keys| keys.contains(&e.key))
+        })
+        .collect();
+    options.progress.start(selected.len());
+    let sets = sets(bib);
+    let converted = parallel::map(&selected, options.jobs, |e| {
+        let e = SRAEntry::with_sets(e, bib, options, &sets);
+        let keep = options.filter.matches(&e)
+            && (options.keep_incomplete || check::require(&e, &options.require).is_none());
+        options.progress.inc();
+        keep.then_some(e)
+    });
+    let mut entries: Vec<SRAEntry> = converted.into_iter().flatten().collect();
+    let mut slugs = slug::assign(bib);
+    for entry in &mut entries {
+        if let Some(slug) = slugs.remove(&entry.id) {
+            entry.slug = slug;
+        }
+    }
+    sort::sort(&mut entries, options);
+    options.paginate(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use biblatex::Bibliography;
+
+    use indexmap::IndexMap;
+
+    use crate::{convert, Options, SRABib, SRAEntry};
+
+    #[test]
+    fn crossref() {
+        let bib = r#"
+            @inproceedings{foo,
+                author = {Max Müller},
+                title = {Lorem Ipsum et Dolor},
+                month = sep,
+                year = 2005,
+                crossref = {ref},
+            }
+            @proceedings{ref,
+                month = jan,
+                year = 2001,
+                title = {Book Title},
+                category = {baz},
+            }
+        "#;
+        let parsed = Bibliography::parse(bib).unwrap();
+        println!("{parsed:#?}");
+        let sra_bib = SRABib::new(&parsed);
+        println!("{sra_bib:#?}");
+
+        let thesis = &sra_bib.entries["foo"];
+        assert_eq!(thesis.entry_type, "inproceedings");
+        assert_eq!(thesis.authors.len(), 1);
+        assert_eq!(thesis.other["title"], "Lorem Ipsum et Dolor");
+        assert_eq!(thesis.other["year"], "2001");
+        assert_eq!(thesis.other["month"], "January");
+        assert_eq!(thesis.other["category"], "baz");
+    }
+
+    #[test]
+    fn bib_example() {
+        let bib = r#"
+            @proceedings{ASE2023,
+                title       = {Proceedings of the 38th IEEE/ACM International Conference on Automated Software Engineering},
+                year        = 2023,
+                publisher   = {IEEE},
+                address     = {San Francisco, California, USA},
+            }
+            @inproceedings{Smith2023,
+                author      = {John Smith},
+                title       = {Automated Code Generation: Innovations and Challenges},
+                pages       = {15-29},
+                crossref    = {ASE2023},
+            }
+            @inproceedings{Doe2023,
+                author      = {Jane Doe},
+                title       = {Towards a New Era of Software Testing},
+                pages       = {30-45},
+                crossref    = {ASE2023},
+            }
+        "#;
+        let parsed = Bibliography::parse(bib).unwrap();
+        let sra_bib = SRABib::new(&parsed);
+
+        let smith23 = &sra_bib.entries["Smith2023"];
+        assert_eq!(smith23.other["booktitle"], "Proceedings of the 38th IEEE/ACM International Conference on Automated Software Engineering");
+        assert_eq!(smith23.other["address"], "San Francisco, California, USA");
+        assert_eq!(smith23.other["year"], "2023");
+        assert_eq!(smith23.other["publisher"], "IEEE");
+
+        let doe23 = &sra_bib.entries["Doe2023"];
+        assert_eq!(doe23.other["booktitle"], "Proceedings of the 38th IEEE/ACM International Conference on Automated Software Engineering");
+        assert_eq!(doe23.other["address"], "San Francisco, California, USA");
+        assert_eq!(doe23.other["year"], "2023");
+        assert_eq!(doe23.other["publisher"], "IEEE");
+    }
+
+    #[test]
+    fn inherited() {
+        let bib = r#"
+            @inproceedings{foo,
+                author = {Max Müller},
+                title = {Lorem Ipsum et Dolor},
+                doi = {10.1000/xyz},
+                crossref = {ref},
+            }
+            @proceedings{ref,
+                year = 2001,
+                title = {Book Title},
+                publisher = {IEEE},
+                category = {baz},
+            }
+        "#;
+        let parsed = Bibliography::parse(bib).unwrap();
+        let options = Options {
+            inherited: true,
+            ..Default::default()
+        };
+        let sra_bib = SRABib::with_options(&parsed, &options);
+
+        let foo = &sra_bib.entries["foo"];
+        assert_eq!(foo.other["title"], "Lorem Ipsum et Dolor");
+        assert_eq!(foo.other["doi"], "10.1000/xyz");
+        assert!(!foo.other.contains_key("booktitle"));
+        assert!(!foo.other.contains_key("publisher"));
+        assert_eq!(foo.inherited["booktitle"], "Book Title");
+        assert_eq!(foo.inherited["publisher"], "IEEE");
+        assert_eq!(foo.inherited["category"], "baz");
+        assert!(!foo.inherited.contains_key("title"));
+        assert!(sra_bib.entries["ref"].inherited.is_empty());
+    }
+
+    #[test]
+    fn select_fields() {
+        let bib = Bibliography::parse(
+            "@article{a, title={A}, year=2020, doi={10.1/a}, file={a.pdf}, abstract={X}}",
+        )
+        .unwrap();
+        let options = Options {
+            fields: vec!["Title".into(), "year".into(), "file".into()],
+            exclude_fields: vec!["file".into()],
+            ..Default::default()
+        };
+        let entries = convert(&bib, &options);
+        let fields: Vec<&String> = entries[0].other.keys().collect();
+        assert_eq!(fields, ["title", "year"]);
+        assert_eq!(entries[0].date_sort, None);
+        assert!(entries[0].bibtex.is_empty() && entries[0].slug.is_empty());
+
+        let bib = Bibliography::parse(
+            "@article{a, author={Max Müller}, title={A}, file={secret.pdf}, note={N}}",
+        )
+        .unwrap();
+        let options = Options {
+            exclude_fields: vec!["file".into(), "note".into()],
+            ..Default::default()
+        };
+        let a = &convert(&bib, &options)[0];
+        assert_eq!(a.authors.len(), 1);
+        assert!(a.bibtex.contains("title = {A}"));
+        assert!(!a.bibtex.contains("secret") && !a.bibtex.contains("note"));
+
+        let options = Options {
+            fields: vec!["title".into(), "bibtex".into()],
+            ..Default::default()
+        };
+        let a = &convert(&bib, &options)[0];
+        assert!(a.authors.is_empty() && !a.bibtex.contains("author"));
+        let json = serde_json::to_value(a).unwrap();
+        let keys: BTreeSet<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            BTreeSet::from(["authors", "bibtex", "editors", "entry_type", "id", "title"])
+        );
+    }
+
+    #[test]
+    fn redact() {
+        let bib = Bibliography::parse(
+            "@book{p, title={P}, owner={me}}\n@inbook{a, title={A}, File={/home/me/a.pdf}, crossref={p}}",
+        )
+        .unwrap();
+        let options = Options {
+            redact: crate::PRIVATE_FIELDS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            crossref_depth: Some(1),
+            ..Default::default()
+        };
+        let entries = convert(&bib, &options);
+        let a = entries.iter().find(|e| e.id == "a").unwrap();
+        assert!(a.get("file").is_none() && a.get("owner").is_none());
+        assert!(!a.bibtex.contains("home") && !a.bibtex.contains("owner"));
+        assert!(a.bibtex.contains("title = {P}"));
+    }
+
+    #[test]
+    fn paginate() {
+        let bib = Bibliography::parse(
+            "@misc{a} @misc{b} @misc{c} @misc{d}"
+                .replace('}', ", title={T}}")
+                .as_str(),
+        )
+        .unwrap();
+        let keys = |offset, limit| {
+            let options = Options {
+                offset,
+                limit,
+                ..Default::default()
+            };
+            let entries = convert(&bib, &options);
+            entries.into_iter().map(|e| e.id).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(1, Some(2)), ["b", "c"]);
+        assert_eq!(keys(3, None), ["d"]);
+        assert!(keys(5, Some(2)).is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let bib = Bibliography::parse(
+            r#"@article{a, title={Hello {World} -- {\"U}ber}, pages={1--2}, note={A---B}, author={M{\"u}ller, Max}}"#,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&SRABib::new(&bib)).unwrap();
+        let entries: IndexMap<String, SRAEntry> = serde_json::from_str(&json).unwrap();
+        let mut a = entries["a"].clone();
+        let bibtex = a.clone().into_entry().to_biblatex_string();
+        assert!(bibtex.contains("title = {Hello {World} -- {Ü}ber}"));
+        assert!(bibtex.contains("pages = {1--2}"));
+        assert!(bibtex.contains("note = {A---B}"));
+        assert!(bibtex.contains("author = {M{ü}ller, Max}"));
+
+        a.other.insert("title".into(), "Goodbye World".into());
+        let bibtex = a.into_entry().to_biblatex_string();
+        assert!(bibtex.contains("title = {Goodbye World}"));
+        assert!(bibtex.contains("pages = {1--2}"));
+    }
+
+    #[test]
+    fn truncate() {
+        let bib = r#"
+            @article{foo,
+                title = {Lorem Ipsum},
+                abstract = {Äöü dolor sit amet},
+            }
+        "#;
+        let parsed = Bibliography::parse(bib).unwrap();
+        let options = Options {
+            truncate: [("abstract".into(), 5), ("title".into(), 20)].into(),
+            ..Default::default()
+        };
+        let sra_bib = SRABib::with_options(&parsed, &options);
+
+        let foo = &sra_bib.entries["foo"];
+        assert_eq!(foo.other["abstract"], "Äöü d…");
+        assert_eq!(foo.other["title"], "Lorem Ipsum");
+        assert_eq!(foo.truncated, ["abstract"]);
+    }
+
+    #[test]
+    fn doi_url() {
+        let bib = Bibliography::parse(
+            "@article{a, doi={doi:10.1000/182}}\n@article{b, doi={10.1000/183}, url={https://example.org}}",
+        )
+        .unwrap();
+        let options = Options {
+            doi_url: true,
+            ..Default::default()
+        };
+        let sra_bib = SRABib::with_options(&bib, &options);
+        assert_eq!(
+            sra_bib.entries["a"].other["url"],
+            "https://doi.org/10.1000/182"
+        );
+        assert_eq!(sra_bib.entries["a"].derived, ["url"]);
+        assert_eq!(sra_bib.entries["b"].other["url"], "https://example.org");
+        assert!(sra_bib.entries["b"].derived.is_empty());
+    }
+
+    #[test]
+    fn crossref_depth() {
+        let bib = Bibliography::parse(
+            r#"
+            @inbook{a, title={A}, crossref={b}}
+            @book{b, title={B}, xref={c}}
+            @mvbook{c, title={C}, publisher={P}, xref={b}}
+            "#,
+        )
+        .unwrap();
+        let a = &SRABib::new(&bib).entries["a"];
+        assert!(!a.other.contains_key("publisher"));
+        assert!(!a.bibtex.contains("@book"));
+
+        let options = Options {
+            crossref_depth: Some(5),
+            ..Default::default()
+        };
+        let a = &SRABib::with_options(&bib, &options).entries["a"];
+        assert_eq!(a.other["title"], "A");
+        assert_eq!(a.other["publisher"], "P");
+        assert_eq!(a.bibtex.matches("\n\n@").count(), 2);
+    }
+
+    #[test]
+    fn missing() {
+        let bib = Bibliography::parse(
+            "@article{a, title={A}, year=2020}\n@misc{b, title={B}, author={Foo}, year=2020}",
+        )
+        .unwrap();
+        let options = Options {
+            missing: true,
+            ..Default::default()
+        };
+        let entries = SRABib::with_options(&bib, &options).entries;
+        assert_eq!(entries["a"].missing, ["journaltitle", "author"]);
+        assert!(entries["b"].missing.is_empty());
+        assert!(!serde_json::to_string(&entries["b"])
+            .unwrap()
+            .contains("_missing"));
+    }
+
+    #[test]
+    fn sets() {
+        let bib = Bibliography::parse(
+            r#"
+            @set{s, entryset={a,b}}
+            @article{a, title={A}}
+            @article{b, title={B}}
+            @article{c, title={C}}
+            "#,
+        )
+        .unwrap();
+        let entries = SRABib::new(&bib).entries;
+        assert_eq!(entries["a"].sets, ["s"]);
+        assert!(entries["c"].sets.is_empty());
+        assert!(entries["s"].bibtex.starts_with("@set{s,"));
+        assert!(entries["s"].bibtex.contains("@article{a,"));
+        assert!(entries["s"].bibtex.contains("@article{b,"));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn schema() {
+        let schema = serde_json::to_value(crate::schema()).unwrap();
+        let entry = &schema["$defs"]["SRAEntry"];
+        assert_eq!(schema["additionalProperties"]["$ref"], "#/$defs/SRAEntry");
+        assert!(entry["required"]
+            .as_array()
+            .unwrap()
+            .contains(&"entry_type".into()));
+        assert_eq!(entry["properties"]["_missing"]["type"], "array");
+        assert_eq!(entry["additionalProperties"]["type"], "string");
+    }
+}
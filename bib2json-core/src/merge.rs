@@ -0,0 +1,185 @@
+//! Merging of bibliographies whose entries may share keys, like exports of
+//! different collaborators.
+
+use std::fmt;
+
+use biblatex::{Bibliography, ChunksExt, Entry};
+
+/// How to resolve entries with the same key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Strategy {
+    /// Keep the entry of the first input
+    #[default]
+    PreferFirst,
+    /// Keep the entry with the later `date-modified` or `timestamp` field,
+    /// otherwise the one of the later input
+    PreferNewest,
+    /// Fail if the entries differ
+    Error,
+    /// Keep the fields of all entries, conflicting values are chosen
+    Union,
+}
+
+/// Entries with the same key and different values of a field, or different
+/// types if `field` is `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: String,
+    pub field: Option<String>,
+    pub first: String,
+    pub second: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "{}: different `{field}`", self.key)?,
+            None => write!(f, "{}: different entry types", self.key)?,
+        }
+        write!(f, ": `{}` and `{}`", self.first, self.second)
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// The fields that tools like JabRef, BibDesk and Zotero set on changes.
+const MODIFIED: &[&str] = &["date-modified", "timestamp"];
+
+/// When the entry was last modified, `2020.01.31` and `2020-01-31` alike.
+fn modified(entry: &Entry) -> Option<String> {
+    MODIFIED
+        .iter()
+        .find_map(|field| entry.get(field))
+        .map(|value| value.format_verbatim().replace('.', "-"))
+}
+
+/// The conflicts between two entries with the same key.
+fn conflicts(first: &Entry, second: &Entry) -> Vec<Conflict> {
+    let conflict = |field: Option<&str>, a: String, b: String| Conflict {
+        key: first.key.clone(),
+        field: field.map(str::to_owned),
+        first: a,
+        second: b,
+    };
+    let mut conflicts = Vec::new();
+    if first.entry_type != second.entry_type {
+        conflicts.push(conflict(
+            None,
+            first.entry_type.to_string(),
+            second.entry_type.to_string(),
+        ));
+    }
+    for (field, value) in &second.fields {
+        let Some(own) = first.get(field) else {
+            continue;
+        };
+        let (own, value) = (own.format_verbatim(), value.format_verbatim());
+        if own != value {
+            conflicts.push(conflict(Some(field), own, value));
+        }
+    }
+    conflicts
+}
+
+/// Merge the bibliographies, resolving entries with the same key by the
+/// strategy.
+///
+/// With [`Strategy::Union`], `choose` decides each conflict and returns
+/// whether to take the second value. Entries keep the order in which their
+/// keys first appear.
+pub fn merge(
+    bibs: impl IntoIterator<Item = Bibliography>,
+    strategy: Strategy,
+    choose: &mut dyn FnMut(&Conflict) -> bool,
+) -> Result<Bibliography, Conflict> {
+    let mut merged = Bibliography::new();
+    for bib in bibs {
+        for entry in bib.into_iter() {
+            let Some(first) = merged.get_mut(&entry.key) else {
+                merged.insert(entry);
+                continue;
+            };
+            match strategy {
+                Strategy::PreferFirst => {}
+                Strategy::PreferNewest => {
+                    if modified(&entry) >= modified(first) {
+                        *first = entry;
+                    }
+                }
+                Strategy::Error => {
+                    if let Some(conflict) = conflicts(first, &entry).into_iter().next() {
+                        return Err(conflict);
+                    }
+                    // Fields only one of them has
+                    for (field, value) in entry.fields {
+                        first.fields.entry(field).or_insert(value);
+                    }
+                }
+                Strategy::Union => {
+                    for conflict in conflicts(first, &entry) {
+                        if choose(&conflict) {
+                            match &conflict.field {
+                                Some(field) => first.set(field, entry.fields[field].clone()),
+                                None => first.entry_type = entry.entry_type.clone(),
+                            }
+                        }
+                    }
+                    for (field, value) in entry.fields {
+                        first.fields.entry(field).or_insert(value);
+                    }
+                }
+            }
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::{Bibliography, ChunksExt};
+
+    use super::{merge, Strategy};
+
+    #[test]
+    fn strategies() {
+        let bibs = || {
+            [
+                "@article{a, title={A}, timestamp={2021.03.01}}\n@misc{b, title={B}}",
+                "@book{a, title={A2}, timestamp={2020-01-01}, note={N}}\n@misc{c, title={C}}",
+            ]
+            .map(|src| Bibliography::parse(src).unwrap())
+        };
+        let title = |bib: &Bibliography| bib.get("a").unwrap().title().unwrap().format_verbatim();
+
+        let bib = merge(bibs(), Strategy::PreferFirst, &mut |_| true).unwrap();
+        let keys: Vec<&str> = bib.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+        assert_eq!(title(&bib), "A");
+
+        let bib = merge(bibs(), Strategy::PreferNewest, &mut |_| true).unwrap();
+        assert_eq!(title(&bib), "A");
+        assert!(bib.get("a").unwrap().get("note").is_none());
+
+        let error = merge(bibs(), Strategy::Error, &mut |_| true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a: different entry types: `article` and `book`"
+        );
+
+        let mut asked = Vec::new();
+        let bib = merge(bibs(), Strategy::Union, &mut |conflict| {
+            asked.push(conflict.field.clone());
+            conflict.field.as_deref() == Some("title")
+        })
+        .unwrap();
+        assert_eq!(
+            asked,
+            [None, Some("timestamp".into()), Some("title".into())]
+        );
+        let a = bib.get("a").unwrap();
+        assert_eq!(a.entry_type.to_string(), "article");
+        assert_eq!(title(&bib), "A2");
+        assert_eq!(a.get("note").unwrap().format_verbatim(), "N");
+    }
+}
@@ -0,0 +1,248 @@
+//! Mapping of converted entries to MODS records, the Metadata Object
+//! Description Schema of the Library of Congress.
+
+use crate::xml::Xml;
+use crate::{date, SRAEntry, SRAPerson};
+
+/// The MODS namespace.
+pub(crate) const NAMESPACE: &str = "http://www.loc.gov/mods/v3";
+
+/// Maps biblatex entry types to MARC genre terms, other types have no genre.
+const GENRES: &[(&str, &str)] = &[
+    ("article", "periodical"),
+    ("book", "book"),
+    ("mvbook", "book"),
+    ("collection", "book"),
+    ("inbook", "book"),
+    ("incollection", "book"),
+    ("inproceedings", "conference publication"),
+    ("proceedings", "conference publication"),
+    ("phdthesis", "thesis"),
+    ("mastersthesis", "thesis"),
+    ("thesis", "thesis"),
+    ("report", "technical report"),
+    ("techreport", "technical report"),
+    ("patent", "patent"),
+    ("online", "web site"),
+];
+
+/// Entry types that are part of a host, a journal or a book.
+const PARTS: &[&str] = &["article", "inbook", "incollection", "inproceedings"];
+
+/// Personal names with a MARC relator role.
+fn names(xml: &mut Xml, persons: &[SRAPerson], role: &str) {
+    for person in persons {
+        xml.open("name", &[("type", "personal")]);
+        if !person.first_name.is_empty() {
+            xml.element("namePart", &[("type", "given")], &person.first_name);
+        }
+        xml.element("namePart", &[("type", "family")], &person.last_name);
+        xml.open("role", &[]);
+        xml.element(
+            "roleTerm",
+            &[("authority", "marcrelator"), ("type", "text")],
+            role,
+        );
+        xml.close("role");
+        xml.close("name");
+    }
+}
+
+/// The volume, issue and pages of an entry.
+fn part(xml: &mut Xml, entry: &SRAEntry) {
+    let details = [
+        ("volume", entry.get("volume")),
+        ("issue", entry.get("number")),
+    ];
+    let pages = entry.get("pages");
+    if details.iter().all(|(_, v)| v.is_none()) && pages.is_none() {
+        return;
+    }
+    xml.open("part", &[]);
+    for (kind, number) in details {
+        if let Some(number) = number {
+            xml.open("detail", &[("type", kind)]);
+            xml.element("number", &[], number);
+            xml.close("detail");
+        }
+    }
+    if let Some(pages) = pages {
+        let mut pages = pages
+            .split(['-', '–'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty());
+        xml.open("extent", &[("unit", "page")]);
+        if let Some(start) = pages.next() {
+            xml.element("start", &[], start);
+        }
+        if let Some(end) = pages.next() {
+            xml.element("end", &[], end);
+        }
+        xml.close("extent");
+    }
+    xml.close("part");
+}
+
+/// Write the MODS record of an entry.
+pub(crate) fn record(xml: &mut Xml, entry: &SRAEntry) {
+    let entry_type = entry.entry_type.to_lowercase();
+    let is_part = PARTS.contains(&entry_type.as_str());
+    xml.open("mods", &[("ID", &entry.id)]);
+
+    xml.open("titleInfo", &[]);
+    xml.element("title", &[], entry.get("title").unwrap_or_default());
+    if let Some(subtitle) = entry.get("subtitle") {
+        xml.element("subTitle", &[], subtitle);
+    }
+    xml.close("titleInfo");
+    names(xml, &entry.authors, "author");
+    if !is_part {
+        names(xml, &entry.editors, "editor");
+    }
+
+    let resource = match entry_type.as_str() {
+        "software" | "dataset" => "software, multimedia",
+        _ => "text",
+    };
+    xml.element("typeOfResource", &[], resource);
+    if let Some((_, genre)) = GENRES.iter().find(|(t, _)| *t == entry_type) {
+        xml.element("genre", &[("authority", "marcgt")], genre);
+    }
+
+    let publisher = ["publisher", "institution", "school", "organization"]
+        .iter()
+        .find_map(|f| entry.get(f));
+    let place = entry.get("location").or_else(|| entry.get("address"));
+    let issued = entry.date_sort.as_deref().map(date::iso);
+    let edition = entry.get("edition");
+    if publisher.is_some() || place.is_some() || issued.is_some() || edition.is_some() {
+        xml.open("originInfo", &[]);
+        if let Some(publisher) = publisher {
+            xml.element("publisher", &[], publisher);
+        }
+        if let Some(place) = place {
+            xml.open("place", &[]);
+            xml.element("placeTerm", &[("type", "text")], place);
+            xml.close("place");
+        }
+        if let Some(issued) = issued {
+            xml.element("dateIssued", &[("encoding", "w3cdtf")], issued);
+        }
+        if let Some(edition) = edition {
+            xml.element("edition", &[], edition);
+        }
+        xml.close("originInfo");
+    }
+
+    let host = ["journaltitle", "journal", "booktitle"]
+        .iter()
+        .find_map(|f| entry.get(f));
+    if is_part {
+        xml.open("relatedItem", &[("type", "host")]);
+        xml.open("titleInfo", &[]);
+        xml.element("title", &[], host.unwrap_or_default());
+        xml.close("titleInfo");
+        names(xml, &entry.editors, "editor");
+        part(xml, entry);
+        xml.close("relatedItem");
+    }
+    if let Some(series) = entry.get("series") {
+        xml.open("relatedItem", &[("type", "series")]);
+        xml.open("titleInfo", &[]);
+        xml.element("title", &[], series);
+        xml.close("titleInfo");
+        xml.close("relatedItem");
+    }
+    if !is_part {
+        part(xml, entry);
+    }
+
+    if let Some(abstract_) = entry.get("abstract") {
+        xml.element("abstract", &[], abstract_);
+    }
+    if let Some(note) = entry.get("note") {
+        xml.element("note", &[], note);
+    }
+    let keywords = entry.keywords();
+    if !keywords.is_empty() {
+        xml.open("subject", &[]);
+        for keyword in keywords {
+            xml.element("topic", &[], keyword);
+        }
+        xml.close("subject");
+    }
+    if let Some(language) = entry.get("langid").or_else(|| entry.get("language")) {
+        xml.open("language", &[]);
+        xml.element("languageTerm", &[("type", "text")], language);
+        xml.close("language");
+    }
+    for (kind, field) in [("doi", "doi"), ("isbn", "isbn"), ("issn", "issn")] {
+        if let Some(id) = entry.get(field) {
+            xml.element("identifier", &[("type", kind)], id);
+        }
+    }
+    xml.element("identifier", &[("type", "citekey")], &entry.id);
+    if let Some(url) = entry.get("url") {
+        xml.open("location", &[]);
+        xml.element("url", &[], url);
+        xml.close("location");
+    }
+    xml.close("mods");
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::record;
+    use crate::xml::Xml;
+    use crate::SRAEntry;
+
+    #[test]
+    fn article() {
+        let bib = Bibliography::parse(
+            "@article{foo, author={Doe, Jane}, title={A & B}, journal={Dolor}, \
+             year=2020, volume=3, pages={10--20}, doi={10.1000/xyz}}",
+        )
+        .unwrap();
+        let mut xml = Xml::new();
+        record(&mut xml, &SRAEntry::from(bib.get("foo").unwrap(), &bib));
+        let mods = xml.finish();
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<mods ID="foo">
+  <titleInfo>
+    <title>A &amp; B</title>
+  </titleInfo>
+  <name type="personal">
+    <namePart type="given">Jane</namePart>
+    <namePart type="family">Doe</namePart>
+    <role>
+      <roleTerm authority="marcrelator" type="text">author</roleTerm>
+    </role>
+  </name>
+  <typeOfResource>text</typeOfResource>
+  <genre authority="marcgt">periodical</genre>
+  <originInfo>
+    <dateIssued encoding="w3cdtf">2020</dateIssued>
+  </originInfo>
+  <relatedItem type="host">
+    <titleInfo>
+      <title>Dolor</title>
+    </titleInfo>
+    <part>
+      <detail type="volume">
+        <number>3</number>
+      </detail>
+      <extent unit="page">
+        <start>10</start>
+        <end>20</end>
+      </extent>
+    </part>
+  </relatedItem>
+  <identifier type="doi">10.1000/xyz</identifier>
+  <identifier type="citekey">foo</identifier>
+</mods>
+"#;
+        assert_eq!(mods, expected);
+    }
+}
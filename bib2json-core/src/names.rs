@@ -0,0 +1,143 @@
+//! Templates for preformatted person names.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::SRAPerson;
+
+/// A part of a name template.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    /// `{first}`
+    First,
+    /// `{last}`
+    Last,
+    /// `{f}`: initials of the first names, e.g. `J. P` for `John Paul`
+    FirstInitials,
+    /// `{l}`: initials of the last name
+    LastInitials,
+}
+
+/// A name template like `{last}, {f}.`, see [`Part`] for the placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameFormat(Vec<Part>);
+
+/// An invalid name template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameFormatError(String);
+
+impl fmt::Display for NameFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NameFormatError {}
+
+impl FromStr for NameFormat {
+    type Err = NameFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| NameFormatError(format!("unclosed `{{` in name format `{s}`")))?;
+            parts.push(match &rest[start + 1..start + end] {
+                "first" => Part::First,
+                "last" => Part::Last,
+                "f" => Part::FirstInitials,
+                "l" => Part::LastInitials,
+                other => {
+                    return Err(NameFormatError(format!(
+                        "unknown placeholder `{{{other}}}`, expected one of \
+                         `{{first}}`, `{{last}}`, `{{f}}` or `{{l}}`"
+                    )))
+                }
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+        Ok(NameFormat(parts))
+    }
+}
+
+/// Initials of the (whitespace or hyphen separated) names, `Jean-Paul Claude` becomes `J.-P. C`.
+fn initials(names: &str) -> String {
+    names
+        .split_whitespace()
+        .map(|name| {
+            name.split('-')
+                .filter_map(|part| part.chars().next())
+                .map(String::from)
+                .collect::<Vec<_>>()
+                .join(".-")
+        })
+        .collect::<Vec<_>>()
+        .join(". ")
+}
+
+impl NameFormat {
+    /// Format the name of a person.
+    pub fn format(&self, person: &SRAPerson) -> String {
+        let mut name = String::new();
+        for part in &self.0 {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::First => name.push_str(&person.first_name),
+                Part::Last => name.push_str(&person.last_name),
+                Part::FirstInitials => name.push_str(&initials(&person.first_name)),
+                Part::LastInitials => name.push_str(&initials(&person.last_name)),
+            }
+        }
+        name
+    }
+
+    /// Format and join the names, `A, B and C`.
+    pub fn join(&self, persons: &[SRAPerson]) -> String {
+        let names: Vec<String> = persons.iter().map(|p| self.format(p)).collect();
+        match names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} and {last}", rest.join(", ")),
+            _ => names.concat(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NameFormat;
+    use crate::SRAPerson;
+
+    fn person(first_name: &str, last_name: &str) -> SRAPerson {
+        SRAPerson {
+            first_name: first_name.into(),
+            last_name: last_name.into(),
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn templates() {
+        let format: NameFormat = "{last}, {f}.".parse().unwrap();
+        assert_eq!(format.format(&person("John Paul", "Smith")), "Smith, J. P.");
+        assert_eq!(
+            format.format(&person("Jean-Luc", "Picard")),
+            "Picard, J.-L."
+        );
+
+        let format: NameFormat = "{first} {last}".parse().unwrap();
+        let persons = [person("A", "B"), person("C", "D"), person("E", "F")];
+        assert_eq!(format.join(&persons), "A B, C D and E F");
+        assert_eq!(format.join(&persons[..1]), "A B");
+
+        assert!("{given}".parse::<NameFormat>().is_err());
+        assert!("{last".parse::<NameFormat>().is_err());
+    }
+}
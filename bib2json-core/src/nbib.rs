@@ -0,0 +1,241 @@
+//! Reading of MEDLINE/PubMed `.nbib` files.
+
+use std::collections::BTreeMap;
+
+use biblatex::{Bibliography, Entry, EntryType};
+
+use crate::input::chunks;
+use crate::ris::{generate_key, ParseError};
+
+/// Maps MEDLINE tags to biblatex fields, tags with several values are joined.
+const FIELDS: &[(&str, &str)] = &[
+    ("TI", "title"),
+    ("BTI", "title"),
+    ("JT", "journal"),
+    ("TA", "journal"),
+    ("VI", "volume"),
+    ("IP", "number"),
+    ("AB", "abstract"),
+    ("PMID", "pmid"),
+    ("PB", "publisher"),
+    ("MH", "keywords"),
+    ("OT", "keywords"),
+    ("LA", "language"),
+];
+
+/// Splits a MEDLINE line into its tag and value.
+fn tag(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start_matches('\u{feff}');
+    let (tag, value) = line.split_at_checked(4)?;
+    let value = value.strip_prefix("- ")?;
+    let tag = tag.trim_end();
+    (!tag.is_empty() && tag.chars().all(|c| c.is_ascii_uppercase())).then(|| (tag, value.trim()))
+}
+
+/// Parse the records of a `.nbib` file into a bibliography.
+///
+/// Records are separated by empty lines and values continue on indented lines.
+pub fn parse(src: &str) -> Result<Bibliography, ParseError> {
+    let mut bib = Bibliography::new();
+    let mut record: Vec<(&str, String)> = Vec::new();
+
+    for (i, line) in src.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !record.is_empty() {
+                let entry = entry(std::mem::take(&mut record), &bib);
+                bib.insert(entry);
+            }
+            continue;
+        }
+        match (tag(line), record.last_mut()) {
+            (Some((tag, value)), _) => record.push((tag, value.to_owned())),
+            (None, Some((_, last))) if line.starts_with(' ') => {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+            _ => {
+                return Err(ParseError {
+                    line: i + 1,
+                    message: "expected a `XXXX- value` tag line".into(),
+                })
+            }
+        }
+    }
+    if !record.is_empty() {
+        let entry = entry(record, &bib);
+        bib.insert(entry);
+    }
+    Ok(bib)
+}
+
+/// Convert one MEDLINE record into an entry.
+fn entry(record: Vec<(&str, String)>, bib: &Bibliography) -> Entry {
+    let all = |tags: &[&str]| {
+        record
+            .iter()
+            .filter(|(tag, value)| tags.contains(tag) && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+    };
+    let first = |tags: &[&str]| all(tags).first().copied();
+
+    let types = all(&["PT"]);
+    let entry_type = if types.contains(&"Book") {
+        "book"
+    } else if types.contains(&"Book Chapter")
+        || first(&["BTI"]).is_some() && first(&["TI"]).is_some()
+    {
+        "incollection"
+    } else {
+        "article"
+    };
+
+    let mut fields: BTreeMap<&str, String> = BTreeMap::new();
+    for (tag, field) in FIELDS {
+        if fields.contains_key(field) {
+            continue;
+        }
+        let values = if *field == "keywords" {
+            all(&["MH", "OT"])
+        } else {
+            all(&[tag])
+        };
+        if !values.is_empty() {
+            let sep = if *field == "keywords" { ", " } else { " " };
+            fields.insert(field, values.join(sep));
+        }
+    }
+    if entry_type == "incollection" {
+        if let Some(book) = first(&["BTI"]) {
+            fields.insert("title", first(&["TI"]).unwrap_or(book).to_owned());
+            fields.insert("booktitle", book.to_owned());
+        }
+    }
+
+    // Full author names are `Last, First`, the short ones `Last FM`
+    let authors = all(&["FAU"]);
+    let authors: Vec<String> = if authors.is_empty() {
+        all(&["AU"])
+            .into_iter()
+            .map(|au| match au.rsplit_once(' ') {
+                Some((last, initials)) => format!("{last}, {initials}"),
+                None => au.to_owned(),
+            })
+            .collect()
+    } else {
+        authors.into_iter().map(str::to_owned).collect()
+    };
+    if !authors.is_empty() {
+        fields.insert("author", authors.join(" and "));
+    }
+    let editors = all(&["FED", "ED"]);
+    if !editors.is_empty() {
+        fields.insert("editor", editors.join(" and "));
+    }
+    if let Some(date) = first(&["DP"]) {
+        let mut parts = date.split_whitespace();
+        let year = parts.next().unwrap_or_default();
+        if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) {
+            fields.insert("year", year.to_owned());
+        }
+        if let Some(month) = parts.next().filter(|m| m.chars().all(char::is_alphabetic)) {
+            fields.insert("month", month.to_lowercase());
+        }
+    }
+    if let Some(pages) = first(&["PG"]) {
+        fields.insert("pages", expand_pages(pages));
+    }
+    let doi = all(&["LID", "AID"])
+        .into_iter()
+        .find_map(|id| id.strip_suffix("[doi]"));
+    if let Some(doi) = doi {
+        fields.insert("doi", doi.trim().to_owned());
+    }
+    if let Some(issn) = first(&["IS"]) {
+        let issn = issn.split_whitespace().next().unwrap_or(issn);
+        let field = if entry_type == "article" {
+            "issn"
+        } else {
+            "isbn"
+        };
+        fields.insert(field, issn.to_owned());
+    }
+
+    let key = generate_key(&fields, bib);
+    let mut entry = Entry::new(key, EntryType::new(entry_type));
+    for (field, value) in fields {
+        entry.set(field, chunks(&value));
+    }
+    entry
+}
+
+/// MEDLINE abbreviates the last page, `123-9` becomes `123--129`.
+fn expand_pages(pages: &str) -> String {
+    let Some((start, end)) = pages.split_once('-') else {
+        return pages.to_owned();
+    };
+    let prefix = start.get(..start.len().saturating_sub(end.len()));
+    let end = match prefix {
+        Some(prefix) if !prefix.is_empty() && end.chars().all(|c| c.is_ascii_digit()) => {
+            format!("{prefix}{end}")
+        }
+        _ => end.to_owned(),
+    };
+    format!("{start}--{end}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expand_pages, parse};
+
+    #[test]
+    fn pages() {
+        assert_eq!(expand_pages("123-9"), "123--129");
+        assert_eq!(expand_pages("e12-e19"), "e12--e19");
+        assert_eq!(expand_pages("ä123-9"), "ä123--ä129");
+        assert_eq!(expand_pages("ä-1"), "ä--1");
+        assert_eq!(expand_pages("12"), "12");
+    }
+
+    #[test]
+    fn records() {
+        let nbib = "PMID- 12345678\n\
+                    TI  - Lorem ipsum dolor sit amet in\n      \
+                    patients.\n\
+                    PG  - 123-9\n\
+                    DP  - 2021 Mar 15\n\
+                    FAU - Smith, John\n\
+                    AU  - Smith J\n\
+                    FAU - Doe, Jane A\n\
+                    AU  - Doe JA\n\
+                    PT  - Journal Article\n\
+                    TA  - J Dolor\n\
+                    JT  - Journal of Dolor\n\
+                    LID - 10.1000/xyz [doi]\n\
+                    \n\
+                    PMID- 87654321\n\
+                    TI  - Second.\n\
+                    AU  - Foo B\n\
+                    DP  - 2020\n";
+        let bib = parse(nbib).unwrap();
+        assert_eq!(bib.len(), 2);
+
+        let smith = bib.get("Smith2021").unwrap();
+        assert_eq!(smith.entry_type.to_string(), "article");
+        assert_eq!(
+            smith.get_as::<String>("title").unwrap(),
+            "Lorem ipsum dolor sit amet in patients."
+        );
+        assert_eq!(smith.author().unwrap()[1].given_name, "Jane A");
+        assert_eq!(
+            smith.get_as::<String>("journal").unwrap(),
+            "Journal of Dolor"
+        );
+        assert_eq!(smith.get_as::<String>("pages").unwrap(), "123--129");
+        assert_eq!(smith.get_as::<String>("doi").unwrap(), "10.1000/xyz");
+        assert_eq!(smith.get_as::<String>("pmid").unwrap(), "12345678");
+
+        let foo = bib.get("Foo2020").unwrap();
+        assert_eq!(foo.author().unwrap()[0].given_name, "B");
+    }
+}
@@ -0,0 +1,162 @@
+//! Markdown literature notes, one per entry.
+
+use crate::names::NameFormat;
+use crate::{output, SRAEntry};
+
+/// The default note: all fields as front matter, the citation and the BibTeX source.
+pub const DEFAULT_TEMPLATE: &str = "---
+{fields}
+---
+
+# {title}
+
+{citation}
+
+```bibtex
+{bibtex}
+```
+";
+
+/// Render a note from a template.
+///
+/// The placeholders are `{key}`, `{type}`, `{authors}`, `{citation}`, `{bibtex}`,
+/// `{fields}` (YAML front matter lines) and the names of the entry fields,
+/// missing fields are empty.
+pub fn render(template: &str, entry: &SRAEntry) -> String {
+    let mut note = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        note.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let name = rest[1..]
+            .find('}')
+            .map(|end| &rest[1..end + 1])
+            .filter(|name| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            });
+        match name {
+            Some(name) => {
+                note.push_str(&placeholder(name, entry));
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                note.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    note.push_str(rest);
+    note
+}
+
+fn placeholder(name: &str, entry: &SRAEntry) -> String {
+    match name {
+        "key" => entry.id.clone(),
+        "type" => entry.entry_type.clone(),
+        "authors" => authors(entry),
+        "citation" => citation(entry),
+        "bibtex" => entry.bibtex.clone(),
+        "fields" => fields(entry),
+        field => entry.get(field).unwrap_or_default().to_owned(),
+    }
+}
+
+fn authors(entry: &SRAEntry) -> String {
+    let format: NameFormat = "{first} {last}".parse().unwrap();
+    format.join(&entry.authors)
+}
+
+/// A short citation: `Smith, J. and Doe, J. (2020). Title. Venue.`
+pub fn citation(entry: &SRAEntry) -> String {
+    let format: NameFormat = "{last}, {f}.".parse().unwrap();
+    let mut citation = format.join(&entry.authors);
+    if let Some(year) = entry.year() {
+        citation.push_str(&format!(" ({year})"));
+    }
+    if !citation.is_empty() {
+        citation.push_str(". ");
+    }
+    for field in ["title", "journal", "journaltitle", "booktitle", "publisher"] {
+        if let Some(value) = entry.get(field) {
+            citation.push_str(value.trim_end_matches('.'));
+            citation.push_str(". ");
+        }
+    }
+    citation.trim_end().to_owned()
+}
+
+/// The key, type, authors and fields as YAML, with the values quoted.
+fn fields(entry: &SRAEntry) -> String {
+    let quote = |value: &str| serde_json::to_string(value).unwrap();
+    let mut lines = vec![
+        format!("key: {}", quote(&entry.id)),
+        format!("type: {}", quote(&entry.entry_type)),
+    ];
+    for (name, persons) in [("authors", &entry.authors), ("editors", &entry.editors)] {
+        if !persons.is_empty() {
+            let names: Vec<String> = persons
+                .iter()
+                .map(|p| quote(format!("{} {}", p.first_name, p.last_name).trim()))
+                .collect();
+            lines.push(format!("{name}: [{}]", names.join(", ")));
+        }
+    }
+    let mut other: Vec<_> = entry.other.iter().chain(&entry.inherited).collect();
+    other.sort_by_key(|(name, _)| *name);
+    other.dedup_by_key(|(name, _)| *name);
+    for (name, value) in other {
+        if name != "author" && name != "editor" {
+            lines.push(format!("{name}: {}", quote(value)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// A file name for the note of the entry, see [`output::file_name`].
+pub fn file_name(entry: &SRAEntry) -> String {
+    output::file_name(entry, "md")
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{citation, file_name, render, DEFAULT_TEMPLATE};
+    use crate::SRABib;
+
+    #[test]
+    fn note() {
+        let bib = Bibliography::parse(
+            "@article{doe:2020, author={Doe, Jane}, title={On Notes}, journal={J. Notes}, year={2020}}",
+        )
+        .unwrap();
+        let entry = &SRABib::new(&bib).entries["doe:2020"];
+        assert_eq!(file_name(entry), "doe_2020.md");
+        let note = render(DEFAULT_TEMPLATE, entry);
+        assert!(note.starts_with(
+            "---\nkey: \"doe:2020\"\ntype: \"article\"\nauthors: [\"Jane Doe\"]\njournal: \"J. Notes\"\n"
+        ));
+        assert!(note.contains(
+            "\n# On Notes\n\nDoe, J. (2020). On Notes. J. Notes.\n\n```bibtex\n@article{doe:2020,"
+        ));
+        assert_eq!(render("{key} {missing} {x y}", entry), "doe:2020  {x y}");
+    }
+
+    #[test]
+    fn citations() {
+        let bib = Bibliography::parse(
+            "@book{c, author={Caesar, Gaius Iulius}, title={Gallic War}, date={-0044-03-15}}
+             @misc{d, author={Doe, Jane}, title={Dated}, date={2020-05}}",
+        )
+        .unwrap();
+        let entries = SRABib::new(&bib).entries;
+        assert_eq!(
+            citation(&entries["c"]),
+            "Caesar, G. I. (-0044). Gallic War."
+        );
+        assert_eq!(citation(&entries["d"]), "Doe, J. (2020). Dated.");
+    }
+}
@@ -0,0 +1,1172 @@
+//! Output formats and the registry of built-in ones.
+//!
+//! Custom formats implement [`OutputFormat`] and are passed to [`write`].
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use biblatex::Bibliography;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::input::extension;
+use crate::xml::Xml;
+use crate::{convert, csl, datacite, date, dublincore, mods, ris, Options, SRAEntry, SRAPerson};
+
+/// A serializer for converted entries.
+///
+/// The entries are visited in the order of [`Options::sort_by`], not of the
+/// bibliography, afterwards `finalize` is called exactly once. Formats that need the whole
+/// bibliography collect the entries and write them in `finalize`.
+pub trait OutputFormat {
+    /// Visit the next entry.
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Write everything remaining after the last entry.
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Write a single entry on its own, by default as a bibliography with one entry.
+    fn single(mut self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.entry(entry, writer)?;
+        self.finalize(writer)
+    }
+}
+
+/// A registered output format.
+#[derive(Debug)]
+pub struct Format {
+    /// Name of the format, as used on the command line.
+    pub name: &'static str,
+    /// File extensions that select the format.
+    pub extensions: &'static [&'static str],
+    /// Extension of the files written in this format.
+    pub extension: &'static str,
+    /// Create a new serializer.
+    pub new: fn(&Options) -> Box<dyn OutputFormat>,
+}
+
+/// The built-in output formats, the first one is the default.
+pub const FORMATS: &[Format] = &[
+    Format {
+        name: "json",
+        extensions: &["json"],
+        extension: "json",
+        new: |options| Box::new(Json::new(options)),
+    },
+    Format {
+        name: "ndjson",
+        extensions: &["ndjson", "jsonl"],
+        extension: "ndjson",
+        new: |options| Box::new(Ndjson(options.layout)),
+    },
+    Format {
+        name: "bibtex",
+        extensions: &["bib", "bibtex"],
+        extension: "bib",
+        new: |_| Box::<Bibtex>::default(),
+    },
+    Format {
+        name: "csl-json",
+        extensions: &[],
+        extension: "json",
+        new: |options| {
+            Box::new(CslJson {
+                indent: options.indent,
+                items: Vec::new(),
+            })
+        },
+    },
+    Format {
+        name: "ris",
+        extensions: &["ris"],
+        extension: "ris",
+        new: |_| Box::new(Ris),
+    },
+    Format {
+        name: "csv",
+        extensions: &["csv"],
+        extension: "csv",
+        new: |options| Box::new(Table::new(',', options)),
+    },
+    Format {
+        name: "tsv",
+        extensions: &["tsv"],
+        extension: "tsv",
+        new: |options| Box::new(Table::new('\t', options)),
+    },
+    Format {
+        name: "mods",
+        extensions: &["mods"],
+        extension: "mods",
+        new: |_| Box::new(Mods::new()),
+    },
+    Format {
+        name: "dublincore",
+        extensions: &[],
+        extension: "xml",
+        new: |_| Box::new(DublinCore::new()),
+    },
+    Format {
+        name: "markdown",
+        extensions: &["md", "markdown"],
+        extension: "md",
+        new: |options| Box::new(Markdown::new(options)),
+    },
+    Format {
+        name: "datacite",
+        extensions: &[],
+        extension: "xml",
+        new: |_| Box::new(DataCite::new()),
+    },
+    Format {
+        name: "keys",
+        extensions: &[],
+        extension: "txt",
+        new: |_| Box::new(Keys),
+    },
+    Format {
+        name: "nocite",
+        extensions: &[],
+        extension: "txt",
+        new: |_| Box::<Nocite>::default(),
+    },
+    #[cfg(feature = "yaml")]
+    Format {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        extension: "yaml",
+        new: |options| Box::new(Yaml(Json::new(options))),
+    },
+    #[cfg(feature = "yaml")]
+    Format {
+        name: "cff",
+        extensions: &["cff"],
+        extension: "cff",
+        new: |_| Box::<Cff>::default(),
+    },
+    #[cfg(feature = "toml")]
+    Format {
+        name: "toml",
+        extensions: &["toml"],
+        extension: "toml",
+        new: |options| Box::new(Toml(Json::new(options))),
+    },
+    #[cfg(feature = "msgpack")]
+    Format {
+        name: "msgpack",
+        extensions: &["msgpack", "mpk"],
+        extension: "msgpack",
+        new: |options| Box::new(Msgpack(Json::new(options))),
+    },
+    #[cfg(feature = "cbor")]
+    Format {
+        name: "cbor",
+        extensions: &["cbor"],
+        extension: "cbor",
+        new: |options| Box::new(Cbor(Json::new(options))),
+    },
+];
+
+impl Format {
+    /// Look up a built-in format by name.
+    pub fn by_name(name: &str) -> Option<&'static Format> {
+        FORMATS.iter().find(|f| f.name == name)
+    }
+
+    /// The extension of files in this format.
+    pub fn extension(&self) -> &'static str {
+        self.extension
+    }
+
+    /// Infer a built-in format from the file extension.
+    pub fn from_path(path: &Path) -> Option<&'static Format> {
+        let extension = extension(path)?;
+        FORMATS.iter().find(|f| f.extensions.contains(&&*extension))
+    }
+}
+
+/// Convert all entries of the bibliography and write them in the given format.
+pub fn write(
+    bib: &Bibliography,
+    options: &Options,
+    format: Box<dyn OutputFormat>,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    write_entries(convert(bib, options), format, writer)
+}
+
+/// Write converted entries in the given format.
+pub fn write_entries(
+    entries: impl IntoIterator<Item = SRAEntry>,
+    mut format: Box<dyn OutputFormat>,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    for entry in entries {
+        format.entry(entry, writer)?;
+    }
+    format.finalize(writer)
+}
+
+/// A file name for an entry with the extension, with path separators and
+/// other unsafe characters in the key replaced.
+pub fn file_name(entry: &SRAEntry, extension: &str) -> String {
+    format!("{}.{extension}", file_stem(&entry.id))
+}
+
+/// A name with path separators and other unsafe characters replaced.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect()
+}
+
+/// Write each entry into its own file in the directory, named after its key
+/// with the extension of the format. Files whose content would not
+/// change are left untouched, files of entries that are gone since the
+/// last run are removed. Returns the number of written files.
+pub fn write_split(
+    entries: impl IntoIterator<Item = SRAEntry>,
+    format: &Format,
+    options: &Options,
+    dir: &Path,
+) -> io::Result<usize> {
+    let files = entries
+        .into_iter()
+        .map(|entry| {
+            let name = file_name(&entry, format.extension());
+            let key = entry.id.clone();
+            let mut content = Vec::new();
+            (format.new)(options).single(entry, &mut content)?;
+            Ok((name, key, content))
+        })
+        .collect::<io::Result<_>>()?;
+    write_files(dir, ".bib2json-manifest", files)
+}
+
+/// Write the files, of a name, the key or group they are written from and
+/// their content, into the directory. Fails before writing if two share a
+/// name. The names are recorded in the manifest, and the files of the
+/// previous manifest that were not written again are removed, other files
+/// in the directory are left alone.
+fn write_files(
+    dir: &Path,
+    manifest: &str,
+    files: Vec<(String, String, Vec<u8>)>,
+) -> io::Result<usize> {
+    let mut sources = BTreeMap::new();
+    for (name, source, _) in &files {
+        if let Some(other) = sources.insert(name.as_str(), source.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "`{other}` and `{source}` would both be written to {}",
+                    dir.join(name).display()
+                ),
+            ));
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    let mut written = 0;
+    for (name, _, content) in &files {
+        let path = dir.join(name);
+        if std::fs::read(&path).ok().as_ref() != Some(content) {
+            std::fs::write(&path, content)?;
+            written += 1;
+        }
+    }
+    let manifest = dir.join(manifest);
+    let previous = std::fs::read_to_string(&manifest).unwrap_or_default();
+    let stale = previous.lines().filter(|name| {
+        // Only plain file names, a changed manifest must not reach outside
+        Path::new(name).file_name().is_some_and(|f| f == *name) && !sources.contains_key(name)
+    });
+    for name in stale {
+        match std::fs::remove_file(dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    let names: String = sources.keys().map(|name| format!("{name}\n")).collect();
+    std::fs::write(manifest, names)?;
+    Ok(written)
+}
+
+/// Property to split the output into several files by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SplitBy {
+    /// A file per year, like `2023.json`, and `unknown.json` for entries without a date
+    Year,
+    /// A file per entry type, like `article.json`
+    Type,
+}
+
+impl SplitBy {
+    /// The file stem of the group of an entry.
+    fn group(self, entry: &SRAEntry) -> String {
+        match self {
+            SplitBy::Year => entry.year().unwrap_or("unknown").to_owned(),
+            SplitBy::Type => entry.entry_type.to_lowercase(),
+        }
+    }
+}
+
+/// Write the entries into a file per group in the directory, with the
+/// extension of the format. Files whose content would not change are left
+/// untouched, files of groups that are gone since the last run are removed.
+/// Returns the number of written files.
+pub fn write_grouped(
+    entries: impl IntoIterator<Item = SRAEntry>,
+    by: SplitBy,
+    format: &Format,
+    options: &Options,
+    dir: &Path,
+) -> io::Result<usize> {
+    let mut groups: BTreeMap<String, Vec<SRAEntry>> = BTreeMap::new();
+    for entry in entries {
+        groups.entry(by.group(&entry)).or_default().push(entry);
+    }
+    let files = groups
+        .into_iter()
+        .map(|(group, entries)| {
+            let name = format!("{}.{}", file_stem(&group), format.extension());
+            let mut content = Vec::new();
+            write_entries(entries, (format.new)(options), &mut content)?;
+            Ok((name, group, content))
+        })
+        .collect::<io::Result<_>>()?;
+    let manifest = match by {
+        SplitBy::Year => ".bib2json-manifest-year",
+        SplitBy::Type => ".bib2json-manifest-type",
+    };
+    write_files(dir, manifest, files)
+}
+
+/// Structure of the entries in the JSON and YAML documents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Layout {
+    /// Persons as objects, all fields as strings
+    #[default]
+    Default,
+    /// Like the Python `bibtexparser` library: `ENTRYTYPE`, `ID` and the raw fields
+    Bibtexparser,
+}
+
+/// An entry in one of the layouts.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum LaidOut {
+    Default(Box<SRAEntry>),
+    Bibtexparser(BTreeMap<String, String>),
+}
+
+impl Layout {
+    fn apply(self, entry: SRAEntry) -> LaidOut {
+        match self {
+            Layout::Default => LaidOut::Default(Box::new(entry)),
+            Layout::Bibtexparser => {
+                let mut fields = entry.inherited;
+                fields.extend(entry.other);
+                fields.insert("ENTRYTYPE".into(), entry.entry_type);
+                fields.insert("ID".into(), entry.id);
+                LaidOut::Bibtexparser(fields)
+            }
+        }
+    }
+}
+
+/// Write a value as JSON, indented by this many spaces per level if set.
+fn to_json(
+    writer: &mut dyn Write,
+    value: &impl Serialize,
+    indent: Option<usize>,
+) -> io::Result<()> {
+    match indent {
+        Some(indent) => {
+            let indent = " ".repeat(indent);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut serializer = serde_json::Serializer::with_formatter(writer, formatter);
+            Ok(value.serialize(&mut serializer)?)
+        }
+        None => Ok(serde_json::to_writer(writer, value)?),
+    }
+}
+
+/// The JSON document, keyed by citation key.
+struct Json {
+    layout: Layout,
+    indent: Option<usize>,
+    entries: IndexMap<String, LaidOut>,
+}
+
+impl Json {
+    fn new(options: &Options) -> Self {
+        Json {
+            layout: options.layout,
+            indent: options.indent,
+            entries: IndexMap::new(),
+        }
+    }
+}
+
+impl OutputFormat for Json {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        self.entries
+            .insert(entry.id.clone(), self.layout.apply(entry));
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        to_json(writer, &self.entries, self.indent)
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        to_json(writer, &self.layout.apply(entry), self.indent)
+    }
+}
+
+/// One JSON object per line and entry, streamed.
+struct Ndjson(Layout);
+
+impl OutputFormat for Ndjson {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer(&mut *writer, &self.0.apply(entry))?;
+        writeln!(writer)
+    }
+
+    fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The biblatex source of the entries, separated by empty lines.
+#[derive(Default)]
+struct Bibtex {
+    started: bool,
+}
+
+impl OutputFormat for Bibtex {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        if self.started {
+            writeln!(writer)?;
+        }
+        self.started = true;
+        writeln!(writer, "{}", entry.bibtex)
+    }
+
+    fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An array of CSL-JSON items, see [`csl::item`].
+struct CslJson {
+    indent: Option<usize>,
+    items: Vec<serde_json::Value>,
+}
+
+impl OutputFormat for CslJson {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        self.items.push(csl::item(&entry));
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        to_json(writer, &self.items, self.indent)
+    }
+}
+
+/// RIS records, for reference managers.
+struct Ris;
+
+impl OutputFormat for Ris {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        ris::write(&entry, writer)
+    }
+
+    fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Columns of tabular output without [`Options::columns`].
+const COLUMNS: &[&str] = &["id", "entry_type", "year", "title", "authors"];
+
+/// A CSV or TSV table with a header and a row per entry.
+struct Table {
+    delimiter: char,
+    columns: Vec<String>,
+    person_separator: String,
+    started: bool,
+}
+
+impl Table {
+    fn new(delimiter: char, options: &Options) -> Self {
+        let columns = if options.columns.is_empty() {
+            COLUMNS.iter().map(|c| c.to_string()).collect()
+        } else {
+            options.columns.clone()
+        };
+        Table {
+            delimiter,
+            columns,
+            person_separator: options.person_separator.as_deref().unwrap_or("; ").into(),
+            started: false,
+        }
+    }
+
+    /// Quote a CSV cell if needed, TSV cells cannot contain tabs or line breaks.
+    fn cell(&self, value: &str) -> String {
+        if self.delimiter == '\t' {
+            value.replace(['\t', '\r', '\n'], " ")
+        } else if value.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_owned()
+        }
+    }
+
+    fn row(&self, cells: &[String], writer: &mut dyn Write) -> io::Result<()> {
+        let cells: Vec<String> = cells.iter().map(|c| self.cell(c)).collect();
+        writeln!(writer, "{}", cells.join(&self.delimiter.to_string()))
+    }
+
+    fn write_header(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.row(&self.columns, writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputFormat for Table {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.write_header(writer)?;
+        let persons = |persons: &[SRAPerson]| {
+            persons
+                .iter()
+                .map(|p| p.display_name.clone().unwrap_or_else(|| p.to_bibtex()))
+                .collect::<Vec<_>>()
+                .join(&self.person_separator)
+        };
+        let cells: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| match column.as_str() {
+                "id" => entry.id.clone(),
+                "entry_type" => entry.entry_type.clone(),
+                "authors" => persons(&entry.authors),
+                "editors" => persons(&entry.editors),
+                "bibtex" => entry.bibtex.clone(),
+                "slug" => entry.slug.clone(),
+                "date_sort" => entry.date_sort.clone().unwrap_or_default(),
+                field => entry.get(field).unwrap_or_default().to_owned(),
+            })
+            .collect();
+        self.row(&cells, writer)
+    }
+
+    fn finalize(mut self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        self.write_header(writer)
+    }
+}
+
+/// A collection of MODS records.
+struct Mods(Xml);
+
+impl Mods {
+    fn new() -> Self {
+        let mut xml = Xml::new();
+        xml.open(
+            "modsCollection",
+            &[("xmlns", mods::NAMESPACE), ("version", "3.8")],
+        );
+        Mods(xml)
+    }
+}
+
+impl OutputFormat for Mods {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        mods::record(&mut self.0, &entry);
+        Ok(())
+    }
+
+    fn finalize(mut self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.close("modsCollection");
+        writer.write_all(self.0.finish().as_bytes())
+    }
+}
+
+/// Simple Dublin Core records in `oai_dc` containers.
+struct DublinCore(Xml);
+
+impl DublinCore {
+    fn new() -> Self {
+        let mut xml = Xml::new();
+        xml.open("records", dublincore::NAMESPACES);
+        DublinCore(xml)
+    }
+}
+
+impl OutputFormat for DublinCore {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        dublincore::record(&mut self.0, &entry);
+        Ok(())
+    }
+
+    fn finalize(mut self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.close("records");
+        writer.write_all(self.0.finish().as_bytes())
+    }
+}
+
+/// A Markdown list with a line per entry, optionally under year headings.
+struct Markdown {
+    years: Option<BTreeMap<Option<i32>, Vec<String>>>,
+}
+
+impl Markdown {
+    fn new(options: &Options) -> Self {
+        Markdown {
+            years: options.group_by_year.then(BTreeMap::new),
+        }
+    }
+
+    /// The list item of an entry: authors, title, venue, year and links.
+    fn line(entry: &SRAEntry) -> String {
+        let mut parts = Vec::new();
+        let persons = if entry.authors.is_empty() {
+            &entry.editors
+        } else {
+            &entry.authors
+        };
+        let names: Vec<String> = persons
+            .iter()
+            .map(|p| match &p.display_name {
+                Some(name) => name.clone(),
+                None => format!("{} {}", p.first_name, p.last_name)
+                    .trim()
+                    .to_owned(),
+            })
+            .collect();
+        if !names.is_empty() {
+            parts.push(escape_markdown(&names.join(", ")));
+        }
+        if let Some(title) = entry.get("title") {
+            parts.push(format!("**{}**", escape_markdown(title)));
+        }
+        let venue = [
+            "journaltitle",
+            "journal",
+            "booktitle",
+            "publisher",
+            "institution",
+            "school",
+        ]
+        .iter()
+        .find_map(|f| entry.get(f))
+        .map(escape_markdown);
+        let year = entry.get("year").map(escape_markdown);
+        match (venue, year) {
+            (Some(venue), Some(year)) => parts.push(format!("*{venue}*, {year}")),
+            (Some(venue), None) => parts.push(format!("*{venue}*")),
+            (None, Some(year)) => parts.push(year),
+            (None, None) => {}
+        }
+        let mut line = format!("- {}.", parts.join(". "));
+        if let Some(doi) = entry.get("doi") {
+            let doi = crate::strip_doi_prefix(doi);
+            let doi = escape_link(doi);
+            line.push_str(&format!(" [DOI](https://doi.org/{doi})"));
+        }
+        if let Some(url) = entry.get("url") {
+            line.push_str(&format!(" [URL]({})", escape_link(url)));
+        }
+        line
+    }
+}
+
+/// Escape the characters that Markdown would interpret as markup.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Percent-encode the characters that would end a Markdown link target.
+fn escape_link(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+}
+
+impl OutputFormat for Markdown {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        match &mut self.years {
+            Some(years) => {
+                let year = entry.date_sort.as_deref().map(|d| date::parts(d).0);
+                years.entry(year).or_default().push(Self::line(&entry));
+                Ok(())
+            }
+            None => writeln!(writer, "{}", Self::line(&entry)),
+        }
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        // Newest first, undated entries last
+        let years = self.years.into_iter().flatten().rev();
+        for (i, (year, lines)) in years.enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            match year {
+                Some(year) => writeln!(writer, "## {year}\n")?,
+                None => writeln!(writer, "## Undated\n")?,
+            }
+            for line in lines {
+                writeln!(writer, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// DataCite resources of the entries with a DOI.
+struct DataCite(Xml);
+
+impl DataCite {
+    fn new() -> Self {
+        let mut xml = Xml::new();
+        xml.open("resources", &[]);
+        DataCite(xml)
+    }
+}
+
+impl OutputFormat for DataCite {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        datacite::resource(&mut self.0, &entry);
+        Ok(())
+    }
+
+    fn finalize(mut self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.close("resources");
+        writer.write_all(self.0.finish().as_bytes())
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        let mut xml = Xml::new();
+        if datacite::resource(&mut xml, &entry).is_none() {
+            let message = format!("entry `{}` has no DOI", entry.id);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        writer.write_all(xml.finish().as_bytes())
+    }
+}
+
+/// The keys of the entries, one per line.
+struct Keys;
+
+impl OutputFormat for Keys {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "{}", entry.id)
+    }
+
+    fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A LaTeX `\nocite` command citing all entries.
+#[derive(Default)]
+struct Nocite {
+    keys: Vec<String>,
+}
+
+impl OutputFormat for Nocite {
+    fn entry(&mut self, entry: SRAEntry, _: &mut dyn Write) -> io::Result<()> {
+        self.keys.push(entry.id);
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "\\nocite{{{}}}", self.keys.join(","))
+    }
+}
+
+/// The JSON document as YAML.
+#[cfg(feature = "yaml")]
+struct Yaml(Json);
+
+#[cfg(feature = "yaml")]
+impl OutputFormat for Yaml {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.entry(entry, writer)
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        serde_yaml::to_writer(writer, &self.0.entries).map_err(io::Error::other)
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        serde_yaml::to_writer(writer, &self.0.layout.apply(entry)).map_err(io::Error::other)
+    }
+}
+
+/// A `CITATION.cff` document per entry.
+#[cfg(feature = "yaml")]
+#[derive(Default)]
+struct Cff {
+    started: bool,
+}
+
+#[cfg(feature = "yaml")]
+impl OutputFormat for Cff {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        if self.started {
+            writeln!(writer, "---")?;
+        }
+        self.started = true;
+        serde_yaml::to_writer(writer, &crate::cff::citation(&entry)).map_err(io::Error::other)
+    }
+
+    fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The JSON document as TOML, with a table per entry.
+#[cfg(feature = "toml")]
+struct Toml(Json);
+
+#[cfg(feature = "toml")]
+impl OutputFormat for Toml {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.entry(entry, writer)
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        let toml = toml::to_string(&self.0.entries).map_err(io::Error::other)?;
+        writer.write_all(toml.as_bytes())
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        let toml = toml::to_string(&self.0.layout.apply(entry)).map_err(io::Error::other)?;
+        writer.write_all(toml.as_bytes())
+    }
+}
+
+/// The JSON document as MessagePack.
+#[cfg(feature = "msgpack")]
+struct Msgpack(Json);
+
+#[cfg(feature = "msgpack")]
+impl OutputFormat for Msgpack {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.entry(entry, writer)
+    }
+
+    fn finalize(self: Box<Self>, mut writer: &mut dyn Write) -> io::Result<()> {
+        rmp_serde::encode::write_named(&mut writer, &self.0.entries).map_err(io::Error::other)
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, mut writer: &mut dyn Write) -> io::Result<()> {
+        rmp_serde::encode::write_named(&mut writer, &self.0.layout.apply(entry))
+            .map_err(io::Error::other)
+    }
+}
+
+/// The JSON document as CBOR.
+#[cfg(feature = "cbor")]
+struct Cbor(Json);
+
+#[cfg(feature = "cbor")]
+impl OutputFormat for Cbor {
+    fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.entry(entry, writer)
+    }
+
+    fn finalize(self: Box<Self>, writer: &mut dyn Write) -> io::Result<()> {
+        ciborium::into_writer(&self.0.entries, writer).map_err(io::Error::other)
+    }
+
+    fn single(self: Box<Self>, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+        ciborium::into_writer(&self.0.layout.apply(entry), writer).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Write};
+    use std::path::Path;
+
+    use biblatex::Bibliography;
+
+    use super::{write, write_grouped, write_split, Format, Layout, OutputFormat, SplitBy};
+    use crate::{convert, Options, SRAEntry};
+
+    /// Writes the keys of the entries, one per line.
+    struct Ids;
+
+    impl OutputFormat for Ids {
+        fn entry(&mut self, entry: SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+            writeln!(writer, "{}", entry.id)
+        }
+
+        fn finalize(self: Box<Self>, _: &mut dyn Write) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_format() {
+        let bib = Bibliography::parse("@misc{foo, title={Foo}}\n@misc{bar, title={Bar}}").unwrap();
+        let mut out = Vec::new();
+        write(&bib, &Options::default(), Box::new(Ids), &mut out).unwrap();
+        // By key, not in the order of the bibliography
+        assert_eq!(String::from_utf8(out).unwrap(), "bar\nfoo\n");
+    }
+
+    #[test]
+    fn registry() {
+        assert_eq!(Format::by_name("json").unwrap().name, "json");
+        #[cfg(feature = "yaml")]
+        assert_eq!(Format::by_name("yaml").unwrap().name, "yaml");
+        assert_eq!(
+            Format::from_path(Path::new("refs.bib")).unwrap().name,
+            "bibtex"
+        );
+        assert!(Format::from_path(Path::new("refs.txt")).is_none());
+        assert_eq!(Format::by_name("csl-json").unwrap().extension(), "json");
+        assert_eq!(Format::by_name("dublincore").unwrap().extension(), "xml");
+        assert_eq!(Format::by_name("datacite").unwrap().extension(), "xml");
+    }
+
+    #[test]
+    fn bibtexparser_layout() {
+        let bib =
+            Bibliography::parse("@article{foo, author={Smith, John and Doe, Jane}, title={Bar}}")
+                .unwrap();
+        let options = Options {
+            layout: Layout::Bibtexparser,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let json = (Format::by_name("json").unwrap().new)(&options);
+        write(&bib, &options, json, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"foo":{"ENTRYTYPE":"article","ID":"foo","author":"Smith, John and Doe, Jane","title":"Bar"}}"#
+        );
+    }
+
+    #[test]
+    fn nocite() {
+        let bib = Bibliography::parse("@misc{foo, title={Foo}}\n@misc{bar, title={Bar}}").unwrap();
+        let options = Options::default();
+        let mut out = Vec::new();
+        let nocite = (Format::by_name("nocite").unwrap().new)(&options);
+        write(&bib, &options, nocite, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\\nocite{bar,foo}\n");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml() {
+        let bib =
+            Bibliography::parse("@misc{foo:1, author={Doe, Jane}, title={Foo}, date={2020/2021}}")
+                .unwrap();
+        let options = Options::default();
+        let mut out = Vec::new();
+        let toml = (Format::by_name("toml").unwrap().new)(&options);
+        write(&bib, &options, toml, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("[\"foo:1\"]\nid = \"foo:1\"\n"), "{out}");
+        assert!(
+            out.contains("[[\"foo:1\".authors]]\nfirst_name = \"Jane\""),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn ndjson() {
+        let bib = Bibliography::parse("@misc{foo, title={Foo}}\n@misc{bar, title={Bar}}").unwrap();
+        let options = Options {
+            layout: Layout::Bibtexparser,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let ndjson = (Format::from_path(Path::new("refs.jsonl")).unwrap().new)(&options);
+        write(&bib, &options, ndjson, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"ENTRYTYPE\":\"misc\",\"ID\":\"bar\",\"title\":\"Bar\"}\n\
+             {\"ENTRYTYPE\":\"misc\",\"ID\":\"foo\",\"title\":\"Foo\"}\n"
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack() {
+        let bib = Bibliography::parse("@misc{foo, author={Doe, Jane}, title={Foo}}").unwrap();
+        let options = Options::default();
+        let mut out = Vec::new();
+        let msgpack = (Format::by_name("msgpack").unwrap().new)(&options);
+        write(&bib, &options, msgpack, &mut out).unwrap();
+        let value: serde_json::Value = rmp_serde::from_slice(&out).unwrap();
+        assert_eq!(value["foo"]["title"], "Foo");
+        assert_eq!(value["foo"]["authors"][0]["last_name"], "Doe");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor() {
+        let bib = Bibliography::parse("@misc{foo, author={Doe, Jane}, title={Foo}}").unwrap();
+        let options = Options::default();
+        let mut out = Vec::new();
+        let cbor = (Format::from_path(Path::new("refs.cbor")).unwrap().new)(&options);
+        write(&bib, &options, cbor, &mut out).unwrap();
+        let value: serde_json::Value = ciborium::from_reader(out.as_slice()).unwrap();
+        assert_eq!(value["foo"]["title"], "Foo");
+        assert_eq!(value["foo"]["authors"][0]["first_name"], "Jane");
+    }
+
+    #[test]
+    fn csv() {
+        let bib = Bibliography::parse(
+            "@misc{foo, author={Doe, Jane and Smith, John}, title={Foo, \"Bar\"}, year=2020}",
+        )
+        .unwrap();
+        let options = Options {
+            columns: ["id", "year", "title", "authors"].map(String::from).into(),
+            person_separator: Some(" & ".into()),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let csv = (Format::by_name("csv").unwrap().new)(&options);
+        write(&bib, &options, csv, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,year,title,authors\nfoo,2020,\"Foo, \"\"Bar\"\"\",\"Doe, Jane & Smith, John\"\n"
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, author={Doe, Jane and Smith, John}, title={Lorem}, journal={Dolor}, year=2020, doi={10.1000/xyz}}
+            @misc{b, title={Ipsum}, year=2021, url={https://example.org}}
+            @misc{c, title={Sit}}
+            @misc{d, author={Doe_, J.}, title={A *B* [C] | D}, url={https://example.org/a_(b)}}
+            "#,
+        )
+        .unwrap();
+        let options = Options {
+            group_by_year: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let markdown = (Format::from_path(Path::new("pubs.md")).unwrap().new)(&options);
+        write(&bib, &options, markdown, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "## 2021\n\n- **Ipsum**. 2021. [URL](https://example.org)\n\n\
+             ## 2020\n\n- Jane Doe, John Smith. **Lorem**. *Dolor*, 2020. [DOI](https://doi.org/10.1000/xyz)\n\n\
+             ## Undated\n\n- **Sit**.\n\
+             - J. Doe\\_. **A \\*B\\* \\[C\\] \\| D**. [URL](https://example.org/a_%28b%29)\n"
+        );
+    }
+
+    #[test]
+    fn indent() {
+        let bib = Bibliography::parse("@misc{foo, title={Foo}}").unwrap();
+        let options = Options {
+            layout: Layout::Bibtexparser,
+            indent: Some(4),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let json = (Format::by_name("json").unwrap().new)(&options);
+        write(&bib, &options, json, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\n    \"foo\": {\n        \"ENTRYTYPE\": \"misc\",\n        \"ID\": \"foo\",\n        \"title\": \"Foo\"\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn split() {
+        let bib = Bibliography::parse("@misc{a, title={A}} @misc{b/c, title={B}}").unwrap();
+        let dir = std::env::temp_dir().join(format!("bib2json-split-{}", std::process::id()));
+        let json = Format::by_name("json").unwrap();
+        let options = Options::default();
+        let written = write_split(convert(&bib, &options), json, &options, &dir).unwrap();
+        assert_eq!(written, 2);
+        let a = std::fs::read_to_string(dir.join("a.json")).unwrap();
+        assert!(a.starts_with(r#"{"id":"a","#), "{a}");
+        assert!(dir.join("b_c.json").exists());
+
+        let bib = Bibliography::parse("@misc{a, title={A}} @misc{b/c, title={C}}").unwrap();
+        let written = write_split(convert(&bib, &options), json, &options, &dir).unwrap();
+        assert_eq!(written, 1);
+
+        // Only files of the previous run are removed
+        std::fs::write(dir.join("index.json"), "").unwrap();
+        let bib = Bibliography::parse("@misc{a, title={A}}").unwrap();
+        let written = write_split(convert(&bib, &options), json, &options, &dir).unwrap();
+        assert_eq!(written, 0);
+        assert!(!dir.join("b_c.json").exists());
+        assert!(dir.join("index.json").exists());
+
+        let bib = Bibliography::parse("@misc{b/c, title={B}} @misc{b_c, title={C}}").unwrap();
+        let error = write_split(convert(&bib, &options), json, &options, &dir).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(!dir.join("b_c.json").exists());
+        assert!(dir.join("a.json").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn grouped() {
+        let bib = Bibliography::parse(
+            "@misc{a, year=2023} @misc{b, date={2023-05}} @misc{c, year=2021} @misc{d, title={D}}",
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("bib2json-grouped-{}", std::process::id()));
+        let keys = Format::by_name("keys").unwrap();
+        let options = Options::default();
+        let entries = convert(&bib, &options);
+        let written = write_grouped(entries, SplitBy::Year, keys, &options, &dir).unwrap();
+        assert_eq!(written, 3);
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("2023.txt"), "a\nb\n");
+        assert_eq!(read("2021.txt"), "c\n");
+        assert_eq!(read("unknown.txt"), "d\n");
+
+        // The output by type stays when the output by year changes
+        let entries = convert(&bib, &options);
+        write_grouped(entries, SplitBy::Type, keys, &options, &dir).unwrap();
+        let bib = Bibliography::parse("@misc{a, year=2023} @misc{e, date={-0044-03-15}}").unwrap();
+        let entries = convert(&bib, &options);
+        let written = write_grouped(entries, SplitBy::Year, keys, &options, &dir).unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(read("-0044.txt"), "e\n");
+        assert!(!dir.join("2021.txt").exists());
+        assert!(!dir.join("unknown.txt").exists());
+        assert_eq!(read("misc.txt"), "a\nb\nc\nd\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+//! Work split across threads, and its progress.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Counts the processed items of a long-running step, to report the progress
+/// from another thread. Clones share the counts.
+#[derive(Debug, Default, Clone)]
+pub struct Progress {
+    done: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl Progress {
+    /// Start over with this many items.
+    pub fn start(&self, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Count a processed item.
+    pub fn inc(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of processed items and of all items.
+    pub fn get(&self) -> (usize, usize) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Apply `f` to the items on up to `jobs` threads, keeping their order.
+/// With at most one job, the items are processed on the calling thread.
+pub fn map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let size = items.len().div_ceil(jobs);
+    let f = &f;
+    thread::scope(|s| {
+        let workers: Vec<_> = items
+            .chunks(size)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{map, Progress};
+
+    #[test]
+    fn order() {
+        let items: Vec<u32> = (0..100).collect();
+        let squares: Vec<u32> = items.iter().map(|i| i * i).collect();
+        for jobs in [0, 1, 3, 8, 200] {
+            assert_eq!(map(&items, jobs, |i| i * i), squares);
+        }
+
+        let progress = Progress::default();
+        progress.start(items.len());
+        map(&items, 4, |_| progress.clone().inc());
+        assert_eq!(progress.get(), (100, 100));
+    }
+}
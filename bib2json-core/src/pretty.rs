@@ -0,0 +1,183 @@
+//! Canonical formatting of BibTeX sources, like `rustfmt` for `.bib` files.
+//!
+//! Entries get lowercase types and field names, one field per line with a
+//! trailing comma, aligned `=` signs and braces instead of quotes. Comments,
+//! `@string` and `@preamble` blocks between the entries are kept as they are.
+
+use biblatex::{Pair, RawBibliography, RawEntry, Spanned};
+
+use crate::input::ReadError;
+
+/// The order of the fields in an entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FieldOrder {
+    /// Persons, titles, the venue, numbers, dates, identifiers, then the others alphabetically
+    #[default]
+    Canonical,
+    /// Alphabetically by name
+    Alphabetical,
+    /// As in the source
+    Source,
+}
+
+/// Fields in their canonical order.
+const CANONICAL: &[&str] = &[
+    "author",
+    "editor",
+    "translator",
+    "title",
+    "subtitle",
+    "booktitle",
+    "journaltitle",
+    "journal",
+    "eventtitle",
+    "series",
+    "edition",
+    "volume",
+    "number",
+    "issue",
+    "chapter",
+    "pages",
+    "publisher",
+    "institution",
+    "school",
+    "organization",
+    "location",
+    "address",
+    "date",
+    "year",
+    "month",
+    "day",
+    "isbn",
+    "issn",
+    "doi",
+    "eprint",
+    "eprinttype",
+    "url",
+    "urldate",
+    "crossref",
+    "xref",
+    "note",
+    "keywords",
+    "abstract",
+];
+
+/// How to format entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// Spaces before each field.
+    pub indent: usize,
+    /// Pad the field names of an entry to the same width.
+    pub align: bool,
+    pub order: FieldOrder,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent: 2,
+            align: true,
+            order: FieldOrder::default(),
+        }
+    }
+}
+
+/// The position of a field in the canonical order.
+fn rank(field: &str) -> (usize, &str) {
+    let position = CANONICAL.iter().position(|f| *f == field);
+    (position.unwrap_or(CANONICAL.len()), field)
+}
+
+/// The end of an entry, after its closing brace.
+pub(crate) fn end(src: &str, entry: &Spanned<RawEntry>) -> usize {
+    let rest = &src[entry.span.end..];
+    entry.span.end + rest.find('}').map_or(rest.len(), |i| i + 1)
+}
+
+/// The value of a field as in the source, with braces instead of quotes.
+fn value(src: &str, pair: &Pair) -> String {
+    let value = src[pair.value.span.clone()].trim_end();
+    let quoted = value.len() >= 2 && value.starts_with('"') && value.ends_with('"');
+    match quoted && pair.value.v.len() == 1 {
+        true => format!("{{{}}}", &value[1..value.len() - 1]),
+        false => value.to_owned(),
+    }
+}
+
+/// An entry in the style.
+fn entry(src: &str, entry: &RawEntry, style: &Style) -> String {
+    let mut fields: Vec<(String, String)> = entry
+        .fields
+        .iter()
+        .map(|pair| (pair.key.v.to_lowercase(), value(src, pair)))
+        .collect();
+    match style.order {
+        FieldOrder::Canonical => fields.sort_by(|(a, _), (b, _)| rank(a).cmp(&rank(b))),
+        FieldOrder::Alphabetical => fields.sort_by(|(a, _), (b, _)| a.cmp(b)),
+        FieldOrder::Source => {}
+    }
+    let width = match style.align {
+        true => fields.iter().map(|(f, _)| f.len()).max().unwrap_or(0),
+        false => 0,
+    };
+    let mut out = format!("@{}{{{},\n", entry.kind.v.to_lowercase(), entry.key.v);
+    for (field, value) in fields {
+        let indent = " ".repeat(style.indent);
+        out.push_str(&format!("{indent}{field:width$} = {value},\n"));
+    }
+    out.push('}');
+    out
+}
+
+/// Format all entries of a BibTeX source, separated by empty lines.
+pub fn format(src: &str, style: &Style) -> Result<String, ReadError> {
+    let raw = RawBibliography::parse(src)
+        .map_err(|e| ReadError::at(src, e.span.start, e.kind.to_string()))?;
+    let mut out = String::new();
+    let mut cursor = 0;
+    for e in &raw.entries {
+        let between = src[cursor..e.span.start].trim();
+        if !between.is_empty() {
+            out.push_str(between);
+            out.push_str("\n\n");
+        }
+        out.push_str(&entry(src, &e.v, style));
+        out.push_str("\n\n");
+        cursor = end(src, e);
+    }
+    let rest = src[cursor..].trim();
+    if !rest.is_empty() {
+        out.push_str(rest);
+        out.push('\n');
+    }
+    if out.ends_with("\n\n") {
+        out.pop();
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format, FieldOrder, Style};
+
+    #[test]
+    fn canonical() {
+        let src = "% refs\n@string{j = \"Journal\"}\n@ARTICLE{a, Year=2020,\n    title = \"A {B}\", journal = j # \" X\", Author={Doe, Jane}}\n\n\n@misc{b,title={B}}\n";
+        assert_eq!(
+            format(src, &Style::default()).unwrap(),
+            "% refs\n@string{j = \"Journal\"}\n\n@article{a,\n  author  = {Doe, Jane},\n  title   = {A {B}},\n  journal = j # \" X\",\n  year    = 2020,\n}\n\n@misc{b,\n  title = {B},\n}\n"
+        );
+        let style = Style {
+            indent: 4,
+            align: false,
+            order: FieldOrder::Alphabetical,
+        };
+        assert_eq!(
+            format("@misc{b, year={2020}, note={N}}", &style).unwrap(),
+            "@misc{b,\n    note = {N},\n    year = {2020},\n}\n"
+        );
+        let formatted = format(src, &Style::default()).unwrap();
+        assert_eq!(format(&formatted, &Style::default()).unwrap(), formatted);
+    }
+}
@@ -0,0 +1,357 @@
+//! Filter expressions like `year >= 2020 && entry_type == "article" && keywords ~ "rtos"`.
+//!
+//! An expression compares properties of an entry with strings or numbers
+//! using `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` (case-insensitive regular
+//! expression match), and combines comparisons with `&&`, `||`, `!` and
+//! parentheses. A property on its own tests whether the entry has it.
+//!
+//! Properties are `id`, `entry_type`, `year`, `authors`, `editors` and
+//! `keywords`, as well as any field name. A comparison holds if one of the
+//! values of a property satisfies it, `!=` if none is equal. Values that are
+//! both numbers are compared as numbers.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::SRAEntry;
+
+/// An error in a query, at a byte offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(String),
+    Op(&'static str),
+}
+
+/// Operators, longer ones first.
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "~", "!", "(", ")",
+];
+
+/// Split a query into tokens with their offsets.
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let rest = &query[offset..];
+        if c.is_whitespace() {
+            chars.next();
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push((offset, Token::Op(op)));
+            for _ in 0..op.len() {
+                chars.next();
+            }
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => value.push(c),
+                        None => break,
+                    },
+                    Some((_, c)) => value.push(c),
+                    None => {
+                        return Err(QueryError {
+                            offset,
+                            message: "unterminated string".into(),
+                        })
+                    }
+                }
+            }
+            tokens.push((offset, Token::Str(value)));
+        } else if c.is_ascii_digit() || c == '-' {
+            let mut value = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.' || (c == '-' && value.is_empty())) {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            tokens.push((offset, Token::Num(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut value = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            tokens.push((offset, Token::Ident(value.to_lowercase())));
+        } else {
+            return Err(QueryError {
+                offset,
+                message: format!("unexpected `{c}`"),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Has(String),
+    Compare(String, Compare, String),
+    Match(String, Regex),
+}
+
+/// A recursive descent parser over the tokens.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, t)| t)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.end, |(o, _)| *o)
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError {
+            offset: self.offset(),
+            message: message.into(),
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Op(o)) if *o == op);
+        self.position += usize::from(found);
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            return match self.eat(")") {
+                true => Ok(expr),
+                false => Err(self.error("expected `)`")),
+            };
+        }
+        let Some(Token::Ident(property)) = self.peek().cloned() else {
+            return Err(self.error("expected a property like `year`"));
+        };
+        self.position += 1;
+        let compare = match self.peek() {
+            Some(Token::Op("==")) => Compare::Eq,
+            Some(Token::Op("!=")) => Compare::Ne,
+            Some(Token::Op("<")) => Compare::Lt,
+            Some(Token::Op("<=")) => Compare::Le,
+            Some(Token::Op(">")) => Compare::Gt,
+            Some(Token::Op(">=")) => Compare::Ge,
+            Some(Token::Op("~")) => {
+                self.position += 1;
+                let offset = self.offset();
+                let value = self.value()?;
+                let regex = RegexBuilder::new(&value)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| QueryError {
+                        offset,
+                        message: e.to_string(),
+                    })?;
+                return Ok(Expr::Match(property, regex));
+            }
+            _ => return Ok(Expr::Has(property)),
+        };
+        self.position += 1;
+        Ok(Expr::Compare(property, compare, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<String, QueryError> {
+        match self.peek().cloned() {
+            Some(Token::Str(value) | Token::Num(value)) => {
+                self.position += 1;
+                Ok(value)
+            }
+            _ => Err(self.error("expected a string or number")),
+        }
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub struct Query(Expr);
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            end: s.len(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Query(expr)),
+            Some(_) => Err(parser.error("expected `&&`, `||` or the end")),
+        }
+    }
+}
+
+/// The values of a property of an entry.
+fn values(entry: &SRAEntry, property: &str) -> Vec<String> {
+    let names = |persons: &[crate::SRAPerson]| {
+        persons
+            .iter()
+            .map(|p| {
+                format!("{} {}", p.first_name, p.last_name)
+                    .trim()
+                    .to_owned()
+            })
+            .collect()
+    };
+    match property {
+        "id" | "key" => vec![entry.id.clone()],
+        "entry_type" | "type" => vec![entry.entry_type.to_lowercase()],
+        "year" => entry.year().map(str::to_owned).into_iter().collect(),
+        "authors" => names(&entry.authors),
+        "editors" => names(&entry.editors),
+        "keywords" => entry.keywords().into_iter().map(str::to_owned).collect(),
+        field => entry.get(field).map(str::to_owned).into_iter().collect(),
+    }
+}
+
+/// Compare as numbers if both are ones, as strings otherwise.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+impl Expr {
+    fn eval(&self, entry: &SRAEntry) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(entry) && b.eval(entry),
+            Expr::Or(a, b) => a.eval(entry) || b.eval(entry),
+            Expr::Not(a) => !a.eval(entry),
+            Expr::Has(property) => !values(entry, property).is_empty(),
+            Expr::Match(property, regex) => {
+                values(entry, property).iter().any(|v| regex.is_match(v))
+            }
+            Expr::Compare(property, Compare::Ne, value) => values(entry, property)
+                .iter()
+                .all(|v| compare(v, value).is_ne()),
+            Expr::Compare(property, op, value) => values(entry, property).iter().any(|v| {
+                let ordering = compare(v, value);
+                match op {
+                    Compare::Eq => ordering.is_eq(),
+                    Compare::Lt => ordering.is_lt(),
+                    Compare::Le => ordering.is_le(),
+                    Compare::Gt => ordering.is_gt(),
+                    Compare::Ge => ordering.is_ge(),
+                    Compare::Ne => unreachable!(),
+                }
+            }),
+        }
+    }
+}
+
+impl Query {
+    /// Whether the entry satisfies the query.
+    pub fn matches(&self, entry: &SRAEntry) -> bool {
+        self.0.eval(entry)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::Query;
+    use crate::{convert, Options};
+
+    #[test]
+    fn query() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, author={Doe, Jane}, title={A}, year=2021, keywords={RTOS, energy}}
+            @inproceedings{b, author={Roe, Rick}, title={B}, year=2019, keywords={rtos}}
+            @article{c, title={C}, date={2022-05}}
+            "#,
+        )
+        .unwrap();
+        let entries = convert(&bib, &Options::default());
+        let keys = |query: &str| -> Vec<&str> {
+            let query: Query = query.parse().unwrap();
+            entries
+                .iter()
+                .filter(|e| query.matches(e))
+                .map(|e| e.id.as_str())
+                .collect()
+        };
+        assert_eq!(
+            keys(r#"year >= 2020 && entry_type == "article" && keywords ~ "^rtos$""#),
+            ["a"]
+        );
+        assert_eq!(keys(r#"keywords ~ "rtos" && !(year > 2020)"#), ["b"]);
+        assert_eq!(keys(r#"authors ~ "doe" || year == "2022""#), ["a", "c"]);
+        assert_eq!(keys("!authors"), ["c"]);
+        assert_eq!(keys(r#"entry_type != "article""#), ["b"]);
+
+        let error = "year >= && x".parse::<Query>().unwrap_err();
+        assert_eq!(error.to_string(), "expected a string or number at offset 8");
+        assert!("year ~ \"(\"".parse::<Query>().is_err());
+        assert!("(year".parse::<Query>().is_err());
+    }
+}
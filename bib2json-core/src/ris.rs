@@ -0,0 +1,393 @@
+//! Reading and writing of RIS files (as exported by most reference managers).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use biblatex::{Bibliography, Entry, EntryType};
+
+use crate::input::chunks;
+use crate::{date, SRAEntry};
+
+/// Maps RIS reference types to biblatex entry types.
+const TYPES: &[(&str, &str)] = &[
+    ("JOUR", "article"),
+    ("EJOUR", "article"),
+    ("MGZN", "article"),
+    ("NEWS", "article"),
+    ("CONF", "inproceedings"),
+    ("CPAPER", "inproceedings"),
+    ("BOOK", "book"),
+    ("EBOOK", "book"),
+    ("EDBOOK", "collection"),
+    ("CHAP", "incollection"),
+    ("ECHAP", "incollection"),
+    ("THES", "phdthesis"),
+    ("RPRT", "techreport"),
+    ("PAT", "patent"),
+    ("ELEC", "online"),
+    ("WEB", "online"),
+    ("UNPB", "unpublished"),
+    ("COMP", "software"),
+    ("DATA", "dataset"),
+];
+
+/// Maps the biblatex entry types that are missing in [`TYPES`] for writing.
+const MORE_TYPES: &[(&str, &str)] = &[
+    ("CHAP", "inbook"),
+    ("THES", "mastersthesis"),
+    ("THES", "thesis"),
+    ("RPRT", "report"),
+    ("CONF", "proceedings"),
+];
+
+/// Maps RIS tags to biblatex fields, tags with several values are joined.
+const FIELDS: &[(&str, &str)] = &[
+    ("TI", "title"),
+    ("T1", "title"),
+    ("T3", "series"),
+    ("VL", "volume"),
+    ("IS", "number"),
+    ("ET", "edition"),
+    ("PB", "publisher"),
+    ("CY", "address"),
+    ("DO", "doi"),
+    ("UR", "url"),
+    ("AB", "abstract"),
+    ("N2", "abstract"),
+    ("N1", "note"),
+    ("KW", "keywords"),
+    ("LA", "language"),
+];
+
+/// An error while parsing a RIS file.
+#[derive(Debug)]
+pub struct ParseError {
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Splits a RIS line into its tag and value.
+fn tag(line: &str) -> Option<(&str, &str)> {
+    let (tag, value) = line.split_once("  -")?;
+    let tag = tag.trim_start_matches('\u{feff}');
+    (tag.len() == 2 && tag.chars().all(|c| c.is_ascii_alphanumeric())).then(|| (tag, value.trim()))
+}
+
+/// Parse the records of a RIS file into a bibliography.
+pub fn parse(src: &str) -> Result<Bibliography, ParseError> {
+    let mut bib = Bibliography::new();
+    let mut record: Option<Vec<(&str, String)>> = None;
+
+    for (i, line) in src.lines().enumerate() {
+        let error = |message: &str| ParseError {
+            line: i + 1,
+            message: message.to_owned(),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((tag, value)) = tag(line) else {
+            // Untagged lines continue the previous value
+            match record.as_mut().and_then(|r| r.last_mut()) {
+                Some((_, last)) => {
+                    last.push(' ');
+                    last.push_str(line.trim());
+                    continue;
+                }
+                None => return Err(error("expected a `XX  - value` tag line")),
+            }
+        };
+        match (tag, &mut record) {
+            ("TY", None) => record = Some(vec![(tag, value.to_owned())]),
+            ("TY", Some(_)) => return Err(error("missing `ER` before `TY`")),
+            ("ER", Some(_)) => {
+                let entry = entry(record.take().unwrap(), &bib);
+                bib.insert(entry);
+            }
+            (_, Some(record)) => record.push((tag, value.to_owned())),
+            (_, None) => return Err(error("expected `TY` at the start of a record")),
+        }
+    }
+    if record.is_some() {
+        return Err(ParseError {
+            line: src.lines().count(),
+            message: "missing `ER` at the end of the file".into(),
+        });
+    }
+    Ok(bib)
+}
+
+/// Convert one RIS record into an entry.
+fn entry(record: Vec<(&str, String)>, bib: &Bibliography) -> Entry {
+    let all = |tags: &[&str]| {
+        record
+            .iter()
+            .filter(|(tag, value)| tags.contains(tag) && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+    };
+    let first = |tags: &[&str]| all(tags).first().copied();
+
+    let ty = first(&["TY"]).unwrap_or_default();
+    let entry_type = TYPES
+        .iter()
+        .find(|(ris, _)| *ris == ty)
+        .map_or("misc", |(_, bib)| bib);
+
+    let mut fields: BTreeMap<&str, String> = BTreeMap::new();
+    for (tag, field) in FIELDS {
+        if fields.contains_key(field) {
+            continue;
+        }
+        let values = all(&[tag]);
+        if !values.is_empty() {
+            let sep = if *field == "keywords" { ", " } else { " " };
+            fields.insert(field, values.join(sep));
+        }
+    }
+
+    let authors = all(&["AU", "A1"]);
+    if !authors.is_empty() {
+        fields.insert("author", authors.join(" and "));
+    }
+    let editors = all(&["ED", "A2"]);
+    if !editors.is_empty() && entry_type != "article" {
+        fields.insert("editor", editors.join(" and "));
+    }
+    if let Some(container) = first(&["T2", "JO", "JF", "JA", "BT"]) {
+        let field = if entry_type == "article" {
+            "journal"
+        } else {
+            "booktitle"
+        };
+        fields.insert(field, container.to_owned());
+    }
+    if let Some(date) = first(&["PY", "Y1", "DA"]) {
+        let year: String = date.chars().take_while(char::is_ascii_digit).collect();
+        if !year.is_empty() {
+            fields.insert("year", year);
+        }
+    }
+    if let Some(start) = first(&["SP"]) {
+        let pages = match first(&["EP"]) {
+            Some(end) => format!("{start}--{end}"),
+            None => start.to_owned(),
+        };
+        fields.insert("pages", pages);
+    }
+    if let Some(sn) = first(&["SN"]) {
+        let field = if entry_type == "article" {
+            "issn"
+        } else {
+            "isbn"
+        };
+        fields.insert(field, sn.to_owned());
+    }
+
+    let key = match first(&["ID"]) {
+        Some(id) => id.to_owned(),
+        None => generate_key(&fields, bib),
+    };
+    let mut entry = Entry::new(key, EntryType::new(entry_type));
+    for (field, value) in fields {
+        entry.set(field, chunks(&value));
+    }
+    entry
+}
+
+/// Generate an unused key from the first author's last name and the year.
+pub(crate) fn generate_key(fields: &BTreeMap<&str, String>, bib: &Bibliography) -> String {
+    let author = fields
+        .get("author")
+        .and_then(|a| a.split(" and ").next())
+        .map(|a| a.split(',').next().unwrap_or(a))
+        .map(|a| a.split_whitespace().last().unwrap_or(a))
+        .unwrap_or("anon");
+    let base: String = author
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .chain(fields.get("year").into_iter().flat_map(|y| y.chars()))
+        .collect();
+    // After `a` to `z`, the suffixes continue with `-27`, `-28`, ...
+    std::iter::once(String::new())
+        .chain(('a'..='z').map(String::from))
+        .chain((27..).map(|n| format!("-{n}")))
+        .map(|suffix| format!("{base}{suffix}"))
+        .find(|key| bib.get(key).is_none())
+        .unwrap_or(base)
+}
+
+/// Write an entry as a RIS record, unknown types become `GEN`.
+pub(crate) fn write(entry: &SRAEntry, writer: &mut dyn Write) -> io::Result<()> {
+    let entry_type = entry.entry_type.to_lowercase();
+    let ty = TYPES
+        .iter()
+        .chain(MORE_TYPES)
+        .find(|(_, bib)| *bib == entry_type)
+        .map_or("GEN", |(ris, _)| ris);
+    tagged(writer, "TY", ty)?;
+    tagged(writer, "ID", &entry.id)?;
+    for author in &entry.authors {
+        tagged(writer, "AU", &author.to_bibtex())?;
+    }
+    for editor in &entry.editors {
+        tagged(writer, "ED", &editor.to_bibtex())?;
+    }
+    let mut written = Vec::new();
+    for (tag, field) in FIELDS {
+        let Some(value) = entry.get(field).filter(|_| !written.contains(field)) else {
+            continue;
+        };
+        written.push(*field);
+        if *field == "keywords" {
+            for keyword in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+                tagged(writer, "KW", keyword)?;
+            }
+        } else {
+            tagged(writer, tag, value)?;
+        }
+    }
+    let container = if entry_type == "article" {
+        entry.get("journaltitle").or_else(|| entry.get("journal"))
+    } else {
+        entry.get("booktitle")
+    };
+    if let Some(container) = container {
+        tagged(writer, "T2", container)?;
+    }
+    if let Some(year) = entry.date_sort.as_deref().map(date::sort_year) {
+        tagged(writer, "PY", year)?;
+    }
+    if let Some(pages) = entry.get("pages") {
+        let mut pages = pages.split(['-', '–']).filter(|p| !p.is_empty());
+        if let Some(start) = pages.next() {
+            tagged(writer, "SP", start.trim())?;
+        }
+        if let Some(end) = pages.next() {
+            tagged(writer, "EP", end.trim())?;
+        }
+    }
+    if let Some(sn) = entry.get("issn").or_else(|| entry.get("isbn")) {
+        tagged(writer, "SN", sn)?;
+    }
+    writeln!(writer, "ER  - ")
+}
+
+/// Write a tag line, line breaks in the value become spaces as they would
+/// start a new tag.
+fn tagged(writer: &mut dyn Write, tag: &str, value: &str) -> io::Result<()> {
+    let value = value.replace("\r\n", " ").replace(['\n', '\r'], " ");
+    writeln!(writer, "{tag}  - {value}")
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{parse, write};
+    use crate::SRAEntry;
+
+    #[test]
+    fn records() {
+        let ris = "TY  - JOUR\n\
+                   AU  - Smith, John\n\
+                   AU  - Doe, Jane\n\
+                   TI  - Lorem Ipsum\n\
+                   JO  - Journal of Dolor\n\
+                   PY  - 2023/05/01\n\
+                   SP  - 15\n\
+                   EP  - 29\n\
+                   DO  - 10.1000/xyz\n\
+                   ER  - \n\
+                   \n\
+                   TY  - CONF\n\
+                   ID  - Foo2020\n\
+                   AU  - Foo, Bar\n\
+                   T2  - Proceedings of Baz\n\
+                   ER  - \n";
+        let bib = parse(ris).unwrap();
+        assert_eq!(bib.len(), 2);
+
+        let smith = bib.get("Smith2023").unwrap();
+        assert_eq!(smith.entry_type.to_string(), "article");
+        assert_eq!(smith.author().unwrap().len(), 2);
+        assert_eq!(
+            smith.get_as::<String>("journal").unwrap(),
+            "Journal of Dolor"
+        );
+        assert_eq!(smith.get_as::<String>("pages").unwrap(), "15--29");
+        assert_eq!(smith.get_as::<String>("doi").unwrap(), "10.1000/xyz");
+
+        let foo = bib.get("Foo2020").unwrap();
+        assert_eq!(foo.entry_type.to_string(), "inproceedings");
+        assert_eq!(
+            foo.get_as::<String>("booktitle").unwrap(),
+            "Proceedings of Baz"
+        );
+    }
+
+    #[test]
+    fn generated_keys() {
+        let ris = "TY  - JOUR\nAU  - Smith, John\nPY  - 2023\nER  - \n".repeat(30);
+        let bib = parse(&ris).unwrap();
+        assert_eq!(bib.len(), 30);
+        let keys: Vec<_> = bib.keys().collect();
+        assert_eq!(keys[..3], ["Smith2023", "Smith2023a", "Smith2023b"]);
+        assert_eq!(
+            keys[26..],
+            ["Smith2023z", "Smith2023-27", "Smith2023-28", "Smith2023-29"]
+        );
+    }
+
+    #[test]
+    fn missing_end() {
+        let err = parse("TY  - JOUR\nTI  - Foo\n").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bib = Bibliography::parse(
+            "@article{Smith2023, author={Smith, John}, title={Lorem}, journal={Dolor}, \
+             year=2023, pages={15--29}, keywords={a, b}}",
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        write(
+            &SRAEntry::from(bib.get("Smith2023").unwrap(), &bib),
+            &mut out,
+        )
+        .unwrap();
+        let ris = String::from_utf8(out).unwrap();
+        assert_eq!(
+            ris,
+            "TY  - JOUR\nID  - Smith2023\nAU  - Smith, John\nTI  - Lorem\nKW  - a\nKW  - b\n\
+             T2  - Dolor\nPY  - 2023\nSP  - 15\nEP  - 29\nER  - \n"
+        );
+        let parsed = parse(&ris).unwrap();
+        let entry = parsed.get("Smith2023").unwrap();
+        assert_eq!(entry.get_as::<String>("journal").unwrap(), "Dolor");
+        assert_eq!(entry.get_as::<String>("pages").unwrap(), "15--29");
+    }
+
+    #[test]
+    fn line_breaks() {
+        let entry: SRAEntry = serde_json::from_str(
+            r#"{"id": "a", "entry_type": "misc", "abstract": "Lorem\nipsum\r\ndolor"}"#,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        write(&entry, &mut out).unwrap();
+        let ris = String::from_utf8(out).unwrap();
+        assert!(ris.contains("\nAB  - Lorem ipsum dolor\n"), "{ris}");
+        assert_eq!(parse(&ris).unwrap().len(), 1);
+    }
+}
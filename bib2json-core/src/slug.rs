@@ -0,0 +1,87 @@
+//! ASCII slugs for URLs and file names, like `muller-2005-lorem-ipsum-et-dolor`.
+
+use std::collections::{HashMap, HashSet};
+
+use biblatex::{Bibliography, ChunksExt, Entry};
+use deunicode::deunicode;
+
+/// Number of title words in a slug.
+const TITLE_WORDS: usize = 4;
+
+/// The lowercase ASCII words of a text.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The slug of an entry from the first author's last name, the year and the
+/// beginning of the title, or from the key if they are all missing.
+pub fn slug(e: &Entry) -> String {
+    let author = e
+        .author()
+        .ok()
+        .and_then(|a| a.into_iter().next())
+        .map(|p| p.name)
+        .unwrap_or_default();
+    let year = crate::date::sort_key(e)
+        .map(|key| crate::date::sort_year(&key).to_owned())
+        .unwrap_or_default();
+    let title = e.title().map(|t| t.format_verbatim()).unwrap_or_default();
+    let parts: Vec<String> = words(&author)
+        .into_iter()
+        .chain(words(&year))
+        .chain(words(&title).into_iter().take(TITLE_WORDS))
+        .collect();
+    if parts.is_empty() {
+        words(&e.key).join("-")
+    } else {
+        parts.join("-")
+    }
+}
+
+/// Unique slugs for all entries, keyed by citation key. Entries with the same
+/// slug get a numeric suffix in source order, starting with `-2`.
+pub fn assign(bib: &Bibliography) -> HashMap<String, String> {
+    let mut used = HashSet::new();
+    let mut slugs = HashMap::new();
+    for e in bib.iter() {
+        let base = slug(e);
+        let mut slug = base.clone();
+        let mut n = 1;
+        while !used.insert(slug.clone()) {
+            n += 1;
+            slug = format!("{base}-{n}");
+        }
+        slugs.insert(e.key.clone(), slug);
+    }
+    slugs
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::assign;
+
+    #[test]
+    fn slugs() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, author={Max Müller}, year=2005, title={Lörem Ipsum: et Dolor sit Amet}}
+            @article{b, author={Max Müller}, year=2005, title={Lörem Ipsum: et Dolor}}
+            @misc{Foo:Bar, title={}}
+            @book{c, author={Caesar, Gaius Iulius}, date={-0044-03-15}, title={Gallic War}}
+            "#,
+        )
+        .unwrap();
+        let slugs = assign(&bib);
+        assert_eq!(slugs["a"], "muller-2005-lorem-ipsum-et-dolor");
+        assert_eq!(slugs["b"], "muller-2005-lorem-ipsum-et-dolor-2");
+        assert_eq!(slugs["Foo:Bar"], "foo-bar");
+        assert_eq!(slugs["c"], "caesar-0044-gallic-war");
+    }
+}
@@ -0,0 +1,425 @@
+//! Locale-aware ordering of the entries.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use biblatex::RawBibliography;
+use icu_collator::CollatorOptions;
+
+use crate::input::{InputFormat, ReadError};
+use crate::{by_name, convert, date, pretty, Options, SRAEntry, UnknownName};
+
+/// Orders of the entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortBy {
+    /// By citation key
+    #[default]
+    Key,
+    /// By date, oldest first, entries without a date last
+    Year,
+    /// By the names of the authors, or editors, entries without persons last
+    Author,
+    /// By entry type
+    Type,
+    /// By biblatex's default `nty` scheme (name, title, year), honoring the
+    /// `presort`, `sortkey`, `sortname`, `sorttitle` and `sortyear` fields
+    Biblatex,
+}
+
+impl SortBy {
+    /// The orders by their names in the command line tool and the bindings.
+    const NAMES: &'static [(&'static str, SortBy)] = &[
+        ("key", SortBy::Key),
+        ("year", SortBy::Year),
+        ("author", SortBy::Author),
+        ("type", SortBy::Type),
+        ("biblatex", SortBy::Biblatex),
+    ];
+
+    /// The names of the orders.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().map(|(name, _)| *name)
+    }
+}
+
+impl FromStr for SortBy {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        by_name(Self::NAMES, s)
+    }
+}
+
+/// A BCP 47 locale like `de` or `sv-SE` for the collation of strings.
+///
+/// The default is the root locale, which sorts `Müller` next to `Muller`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Locale(icu_locid::Locale);
+
+/// An invalid locale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocaleError(String);
+
+impl fmt::Display for LocaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid locale `{}`", self.0)
+    }
+}
+
+impl std::error::Error for LocaleError {}
+
+impl FromStr for Locale {
+    type Err = LocaleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Locale).map_err(|_| LocaleError(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Compares strings according to the Unicode collation rules of a locale.
+pub struct Collator(icu_collator::Collator);
+
+impl Collator {
+    pub fn new(locale: &Locale) -> Self {
+        let options = CollatorOptions::new();
+        // The compiled data falls back to the root collation for unknown locales
+        let collator = icu_collator::Collator::try_new(&(&locale.0).into(), options)
+            .expect("compiled collation data");
+        Collator(collator)
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.0.compare(a, b)
+    }
+}
+
+/// The sort key of an entry like biblatex would compute it for `nty`.
+pub fn biblatex_key(entry: &SRAEntry) -> Vec<String> {
+    let field = |keys: &[&str]| keys.iter().find_map(|k| entry.get(k)).map(str::to_owned);
+
+    let presort = field(&["presort"]).unwrap_or_else(|| "mm".into());
+    if let Some(sortkey) = field(&["sortkey"]) {
+        return vec![presort, sortkey];
+    }
+
+    let names = names(entry);
+    let names = (!names.is_empty()).then(|| names.join(" "));
+    let name = field(&["sortname"])
+        .or(names)
+        .or_else(|| field(&["sorttitle", "title"]))
+        .unwrap_or_default();
+    let title = field(&["sorttitle", "title"]).unwrap_or_default();
+    let year = field(&["sortyear", "year"])
+        .or_else(|| entry.year().map(str::to_owned))
+        .unwrap_or_default();
+    vec![presort, name, title, year]
+}
+
+/// The names of the authors, or editors, as `last first` for sorting.
+fn names(entry: &SRAEntry) -> Vec<String> {
+    let persons = match entry.authors.is_empty() {
+        true => &entry.editors,
+        false => &entry.authors,
+    };
+    persons
+        .iter()
+        .map(|p| format!("{} {}", p.last_name, p.first_name))
+        .collect()
+}
+
+/// Compare present values before missing ones.
+fn present_first<T>(a: Option<T>, b: Option<T>, cmp: impl Fn(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sort converted entries into output order. Reversing keeps entries without
+/// a year or author last.
+pub fn sort(entries: &mut [SRAEntry], options: &Options) {
+    let collator = Collator::new(&options.locale);
+    let directed = |o: Ordering| if options.reverse { o.reverse() } else { o };
+    let by_key = |a: &SRAEntry, b: &SRAEntry| {
+        directed(collator.compare(&a.id, &b.id).then_with(|| a.id.cmp(&b.id)))
+    };
+    match options.sort_by {
+        SortBy::Key => entries.sort_by(by_key),
+        SortBy::Year => entries.sort_by(|a, b| {
+            let (a_date, b_date) = (a.date_sort.as_deref(), b.date_sort.as_deref());
+            present_first(a_date.map(date::parts), b_date.map(date::parts), |a, b| {
+                directed(a.cmp(&b))
+            })
+            .then_with(|| by_key(a, b))
+        }),
+        SortBy::Author => entries.sort_by(|a, b| {
+            let (a_names, b_names) = (names(a), names(b));
+            present_first(a_names.first(), b_names.first(), |_, _| {
+                let order = a_names
+                    .iter()
+                    .zip(&b_names)
+                    .map(|(a, b)| collator.compare(a, b))
+                    .find(|o| o.is_ne())
+                    .unwrap_or_else(|| a_names.len().cmp(&b_names.len()));
+                directed(order)
+            })
+            .then_with(|| by_key(a, b))
+        }),
+        SortBy::Type => entries.sort_by(|a, b| {
+            let (a_type, b_type) = (a.entry_type.to_lowercase(), b.entry_type.to_lowercase());
+            directed(a_type.cmp(&b_type)).then_with(|| by_key(a, b))
+        }),
+        SortBy::Biblatex => entries.sort_by(|a, b| {
+            let order = a
+                .sort_key
+                .iter()
+                .zip(&b.sort_key)
+                .map(|(a, b)| collator.compare(a, b))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.sort_key.len().cmp(&b.sort_key.len()));
+            directed(order).then_with(|| by_key(a, b))
+        }),
+    }
+}
+
+/// The spans of the `@string`, `@preamble` and `@comment` blocks in the
+/// text between two entries.
+fn blocks(text: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut cursor = 0;
+    while let Some(at) = text[cursor..].find('@').map(|i| cursor + i) {
+        let after = &text[at + 1..];
+        let name = after.len() - after.trim_start_matches(char::is_alphanumeric).len();
+        let open = at + 1 + name + (after[name..].len() - after[name..].trim_start().len());
+        let mut end = None;
+        if name > 0 && text[open..].starts_with('{') {
+            let mut depth = 0;
+            for (i, c) in text[open..].char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' if depth == 1 => {
+                        end = Some(open + i + 1);
+                        break;
+                    }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        match end {
+            Some(end) => {
+                blocks.push(at..end);
+                cursor = end;
+            }
+            None => cursor = at + 1,
+        }
+    }
+    blocks
+}
+
+/// Reorder the entries of a BibTeX source like [`sort`] orders converted
+/// entries, separated by empty lines.
+///
+/// The text before an entry, like a comment, moves with it. `@string`,
+/// `@preamble` and `@comment` blocks between entries move to the top, after
+/// the text before the first entry, so that macros are still defined before
+/// their use. Of the text before the first entry, only what follows the last
+/// block or empty line moves with it.
+pub fn reorder(src: &str, options: &Options) -> Result<String, ReadError> {
+    let raw = RawBibliography::parse(src)
+        .map_err(|e| ReadError::at(src, e.span.start, e.kind.to_string()))?;
+    let bib = InputFormat::Bibtex.parse(src)?;
+    let position: HashMap<String, usize> = convert(&bib, options)
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| (e.id, i))
+        .collect();
+
+    let mut top = Vec::new();
+    let mut entries = Vec::new();
+    let mut cursor = 0;
+    for (i, e) in raw.entries.iter().enumerate() {
+        let before = &src[cursor..e.span.start];
+        let blocks = blocks(before);
+        let mut rest = String::new();
+        if i == 0 {
+            let head = before.trim_end().rfind("\n\n").unwrap_or(0);
+            let head = blocks.last().map_or(head, |b| head.max(b.end));
+            top.push(before[..head].trim());
+            rest.push_str(&before[head..]);
+        } else {
+            let mut last = 0;
+            for block in blocks {
+                rest.push_str(&before[last..block.start]);
+                top.push(before[block.clone()].trim());
+                last = block.end;
+            }
+            rest.push_str(&before[last..]);
+        }
+        top.retain(|b| !b.is_empty());
+        cursor = pretty::end(src, e);
+        let rest = rest.trim();
+        let text = &src[e.span.start..cursor];
+        let entry = match rest.is_empty() {
+            true => text.to_owned(),
+            false => format!("{rest}\n{text}"),
+        };
+        entries.push((
+            position.get(e.v.key.v).copied().unwrap_or(usize::MAX),
+            entry,
+        ));
+    }
+    // Entries that were not converted stay in source order at the end
+    entries.sort_by_key(|(position, _)| *position);
+
+    let mut parts = Vec::new();
+    if !top.is_empty() {
+        parts.push(top.join("\n"));
+    }
+    parts.extend(entries.into_iter().map(|(_, entry)| entry));
+    let rest = src[cursor..].trim();
+    if !rest.is_empty() {
+        parts.push(rest.to_owned());
+    }
+    Ok(parts.join("\n\n") + "\n")
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::{reorder, Locale, SortBy};
+    use crate::{convert, Options};
+
+    #[test]
+    fn names() {
+        assert_eq!("Year".parse(), Ok(SortBy::Year));
+        let error = "date".parse::<SortBy>().unwrap_err().to_string();
+        assert!(error.ends_with("`key`, `year`, `author`, `type`, `biblatex`"));
+        // The names are the same as on the command line
+        #[cfg(feature = "clap")]
+        for variant in <SortBy as clap::ValueEnum>::value_variants() {
+            let value = clap::ValueEnum::to_possible_value(variant).unwrap();
+            assert_eq!(value.get_name().parse(), Ok(*variant));
+        }
+    }
+
+    #[test]
+    fn collation() {
+        let bib = Bibliography::parse(
+            "@misc{Zobel, title={A}}
+             @misc{Öberg, title={B}}
+             @misc{Müller, title={C}}
+             @misc{Oberg, title={D}}
+             @misc{Muller, title={E}}
+             @misc{mayer, title={F}}",
+        )
+        .unwrap();
+
+        let options = Options::default();
+        let keys: Vec<_> = convert(&bib, &options).into_iter().map(|e| e.id).collect();
+        assert_eq!(
+            keys,
+            ["mayer", "Muller", "Müller", "Oberg", "Öberg", "Zobel"]
+        );
+
+        let options = Options {
+            locale: "sv".parse::<Locale>().unwrap(),
+            ..Default::default()
+        };
+        let keys: Vec<_> = convert(&bib, &options).into_iter().map(|e| e.id).collect();
+        assert_eq!(
+            keys,
+            ["mayer", "Muller", "Müller", "Oberg", "Zobel", "Öberg"]
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let bib = Bibliography::parse(
+            "@book{a, author={Zuse, Konrad}, date={1945-03}}
+             @article{b, author={Babbage, Charles}, year=1837}
+             @article{c, editor={Lovelace, Ada}, year=1843}
+             @misc{d, title={Anonymous}}
+             @misc{e, author={Caesar, Gaius Iulius}, date={-0044-03-15}}
+             @misc{f, author={Zeno}, date={-0450}}",
+        )
+        .unwrap();
+        let keys = |sort_by, reverse| {
+            let options = Options {
+                sort_by,
+                reverse,
+                ..Default::default()
+            };
+            let entries = convert(&bib, &options);
+            entries.into_iter().map(|e| e.id).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(SortBy::Year, false), ["f", "e", "b", "c", "a", "d"]);
+        assert_eq!(keys(SortBy::Year, true), ["a", "c", "b", "e", "f", "d"]);
+        assert_eq!(keys(SortBy::Author, false), ["b", "e", "c", "f", "a", "d"]);
+        assert_eq!(keys(SortBy::Author, true), ["a", "f", "c", "e", "b", "d"]);
+        assert_eq!(keys(SortBy::Type, false), ["b", "c", "a", "d", "e", "f"]);
+        assert_eq!(keys(SortBy::Key, true), ["f", "e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn biblatex() {
+        let bib = Bibliography::parse(
+            "@misc{a, author={Zuse, Konrad}, title={Rechenplan}, year=1945}
+             @misc{b, author={Babbage, Charles}, title={Engine}, year=1837}
+             @misc{c, author={Zuse, Konrad}, title={Computer}, year=1970}
+             @misc{d, author={Zuse, Konrad}, title={Plan}, sortname={Aaron}}
+             @misc{e, title={Anonymous}, presort={zz}}",
+        )
+        .unwrap();
+        let options = Options {
+            sort_by: SortBy::Biblatex,
+            ..Default::default()
+        };
+        let entries = convert(&bib, &options);
+        let keys: Vec<_> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(keys, ["d", "b", "c", "a", "e"]);
+        assert_eq!(
+            entries[3].sort_key,
+            ["mm", "Zuse Konrad", "Rechenplan", "1945"]
+        );
+
+        let bib = Bibliography::parse("@misc{c, author={Caesar}, title={Bellum}, date={-0044-03}}")
+            .unwrap();
+        let entries = convert(&bib, &options);
+        assert_eq!(entries[0].sort_key, ["mm", "Caesar ", "Bellum", "-0044"]);
+    }
+
+    #[test]
+    fn source() {
+        let src = "% refs\n@string{j = {J}}\n\n% about b\n@article{b, journal = j, year = 2001}\n@string{k = {K}}\n@misc{a, note = k # {, mail@example.org}, year = 2020}\n% end\n";
+        let options = Options {
+            sort_by: SortBy::Key,
+            ..Default::default()
+        };
+        assert_eq!(
+            reorder(src, &options).unwrap(),
+            "% refs\n@string{j = {J}}\n@string{k = {K}}\n\n@misc{a, note = k # {, mail@example.org}, year = 2020}\n\n% about b\n@article{b, journal = j, year = 2001}\n\n% end\n"
+        );
+        let options = Options {
+            sort_by: SortBy::Year,
+            reverse: true,
+            ..Default::default()
+        };
+        assert!(reorder(src, &options)
+            .unwrap()
+            .contains("2020}\n\n% about b"));
+    }
+}
@@ -0,0 +1,82 @@
+//! Summary of a converted bibliography.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::SRAEntry;
+
+/// Counts of the entries, by type and year, and of the distinct persons.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub entries: usize,
+    pub types: BTreeMap<String, usize>,
+    /// Entries without a date are counted under `unknown`.
+    pub years: BTreeMap<String, usize>,
+    pub authors: usize,
+    pub editors: usize,
+    /// Entries with a `doi` field.
+    pub with_doi: usize,
+}
+
+impl Stats {
+    /// Collect the statistics of the entries.
+    pub fn new(entries: &[SRAEntry]) -> Self {
+        let mut stats = Stats {
+            entries: entries.len(),
+            ..Default::default()
+        };
+        let mut authors = BTreeSet::new();
+        let mut editors = BTreeSet::new();
+        for entry in entries {
+            *stats
+                .types
+                .entry(entry.entry_type.to_lowercase())
+                .or_default() += 1;
+            let year = entry.year().unwrap_or("unknown");
+            *stats.years.entry(year.to_owned()).or_default() += 1;
+            authors.extend(entry.authors.iter().map(|p| p.to_bibtex()));
+            editors.extend(entry.editors.iter().map(|p| p.to_bibtex()));
+            stats.with_doi += usize::from(entry.get("doi").is_some());
+        }
+        stats.authors = authors.len();
+        stats.editors = editors.len();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use biblatex::Bibliography;
+
+    use super::Stats;
+    use crate::{convert, Options};
+
+    #[test]
+    fn counts() {
+        let bib = Bibliography::parse(
+            r#"
+            @article{a, author={Doe, Jane and Roe, Rick}, title={A}, year=2020, doi={10.1/a}}
+            @article{b, author={Doe, Jane}, title={B}, date={2021-03}}
+            @book{c, editor={Roe, Rick}, title={C}}
+            "#,
+        )
+        .unwrap();
+        let stats = Stats::new(&convert(&bib, &Options::default()));
+        assert_eq!(stats.entries, 3);
+        assert_eq!(
+            stats.types,
+            [("article".into(), 2), ("book".into(), 1)].into()
+        );
+        assert_eq!(
+            stats.years,
+            [
+                ("2020".into(), 1),
+                ("2021".into(), 1),
+                ("unknown".into(), 1)
+            ]
+            .into()
+        );
+        assert_eq!((stats.authors, stats.editors, stats.with_doi), (2, 1, 1));
+    }
+}
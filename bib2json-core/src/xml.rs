@@ -0,0 +1,71 @@
+//! Writing of indented XML documents, for the XML output formats.
+
+/// Escape the special characters of XML text and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// An XML document under construction, indented by two spaces per level.
+pub(crate) struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    /// A document with an XML declaration.
+    pub fn new() -> Self {
+        Xml {
+            out: "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".into(),
+            depth: 0,
+        }
+    }
+
+    fn start(&mut self, tag: &str, attrs: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push('<');
+        self.out.push_str(tag);
+        for (name, value) in attrs {
+            self.out.push_str(&format!(" {name}=\"{}\"", escape(value)));
+        }
+    }
+
+    /// Open an element, its content follows on the next lines.
+    pub fn open(&mut self, tag: &str, attrs: &[(&str, &str)]) {
+        self.start(tag, attrs);
+        self.out.push_str(">\n");
+        self.depth += 1;
+    }
+
+    /// Close the innermost open element.
+    pub fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(&format!("</{tag}>\n"));
+    }
+
+    /// An element with text content, or an empty element for empty text.
+    pub fn element(&mut self, tag: &str, attrs: &[(&str, &str)], text: &str) {
+        self.start(tag, attrs);
+        if text.is_empty() {
+            self.out.push_str("/>\n");
+        } else {
+            self.out.push_str(&format!(">{}</{tag}>\n", escape(text)));
+        }
+    }
+
+    /// The document, all elements have to be closed.
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
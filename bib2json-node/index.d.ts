@@ -0,0 +1,10 @@
+/* auto-generated by NAPI-RS */
+/* eslint-disable */
+
+/** Parse a BibTeX string into an object of entries keyed by citation key. */
+export function loads(content: string): any
+/**
+ * Read a bibliography file, the format is inferred from the extension or content,
+ * the encoding from the bytes.
+ */
+export function load(path: string): any
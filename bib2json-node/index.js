@@ -0,0 +1,51 @@
+// Loads the native addon built by `napi build --platform`, which is named
+// after the platform like `bib2json.linux-x64-gnu.node`.
+
+const { existsSync, readFileSync } = require('fs')
+const { join } = require('path')
+
+const { platform, arch } = process
+
+function isMusl() {
+  if (!process.report || typeof process.report.getReport !== 'function') {
+    try {
+      return readFileSync('/usr/bin/ldd', 'utf8').includes('musl')
+    } catch (e) {
+      return true
+    }
+  }
+  const { glibcVersionRuntime } = process.report.getReport().header
+  return !glibcVersionRuntime
+}
+
+function triple() {
+  switch (platform) {
+    case 'win32':
+      return `win32-${arch}-msvc`
+    case 'darwin':
+      return `darwin-${arch}`
+    case 'linux':
+      if (arch === 'arm') {
+        return 'linux-arm-gnueabihf'
+      }
+      return `linux-${arch}-${isMusl() ? 'musl' : 'gnu'}`
+    default:
+      return `${platform}-${arch}`
+  }
+}
+
+const candidates = [`bib2json.${triple()}.node`, 'bib2json.node']
+if (platform === 'darwin') {
+  candidates.unshift('bib2json.darwin-universal.node')
+}
+const file = candidates.find((name) => existsSync(join(__dirname, name)))
+if (!file) {
+  throw new Error(
+    `bib2json: no native addon for ${platform}-${arch}, build it with \`npm run build\``,
+  )
+}
+
+const { load, loads } = require(join(__dirname, file))
+
+module.exports.load = load
+module.exports.loads = loads
@@ -0,0 +1,34 @@
+//! Node.js bindings: `loads(content)` and `load(path)` return the same
+//! structure as the JSON output of the command line tool, as JavaScript objects.
+
+use std::path::Path;
+
+use bib2json_core::input::{self, InputFormat};
+use bib2json_core::SRABib;
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::Value;
+
+/// Parse a bibliography and convert it into an object of entries keyed by citation key.
+fn convert(format: InputFormat, content: &str) -> Result<Value> {
+    let bib = format
+        .parse(content)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    serde_json::to_value(SRABib::new(&bib)).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Parse a BibTeX string into an object of entries keyed by citation key.
+#[napi]
+pub fn loads(content: String) -> Result<Value> {
+    convert(InputFormat::Bibtex, &content)
+}
+
+/// Read a bibliography file, the format is inferred from the extension or content,
+/// the encoding from the bytes.
+#[napi]
+pub fn load(path: String) -> Result<Value> {
+    let content = input::read(Path::new(&path)).map_err(|e| Error::from_reason(e.to_string()))?;
+    let format =
+        InputFormat::from_path(Path::new(&path)).unwrap_or_else(|| InputFormat::detect(&content));
+    convert(format, &content).map_err(|e| Error::from_reason(format!("{path}:{}", e.reason)))
+}
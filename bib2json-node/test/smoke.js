@@ -0,0 +1,28 @@
+// Smoke test of the native addon, run with `npm test` after `npm run build`.
+
+const assert = require('assert')
+const { mkdtempSync, writeFileSync } = require('fs')
+const { tmpdir } = require('os')
+const { join } = require('path')
+const { gzipSync } = require('zlib')
+
+const { load, loads } = require('..')
+
+const bib = '@article{doe, author = {Doe, John}, title = {The {RISC} Way}, year = 2020}'
+
+const entries = loads(bib)
+assert.deepStrictEqual(Object.keys(entries), ['doe'])
+assert.strictEqual(entries.doe.title, 'The RISC Way')
+assert.strictEqual(entries.doe.entry_type, 'article')
+assert.deepStrictEqual(entries.doe.authors, [{ first_name: 'John', last_name: 'Doe' }])
+
+const dir = mkdtempSync(join(tmpdir(), 'bib2json-'))
+writeFileSync(join(dir, 'refs.bib'), bib)
+assert.deepStrictEqual(load(join(dir, 'refs.bib')), entries)
+writeFileSync(join(dir, 'refs.bib.gz'), gzipSync(bib))
+assert.deepStrictEqual(load(join(dir, 'refs.bib.gz')), entries)
+
+assert.throws(() => loads('@article{doe, title = {Open'), /doe|expected|unexpected/i)
+assert.throws(() => load(join(dir, 'missing.bib')), /missing\.bib/)
+
+console.log('ok')
@@ -0,0 +1,130 @@
+"""Fast parsing of biblatex into Python objects."""
+
+import os
+from typing import (
+    Any,
+    Dict,
+    Iterable,
+    Iterator,
+    List,
+    Literal,
+    Mapping,
+    Optional,
+    Tuple,
+    TypeVar,
+    Union,
+    overload,
+)
+
+from typing_extensions import NotRequired, TypedDict, Unpack
+
+_T = TypeVar("_T")
+
+class Person(TypedDict):
+    """An author or editor."""
+
+    first_name: str
+    last_name: str
+    display_name: NotRequired[str]
+
+class Entry:
+    """A converted entry. Its fields are attributes, like `entry.title`, and
+    items, like `entry["title"]`."""
+
+    @property
+    def id(self) -> str:
+        """The citation key."""
+    @property
+    def entry_type(self) -> str: ...
+    @property
+    def authors(self) -> List[Person]:
+        """The authors as dicts with `first_name` and `last_name`."""
+    @property
+    def editors(self) -> List[Person]:
+        """The editors as dicts with `first_name` and `last_name`."""
+    @property
+    def bibtex(self) -> str:
+        """The BibTeX source of the entry."""
+    def __getattr__(self, name: str) -> Any: ...
+    def __getitem__(self, key: str) -> Any: ...
+    def __setitem__(self, key: str, value: Any) -> None: ...
+    def __delitem__(self, key: str) -> None: ...
+    def __contains__(self, key: object) -> bool: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[str]: ...
+    @overload
+    def get(self, key: str) -> Optional[Any]: ...
+    @overload
+    def get(self, key: str, default: _T) -> Union[Any, _T]: ...
+    def keys(self) -> List[str]: ...
+    def values(self) -> List[Any]: ...
+    def items(self) -> List[Tuple[str, Any]]: ...
+    def to_dict(self) -> Dict[str, Any]:
+        """The entry as a dict, like the JSON output of the command line tool."""
+
+class Bibliography:
+    """Converted entries keyed by citation key, in output order. The entries
+    become `Entry` objects only when they are accessed."""
+
+    def __getitem__(self, key: str) -> Entry: ...
+    def __contains__(self, key: object) -> bool: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[str]: ...
+    @overload
+    def get(self, key: str) -> Optional[Entry]: ...
+    @overload
+    def get(self, key: str, default: _T) -> Union[Entry, _T]: ...
+    def keys(self) -> List[str]: ...
+    def values(self) -> List[Entry]: ...
+    def items(self) -> List[Tuple[str, Entry]]: ...
+
+class Options(TypedDict, total=False):
+    """The conversion options of `loads` and `load`, named like the fields of
+    the conversion options of the command line tool."""
+
+    inherited: bool
+    name_format: Optional[str]
+    locale: str
+    sort_by: Literal["key", "year", "author", "type", "biblatex"]
+    reverse: bool
+    truncate: Mapping[str, int]
+    clean_abstract: bool
+    escape: Literal["none", "html"]
+    keys: Optional[List[str]]
+    types: List[str]
+    from_year: Optional[int]
+    to_year: Optional[int]
+    author: Optional[str]
+    keywords: List[str]
+    any_keyword: bool
+    query: Optional[str]
+    fields: List[str]
+    exclude_fields: List[str]
+    redact: List[str]
+    anonymize: bool
+    offset: int
+    limit: Optional[int]
+    doi_url: bool
+    crossref_depth: Optional[int]
+    require: List[str]
+    keep_incomplete: bool
+    missing: bool
+    jobs: int
+    include_bibtex: bool
+
+def loads(content: str, **options: Unpack[Options]) -> Bibliography:
+    """Parse a BibTeX string into a `Bibliography`, with the conversion
+    options as keyword arguments."""
+
+def load(path: Union[str, "os.PathLike[str]"], **options: Unpack[Options]) -> Bibliography:
+    """Read a bibliography file from a `str` or `os.PathLike` path, the format
+    is inferred from the extension or content, the encoding from the bytes,
+    gzip-compressed files are decompressed."""
+
+_Fields = Union[Entry, Mapping[str, Any]]
+
+def dumps(
+    entries: Union[Bibliography, Mapping[str, _Fields], Iterable[_Fields]],
+) -> str:
+    """Write entries as BibTeX from their fields, either a `Bibliography`, a
+    dict keyed by citation key or a list of entries."""
@@ -0,0 +1,571 @@
+//! Python bindings: `bib2.loads(content)` and `bib2.load(path)` return the
+//! same structure as the JSON output of the command line tool, as a
+//! `bib2.Bibliography` mapping of `bib2.Entry` objects, `bib2.dumps(entries)`
+//! turns it back into BibTeX.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bib2json_core::escape::Escape;
+use bib2json_core::input::{self, InputFormat};
+use bib2json_core::names::NameFormat;
+use bib2json_core::query::Query;
+use bib2json_core::sort::{Locale, SortBy};
+use bib2json_core::{Options, SRABib, SRAEntry, PRIVATE_FIELDS};
+use pyo3::exceptions::{PyAttributeError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// Convert a JSON value into the corresponding Python object.
+fn to_py(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into_pyobject(py)?.into_any().unbind(),
+            None => n.as_f64().into_pyobject(py)?.into_any().unbind(),
+        },
+        Value::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(values) => {
+            let list = PyList::empty(py);
+            for v in values {
+                list.append(to_py(py, v)?)?;
+            }
+            list.into_any().unbind()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, to_py(py, v)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+/// Convert a Python object into the corresponding JSON value.
+fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    Ok(if obj.is_none() {
+        Value::Null
+    } else if let Ok(b) = obj.downcast::<PyBool>() {
+        Value::Bool(b.is_true())
+    } else if let Ok(i) = obj.downcast::<PyInt>() {
+        Value::from(i.extract::<i64>()?)
+    } else if let Ok(f) = obj.downcast::<PyFloat>() {
+        Value::from(f.value())
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        Value::String(s.to_str()?.to_owned())
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (k, v) in dict {
+            map.insert(k.extract()?, from_py(&v)?);
+        }
+        Value::Object(map)
+    } else if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>() {
+        let values: PyResult<Vec<Value>> = obj.try_iter()?.map(|v| from_py(&v?)).collect();
+        Value::Array(values?)
+    } else {
+        let type_name = obj.get_type().name()?;
+        return Err(PyTypeError::new_err(format!(
+            "cannot convert `{type_name}` to BibTeX"
+        )));
+    })
+}
+
+/// A converted entry. Its fields are attributes, like `entry.title`, and
+/// items, like `entry["title"]`.
+#[pyclass(mapping, module = "bib2")]
+struct Entry {
+    fields: Map<String, Value>,
+}
+
+impl Entry {
+    fn field(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        match self.fields.get(name) {
+            Some(value) => to_py(py, value),
+            None => Err(PyKeyError::new_err(name.to_owned())),
+        }
+    }
+
+    fn string(&self, name: &str) -> String {
+        self.fields
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+#[pymethods]
+impl Entry {
+    /// The citation key.
+    #[getter]
+    fn id(&self) -> String {
+        self.string("id")
+    }
+
+    #[getter]
+    fn entry_type(&self) -> String {
+        self.string("entry_type")
+    }
+
+    /// The authors as dicts with `first_name` and `last_name`.
+    #[getter]
+    fn authors(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.field(py, "authors")
+    }
+
+    /// The editors as dicts with `first_name` and `last_name`.
+    #[getter]
+    fn editors(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.field(py, "editors")
+    }
+
+    /// The BibTeX source of the entry.
+    #[getter]
+    fn bibtex(&self) -> String {
+        self.string("bibtex")
+    }
+
+    fn __getattr__(&self, py: Python<'_>, name: &str) -> PyResult<PyObject> {
+        self.field(py, name).map_err(|_| {
+            PyAttributeError::new_err(format!("entry `{}` has no field `{name}`", self.id()))
+        })
+    }
+
+    fn __getitem__(&self, py: Python<'_>, key: &str) -> PyResult<PyObject> {
+        self.field(py, key)
+    }
+
+    fn __setitem__(&mut self, key: String, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.fields.insert(key, from_py(value)?);
+        Ok(())
+    }
+
+    fn __delitem__(&mut self, key: &str) -> PyResult<()> {
+        match self.fields.remove(key) {
+            Some(_) => Ok(()),
+            None => Err(PyKeyError::new_err(key.to_owned())),
+        }
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    fn __len__(&self) -> usize {
+        self.fields.len()
+    }
+
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let keys = PyList::new(py, self.fields.keys())?;
+        Ok(keys.try_iter()?.into_any().unbind())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Entry(id='{}', entry_type='{}')",
+            self.id(),
+            self.entry_type()
+        )
+    }
+
+    /// The value of a field, `default` if the entry does not have it.
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        match self.fields.get(key) {
+            Some(value) => to_py(py, value),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    fn values(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.fields.values().map(|v| to_py(py, v)).collect()
+    }
+
+    fn items(&self, py: Python<'_>) -> PyResult<Vec<(String, PyObject)>> {
+        self.fields
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), to_py(py, v)?)))
+            .collect()
+    }
+
+    /// The entry as a dict, like the JSON output of the command line tool.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &Value::Object(self.fields.clone()))
+    }
+}
+
+/// A converted entry as an [`Entry`].
+fn to_entry(entry: &SRAEntry) -> PyResult<Entry> {
+    match serde_json::to_value(entry).map_err(|e| PyValueError::new_err(e.to_string()))? {
+        Value::Object(mut fields) => {
+            if entry.bibtex.is_empty() {
+                fields.remove("bibtex");
+            }
+            Ok(Entry { fields })
+        }
+        _ => unreachable!("entries are serialized as objects"),
+    }
+}
+
+/// Converted entries keyed by citation key, in output order. The entries
+/// become [`Entry`] objects only when they are accessed.
+#[pyclass(mapping, module = "bib2")]
+struct Bibliography {
+    bib: SRABib,
+    /// The entries accessed so far, by index, so that changes to them are kept.
+    accessed: Vec<Option<Py<Entry>>>,
+}
+
+impl Bibliography {
+    fn new(bib: SRABib) -> Self {
+        let accessed = (0..bib.entries.len()).map(|_| None).collect();
+        Bibliography { bib, accessed }
+    }
+
+    fn entry(&mut self, py: Python<'_>, index: usize) -> PyResult<Py<Entry>> {
+        if let Some(entry) = &self.accessed[index] {
+            return Ok(entry.clone_ref(py));
+        }
+        let entry = Py::new(py, to_entry(&self.bib.entries[index])?)?;
+        self.accessed[index] = Some(entry.clone_ref(py));
+        Ok(entry)
+    }
+}
+
+#[pymethods]
+impl Bibliography {
+    fn __getitem__(&mut self, py: Python<'_>, key: &str) -> PyResult<Py<Entry>> {
+        match self.bib.entries.get_index_of(key) {
+            Some(index) => self.entry(py, index),
+            None => Err(PyKeyError::new_err(key.to_owned())),
+        }
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.bib.entries.contains_key(key)
+    }
+
+    fn __len__(&self) -> usize {
+        self.bib.entries.len()
+    }
+
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let keys = PyList::new(py, self.bib.entries.keys())?;
+        Ok(keys.try_iter()?.into_any().unbind())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Bibliography({} entries)", self.bib.entries.len())
+    }
+
+    /// The entry with the key, `default` if there is none.
+    #[pyo3(signature = (key, default = None))]
+    fn get(&mut self, py: Python<'_>, key: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        match self.bib.entries.get_index_of(key) {
+            Some(index) => Ok(self.entry(py, index)?.into_any()),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.bib.entries.keys().cloned().collect()
+    }
+
+    fn values(&mut self, py: Python<'_>) -> PyResult<Vec<Py<Entry>>> {
+        (0..self.accessed.len())
+            .map(|index| self.entry(py, index))
+            .collect()
+    }
+
+    fn items(&mut self, py: Python<'_>) -> PyResult<Vec<(String, Py<Entry>)>> {
+        let keys = self.keys();
+        keys.into_iter()
+            .enumerate()
+            .map(|(index, key)| Ok((key, self.entry(py, index)?)))
+            .collect()
+    }
+}
+
+/// An entry from an [`Entry`] or its dict.
+fn entry(obj: &Bound<'_, PyAny>) -> PyResult<SRAEntry> {
+    let mut value = match obj.downcast::<Entry>() {
+        Ok(entry) => Value::Object(entry.borrow().fields.clone()),
+        Err(_) => from_py(obj)?,
+    };
+    if let Value::Object(fields) = &mut value {
+        stringify(fields);
+        if let Some(Value::Object(inherited)) = fields.get_mut("inherited") {
+            stringify(inherited);
+        }
+    }
+    serde_json::from_value(value).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Numbers and booleans as strings, as BibTeX field values like
+/// `entry["year"] = 2021` are.
+fn stringify(fields: &mut Map<String, Value>) {
+    for value in fields.values_mut() {
+        if let Value::Number(_) | Value::Bool(_) = value {
+            *value = Value::String(value.to_string());
+        }
+    }
+}
+
+/// An option value parsed from a string, like `sort_by="year"`.
+fn parsed<T: FromStr>(value: &Bound<'_, PyAny>) -> PyResult<T>
+where
+    T::Err: fmt::Display,
+{
+    let value: String = value.extract()?;
+    value
+        .parse()
+        .map_err(|e: T::Err| PyValueError::new_err(e.to_string()))
+}
+
+/// The keyword arguments of `loads` and `load`: the conversion options and
+/// whether to keep the `bibtex` source of the entries.
+struct Settings {
+    options: Options,
+    include_bibtex: bool,
+}
+
+/// Sets a keyword argument.
+type Setter = fn(&mut Settings, &Bound<'_, PyAny>) -> PyResult<()>;
+
+/// The keyword arguments, named like the fields of [`Options`] and its
+/// [`Filter`](bib2json_core::filter::Filter).
+const KWARGS: &[(&str, Setter)] = &[
+    ("inherited", |s, v| {
+        s.options.inherited = v.extract()?;
+        Ok(())
+    }),
+    ("name_format", |s, v| {
+        let format: Option<Bound<'_, PyAny>> = v.extract()?;
+        s.options.name_format = format.map(|f| parsed::<NameFormat>(&f)).transpose()?;
+        Ok(())
+    }),
+    ("locale", |s, v| {
+        s.options.locale = parsed::<Locale>(v)?;
+        Ok(())
+    }),
+    ("sort_by", |s, v| {
+        s.options.sort_by = parsed::<SortBy>(v)?;
+        Ok(())
+    }),
+    ("reverse", |s, v| {
+        s.options.reverse = v.extract()?;
+        Ok(())
+    }),
+    ("truncate", |s, v| {
+        s.options.truncate = v.extract()?;
+        Ok(())
+    }),
+    ("clean_abstract", |s, v| {
+        s.options.clean_abstract = v.extract()?;
+        Ok(())
+    }),
+    ("escape", |s, v| {
+        s.options.escape = parsed::<Escape>(v)?;
+        Ok(())
+    }),
+    ("keys", |s, v| {
+        let keys: Option<Vec<String>> = v.extract()?;
+        s.options.keys = keys.map(|keys| keys.into_iter().collect());
+        Ok(())
+    }),
+    ("types", |s, v| {
+        s.options.filter.types = v.extract()?;
+        Ok(())
+    }),
+    ("from_year", |s, v| {
+        s.options.filter.from_year = v.extract()?;
+        Ok(())
+    }),
+    ("to_year", |s, v| {
+        s.options.filter.to_year = v.extract()?;
+        Ok(())
+    }),
+    ("author", |s, v| {
+        let author: Option<Bound<'_, PyAny>> = v.extract()?;
+        s.options.filter.author = author.map(|a| parsed::<Regex>(&a)).transpose()?;
+        Ok(())
+    }),
+    ("keywords", |s, v| {
+        s.options.filter.keywords = v.extract()?;
+        Ok(())
+    }),
+    ("any_keyword", |s, v| {
+        s.options.filter.any_keyword = v.extract()?;
+        Ok(())
+    }),
+    ("query", |s, v| {
+        let query: Option<Bound<'_, PyAny>> = v.extract()?;
+        s.options.filter.query = query.map(|q| parsed::<Query>(&q)).transpose()?;
+        Ok(())
+    }),
+    ("fields", |s, v| {
+        s.options.fields = v.extract()?;
+        Ok(())
+    }),
+    ("exclude_fields", |s, v| {
+        s.options.exclude_fields = v.extract()?;
+        Ok(())
+    }),
+    ("redact", |s, v| {
+        s.options.redact.extend(v.extract::<Vec<String>>()?);
+        Ok(())
+    }),
+    ("anonymize", |s, v| {
+        if v.extract()? {
+            let private = PRIVATE_FIELDS.iter().map(|f| f.to_string());
+            s.options.redact.extend(private);
+        }
+        Ok(())
+    }),
+    ("offset", |s, v| {
+        s.options.offset = v.extract()?;
+        Ok(())
+    }),
+    ("limit", |s, v| {
+        s.options.limit = v.extract()?;
+        Ok(())
+    }),
+    ("doi_url", |s, v| {
+        s.options.doi_url = v.extract()?;
+        Ok(())
+    }),
+    ("crossref_depth", |s, v| {
+        s.options.crossref_depth = v.extract()?;
+        Ok(())
+    }),
+    ("require", |s, v| {
+        let fields: Vec<String> = v.extract()?;
+        s.options.require = fields.iter().map(|f| f.to_lowercase()).collect();
+        Ok(())
+    }),
+    ("keep_incomplete", |s, v| {
+        s.options.keep_incomplete = v.extract()?;
+        Ok(())
+    }),
+    ("missing", |s, v| {
+        s.options.missing = v.extract()?;
+        Ok(())
+    }),
+    ("jobs", |s, v| {
+        s.options.jobs = v.extract()?;
+        Ok(())
+    }),
+    ("include_bibtex", |s, v| {
+        s.include_bibtex = v.extract()?;
+        Ok(())
+    }),
+];
+
+impl Settings {
+    fn new(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut settings = Settings {
+            options: Options::default(),
+            include_bibtex: true,
+        };
+        for (key, value) in kwargs.into_iter().flatten() {
+            let key: String = key.extract()?;
+            let Some((_, set)) = KWARGS.iter().find(|(name, _)| *name == key) else {
+                return Err(PyTypeError::new_err(format!(
+                    "unexpected keyword argument '{key}'"
+                )));
+            };
+            set(&mut settings, &value).map_err(|e| {
+                let error = e.value(value.py());
+                PyErr::from_type(error.get_type(), format!("{key}: {error}"))
+            })?;
+        }
+        Ok(settings)
+    }
+}
+
+/// Parse and convert a bibliography.
+fn convert(format: InputFormat, content: &str, settings: &Settings) -> PyResult<Bibliography> {
+    let bib = format
+        .parse(content)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let mut bib = SRABib::with_options(&bib, &settings.options);
+    if !settings.include_bibtex {
+        for entry in bib.entries.values_mut() {
+            entry.bibtex = String::new();
+        }
+    }
+    Ok(Bibliography::new(bib))
+}
+
+/// Parse a BibTeX string into a [`Bibliography`], with the conversion
+/// options as keyword arguments.
+#[pyfunction]
+#[pyo3(signature = (content, **options))]
+fn loads(content: &str, options: Option<&Bound<'_, PyDict>>) -> PyResult<Bibliography> {
+    convert(InputFormat::Bibtex, content, &Settings::new(options)?)
+}
+
+/// Read a bibliography file from a `str` or `os.PathLike` path, the format
+/// is inferred from the extension or content, the encoding from the bytes,
+/// gzip-compressed files are decompressed.
+#[pyfunction]
+#[pyo3(signature = (path, **options))]
+fn load(
+    py: Python<'_>,
+    path: PathBuf,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Bibliography> {
+    let settings = Settings::new(options)?;
+    let content = input::read(&path)?;
+    let format = InputFormat::from_path(&path).unwrap_or_else(|| InputFormat::detect(&content));
+    convert(format, &content, &settings)
+        .map_err(|e| PyValueError::new_err(format!("{}:{}", path.display(), e.value(py))))
+}
+
+/// Write entries as BibTeX from their fields, either a [`Bibliography`], a
+/// dict keyed by citation key or a list of entries.
+#[pyfunction]
+fn dumps(py: Python<'_>, entries: &Bound<'_, PyAny>) -> PyResult<String> {
+    let entries: Vec<(String, SRAEntry)> = if let Ok(bib) = entries.downcast::<Bibliography>() {
+        let bib = bib.borrow();
+        bib.bib
+            .entries
+            .iter()
+            .zip(&bib.accessed)
+            .map(|((key, e), accessed)| match accessed {
+                Some(accessed) => Ok((key.clone(), entry(accessed.bind(py))?)),
+                None => Ok((key.clone(), e.clone())),
+            })
+            .collect::<PyResult<_>>()?
+    } else if let Ok(dict) = entries.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(key, value)| Ok((key.extract()?, entry(&value)?)))
+            .collect::<PyResult<_>>()?
+    } else {
+        entries
+            .try_iter()?
+            .map(|value| entry(&value?).map(|e| (e.id.clone(), e)))
+            .collect::<PyResult<_>>()?
+    };
+    let bib = input::from_entries(entries);
+    let bibtex: Vec<String> = bib.iter().map(|e| e.to_biblatex_string()).collect();
+    Ok(bibtex.join("\n\n") + "\n")
+}
+
+#[pymodule]
+fn bib2(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Entry>()?;
+    m.add_class::<Bibliography>()?;
+    m.add_function(wrap_pyfunction!(loads, m)?)?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps, m)?)
+}
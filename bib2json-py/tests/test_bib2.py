@@ -0,0 +1,152 @@
+import gzip
+
+import pytest
+
+import bib2
+
+BIB = r"""
+@article{doe,
+  author = {Doe, John and Roe, Jane},
+  title = {The {RISC} Way},
+  journal = {Journal},
+  year = 2020,
+  pages = {1--10},
+  keywords = {os, risc},
+}
+
+@book{smith,
+  author = {Smith, Anna},
+  title = {Operating Systems},
+  publisher = {Press},
+  year = 1999,
+}
+
+@inproceedings{lee,
+  author = {Lee, Kim},
+  title = {Kernels},
+  booktitle = {Proceedings},
+  year = 2010,
+}
+"""
+
+
+def test_loads():
+    bib = bib2.loads(BIB)
+    assert list(bib) == ["doe", "lee", "smith"]
+    assert len(bib) == 3
+    assert "doe" in bib and "nobody" not in bib
+    assert bib.get("nobody") is None
+    assert bib.get("nobody", 1) == 1
+
+
+def test_load(tmp_path):
+    path = tmp_path / "refs.bib"
+    path.write_text(BIB)
+    assert list(bib2.load(path)) == ["doe", "lee", "smith"]
+    assert list(bib2.load(str(path))) == ["doe", "lee", "smith"]
+
+
+def test_load_gzip(tmp_path):
+    path = tmp_path / "refs.bib.gz"
+    path.write_bytes(gzip.compress(BIB.encode()))
+    assert list(bib2.load(path)) == ["doe", "lee", "smith"]
+
+
+def test_load_encoding(tmp_path):
+    path = tmp_path / "refs.bib"
+    path.write_bytes("@misc{q, title = {“Quoted” Müller}}".encode("cp1252"))
+    assert bib2.load(path)["q"].title == "“Quoted” Müller"
+
+
+def test_load_missing(tmp_path):
+    with pytest.raises(OSError):
+        bib2.load(tmp_path / "missing.bib")
+
+
+def test_entry():
+    entry = bib2.loads(BIB)["doe"]
+    assert entry.id == "doe"
+    assert entry.entry_type == "article"
+    assert entry.title == "The RISC Way"
+    assert entry["title"] == entry.title
+    assert entry.year == "2020"
+    assert entry.pages == "1–10"
+    assert [a["last_name"] for a in entry.authors] == ["Doe", "Roe"]
+    assert entry.editors == []
+    assert entry.bibtex.startswith("@article{doe,")
+    assert "journal" in entry
+    assert entry.get("volume") is None
+    assert dict(entry.items()) == entry.to_dict()
+    assert set(entry.keys()) == set(entry)
+    assert len(entry) == len(entry.keys())
+    with pytest.raises(AttributeError):
+        entry.volume
+    with pytest.raises(KeyError):
+        entry["volume"]
+
+
+def test_entry_edit():
+    entry = bib2.loads(BIB)["doe"]
+    entry["note"] = "Draft"
+    del entry["pages"]
+    assert entry.note == "Draft"
+    assert "pages" not in entry
+    with pytest.raises(KeyError):
+        del entry["volume"]
+
+
+def test_lazy():
+    bib = bib2.loads(BIB)
+    first = bib["doe"]
+    first["note"] = "Draft"
+    assert bib["doe"] is first
+    assert bib["doe"].note == "Draft"
+    assert [e.id for e in bib.values()] == list(bib.keys())
+    assert [k for k, _ in bib.items()] == list(bib)
+    with pytest.raises(KeyError):
+        bib["nobody"]
+
+
+def test_kwargs():
+    assert list(bib2.loads(BIB, sort_by="year")) == ["smith", "lee", "doe"]
+    assert list(bib2.loads(BIB, sort_by="year", reverse=True)) == ["doe", "lee", "smith"]
+    assert list(bib2.loads(BIB, keys=["lee", "doe"])) == ["doe", "lee"]
+    assert list(bib2.loads(BIB, from_year=2000)) == ["doe", "lee"]
+    assert list(bib2.loads(BIB, types=["book"])) == ["smith"]
+    assert list(bib2.loads(BIB, keywords=["risc"])) == ["doe"]
+    assert list(bib2.loads(BIB, author="^Smith")) == ["smith"]
+    assert list(bib2.loads(BIB, query="year < 2015")) == ["lee", "smith"]
+    assert list(bib2.loads(BIB, offset=1, limit=1)) == ["lee"]
+    assert list(bib2.loads(BIB, require=["Journal"])) == ["doe"]
+    assert list(bib2.loads(BIB, fields=["title"])["doe"]) == ["authors", "editors", "entry_type", "id", "title"]
+    assert "title" not in bib2.loads(BIB, exclude_fields=["title"])["doe"]
+    assert bib2.loads(BIB, include_bibtex=False)["doe"].bibtex == ""
+
+
+def test_kwargs_invalid():
+    with pytest.raises(TypeError, match="unexpected keyword argument 'colour'"):
+        bib2.loads(BIB, colour=True)
+    with pytest.raises(ValueError, match="sort_by"):
+        bib2.loads(BIB, sort_by="colour")
+    with pytest.raises(TypeError, match="offset"):
+        bib2.loads(BIB, offset="one")
+
+
+def test_dumps():
+    bib = bib2.loads(BIB)
+    doe = bib2.loads(bib2.dumps(bib))["doe"]
+    assert (doe.title, doe.pages, doe.authors) == ("The RISC Way", "1–10", bib["doe"].authors)
+    assert "title = {The {RISC} Way}" in bib2.dumps(bib)
+    assert "pages = {1--10}" in bib2.dumps(bib)
+
+
+def test_dumps_edited():
+    bib = bib2.loads(BIB)
+    bib["doe"]["note"] = "Draft"
+    assert "note = {Draft}" in bib2.dumps(bib)
+
+
+def test_dumps_dicts():
+    entry = {"id": "k", "entry_type": "misc", "title": "X", "year": 2001}
+    assert bib2.dumps([entry]) == "@misc{k,\ntitle = {X},\nyear = {2001},\n}\n"
+    assert bib2.dumps({"k": entry}) == bib2.dumps([entry])